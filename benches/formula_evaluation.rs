@@ -0,0 +1,60 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use glam::Vec3;
+
+use bevy_graph_sim::{
+    formula_legacy::{translate_nodes, LegacyParser},
+    parsing_function::FormulaParser,
+};
+
+const POINT_COUNT: usize = 100_000;
+const FORMULA: &str = "sin(x - time) * 10 + cos(z / 4) * abs(x - z) / 3 - 2 ^ 3";
+
+fn points() -> Vec<Vec3> {
+    (0..POINT_COUNT)
+        .map(|i| Vec3::new((i % 316) as f32 * 12., 0., (i / 316) as f32 * 12.))
+        .collect()
+}
+
+fn evaluation_backends(c: &mut Criterion) {
+    let parser = FormulaParser::new();
+    let points = points();
+    let tree = parser.parse_tree(FORMULA).unwrap();
+    let program = parser.compile(FORMULA).unwrap();
+
+    let nodes = LegacyParser::new().parse_nodes(FORMULA).unwrap();
+
+    let mut group = c.benchmark_group("evaluate_100k_points");
+    group.bench_function("translate_nodes", |b| b.iter(|| {
+        let mut sum = 0.;
+        for point in points.iter() {
+            sum += translate_nodes(&nodes, black_box(1.5), *point).unwrap();
+        }
+        sum
+    }));
+    group.bench_function("tree_walk", |b| b.iter(|| {
+        let mut sum = 0.;
+        for point in points.iter() {
+            sum += tree.evaluate(black_box(1.5), *point);
+        }
+        sum
+    }));
+    group.bench_function("bytecode", |b| b.iter(|| {
+        let mut sum = 0.;
+        for point in points.iter() {
+            sum += program.run(black_box(1.5), *point);
+        }
+        sum
+    }));
+    let xs: Vec<f32> = points.iter().map(|point| point.x).collect();
+    let ys: Vec<f32> = points.iter().map(|point| point.y).collect();
+    let zs: Vec<f32> = points.iter().map(|point| point.z).collect();
+    let mut output = vec![0.; POINT_COUNT];
+    group.bench_function("bytecode_batch", |b| b.iter(|| {
+        program.run_batch(black_box(1.5), &xs, &ys, &zs, &mut output);
+        output.iter().sum::<f32>()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, evaluation_backends);
+criterion_main!(benches);
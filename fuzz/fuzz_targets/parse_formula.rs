@@ -0,0 +1,33 @@
+//! Feeds arbitrary text through `FormulaParser::parse` and every evaluator, which must never panic.
+//! Run with `cargo fuzz run parse_formula` from the repository root.
+
+#![no_main]
+
+use bevy_graph_sim::{
+    formula_interval::{evaluate_interval, Interval},
+    formula_wgsl::generate_expression,
+    parsing_function::FormulaParser,
+};
+use glam::Vec3;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    for implicit_multiplication in [false, true] {
+        let parser = FormulaParser { implicit_multiplication, ..FormulaParser::default() };
+        let _ = (parser.parse(text))(1., Vec3::new(1., 2., 3.));
+        if let Ok(expr) = parser.parse_tree(text) {
+            let _ = expr.to_string();
+            let _ = generate_expression(&expr);
+            let _ = evaluate_interval(&expr, Interval::new(0., 10.), Interval::ENTIRE, Interval::new(-1., 1.), Interval::point(0.));
+            if let Some(program) = parser.parse_formula(text).program {
+                let xs = [0., 1., -1., f32::NAN, f32::INFINITY, 1e30, -1e-30, 7.5, 3.];
+                let mut output = [0.; 9];
+                program.run_batch(2., &xs, &xs, &xs, &mut output);
+            }
+        }
+    }
+});
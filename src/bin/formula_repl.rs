@@ -0,0 +1,161 @@
+//! Reads formulas from the terminal and prints how they were parsed, their simplified form and their value at a
+//! chosen point, for working out why a formula doesn't behave as expected. Type `:help` for the commands.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use bevy_graph_sim::{
+    formula_bytecode::Program,
+    formula_error::FormulaError,
+    formula_latex::to_latex,
+    formula_tree::Expr,
+    parsing_function::FormulaParser,
+};
+use clap::Parser;
+use glam::Vec3;
+
+#[derive(Parser)]
+#[command(name = "formula-repl", about = "Parse and evaluate graph formulas interactively")]
+struct Args {
+    /// Read `2x` and `(x+1)(x-1)` as multiplication
+    #[arg(long)]
+    implicit_multiplication: bool,
+}
+
+const HELP: &str = "\
+Type a formula to see how it parsed, its simplified form and its value.
+Commands:
+  :set <variable> <formula>  Set time, x, y or z to the value of a formula
+  :vars                      Show the values formulas are evaluated with
+  :tree [formula]            Draw the tree of the formula, or of the last one
+  :latex [formula]           Write the formula, or the last one, as LaTeX
+  :help                      Show this message
+  :quit                      Leave";
+
+struct Session {
+    parser: FormulaParser,
+    time: f32,
+    point: Vec3,
+    last: Option<Expr>,
+}
+
+impl Session {
+    fn evaluate(&self, expr: &Expr) -> Result<f32, Vec<FormulaError>> {
+        Program::compile(expr)
+            .map(|program| program.run(self.time, self.point))
+            .map_err(|error| vec![error])
+    }
+
+    /// Parses `text`, or gives back the last formula when it is empty
+    fn formula(&mut self, text: &str) -> Result<Expr, Vec<FormulaError>> {
+        if text.is_empty() {
+            return self.last.clone().ok_or_else(|| vec![FormulaError::new("No formula entered yet", None)]);
+        }
+        let expr = self.parser.parse_tree_diagnostics(text)?;
+        self.last = Some(expr.clone());
+        Ok(expr)
+    }
+
+    fn set(&mut self, arguments: &str) -> Result<String, Vec<FormulaError>> {
+        let (name, text) = arguments.split_once(char::is_whitespace).unwrap_or((arguments, ""));
+        let expr = self.parser.parse_tree_diagnostics(text.trim())?;
+        let value = self.evaluate(&expr)?;
+        match name {
+            "time" => self.time = value,
+            "x" => self.point.x = value,
+            "y" => self.point.y = value,
+            "z" => self.point.z = value,
+            _ => return Err(vec![FormulaError::new(format!("Can't set `{}`, only time, x, y and z", name), None)]),
+        }
+        Ok(format!("{} = {}", name, value))
+    }
+
+    fn variables(&self) -> String {
+        format!("time = {}, x = {}, y = {}, z = {}", self.time, self.point.x, self.point.y, self.point.z)
+    }
+
+    fn run_line(&mut self, line: &str) -> Result<String, Vec<FormulaError>> {
+        let Some(command) = line.strip_prefix(':') else {
+            let expr = self.formula(line)?;
+            let value = self.evaluate(&expr)?;
+            return Ok(format!("parsed:     {}\nsimplified: {}\nvalue:      {}", expr, expr.simplify(), value));
+        };
+        let (name, arguments) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        let arguments = arguments.trim();
+        match name {
+            "set" => self.set(arguments),
+            "vars" => Ok(self.variables()),
+            "tree" => Ok(self.formula(arguments)?.tree_diagram().trim_end().to_string()),
+            "latex" => Ok(to_latex(&self.formula(arguments)?)),
+            "help" => Ok(HELP.to_string()),
+            _ => Err(vec![FormulaError::new(format!("Unknown command `:{}`, type :help for the list", name), None)]),
+        }
+    }
+}
+
+/// Prints each error, underlining the part of the line it points at
+fn print_errors(line: &str, errors: &[FormulaError]) {
+    // Spans count from the start of the formula, which comes after the command and the name `:set` takes
+    let mut formula_start = 0;
+    if let Some(command) = line.strip_prefix(':') {
+        formula_start = skip_word(line, 1);
+        if command.split_whitespace().next() == Some("set") {
+            formula_start = skip_word(line, formula_start);
+        }
+    }
+    let column = line[..formula_start].chars().count();
+    for error in errors {
+        if let Some(span) = error.span {
+            eprintln!("  {}", line);
+            eprintln!("  {}{}", " ".repeat(column + span.start), "^".repeat(usize::max(span.end - span.start, 1)));
+        }
+        eprintln!("error: {}", error);
+    }
+}
+
+/// Byte offset after the word starting at `offset` and the whitespace following it
+fn skip_word(line: &str, offset: usize) -> usize {
+    let rest = &line[offset..];
+    let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    line.len() - rest[word_end..].trim_start().len()
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut session = Session {
+        parser: FormulaParser {
+            implicit_multiplication: args.implicit_multiplication,
+            ..FormulaParser::default()
+        },
+        time: 0.,
+        point: Vec3::ZERO,
+        last: None,
+    };
+
+    // Only prompt when someone is typing, so piped input gives clean output
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Graph formula REPL, type :help for the commands");
+    }
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("> ");
+            let _ = io::stdout().flush();
+        }
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == ":quit" || line == ":q" {
+            break;
+        }
+        match session.run_line(line) {
+            Ok(output) => println!("{}", output),
+            Err(errors) => print_errors(line, &errors),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Evaluates the X/Y/Z formulas over a grid of points without opening a window, printing every point's position
+//! at each time step. Points move the same way they do in the app: each step applies the formulas to the positions
+//! left by the step before.
+
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::process::ExitCode;
+use std::thread;
+
+use bevy_graph_sim::{
+    parsing_function::{FormulaParser, GraphFormula},
+    point_evaluation::{grid_points, parse_grid_counts, AxisFormulas, PointColumns, GRID_SPACING},
+};
+use clap::{Parser, ValueEnum};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// A `time,index,x,y,z` header then one row per point
+    Csv,
+    /// One JSON object per point, with non-finite coordinates written as null
+    Json,
+}
+
+#[derive(Parser)]
+#[command(name = "graph-eval", about = "Evaluate graph formulas over a grid of points and print the positions")]
+struct Args {
+    /// Formula for the new x coordinate
+    #[arg(short = 'x', long, default_value = "x")]
+    x_formula: String,
+    /// Formula for the new y coordinate
+    #[arg(short = 'y', long, default_value = "y")]
+    y_formula: String,
+    /// Formula for the new z coordinate
+    #[arg(short = 'z', long, default_value = "z")]
+    z_formula: String,
+    /// Number of points along each axis
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_grid_counts, default_value = "25,1,25")]
+    grid: [u32; 3],
+    /// Distance between neighbouring points
+    #[arg(long, default_value_t = GRID_SPACING)]
+    spacing: u32,
+    /// Time of the first step
+    #[arg(long, default_value_t = 0.)]
+    start: f32,
+    /// Time of the last step
+    #[arg(long, default_value_t = 0.)]
+    end: f32,
+    /// Time between steps
+    #[arg(long, default_value_t = 1. / 60.)]
+    step: f32,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+    /// Read `2x` and `(x+1)(x-1)` as multiplication
+    #[arg(long)]
+    implicit_multiplication: bool,
+    /// Threads to evaluate on, every logical core by default
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if !args.step.is_finite() || args.step <= 0. || !args.start.is_finite() || !args.end.is_finite() || args.start > args.end {
+        eprintln!("--start and --end must be finite with --start no later than --end, and --step must be finite and greater than zero");
+        return ExitCode::from(2);
+    }
+
+    let parser = FormulaParser {
+        implicit_multiplication: args.implicit_multiplication,
+        ..FormulaParser::default()
+    };
+    let mut failed = false;
+    let mut compile = |axis: &str, text: &str| {
+        let compiled = parser.compile_diagnostics(text);
+        if let Err(errors) = &compiled {
+            for error in errors {
+                eprintln!("{} formula `{}`: {}", axis, text, error);
+            }
+            failed = true;
+        }
+        GraphFormula::new(compiled)
+    };
+    let formulas = AxisFormulas {
+        x_func: compile("X", &args.x_formula),
+        y_func: compile("Y", &args.y_formula),
+        z_func: compile("Z", &args.z_formula),
+    };
+    if failed {
+        return ExitCode::FAILURE;
+    }
+
+    let thread_count = args.threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get));
+
+    let mut columns = PointColumns::default();
+    for point in grid_points(args.grid[0], args.grid[1], args.grid[2], args.spacing) {
+        columns.push(point);
+    }
+
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    let result = write_steps(&mut output, &args, &formulas, &mut columns, thread_count).and_then(|_| output.flush());
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // Whatever was reading the output stopped early, which isn't an error for us
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Failed to write output: {}", error);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn write_steps(output: &mut impl Write, args: &Args, formulas: &AxisFormulas, columns: &mut PointColumns, thread_count: usize) -> io::Result<()> {
+    if let OutputFormat::Csv = args.format {
+        writeln!(output, "time,index,x,y,z")?;
+    }
+    let mut step_index: u32 = 0;
+    loop {
+        // Multiplying rather than accumulating keeps rounding errors from building up over long ranges
+        let time = args.start + args.step * step_index as f32;
+        // Allow for rounding so the step landing on `end` isn't lost
+        if time > args.end + args.step * 1e-3 && step_index > 0 {
+            return Ok(());
+        }
+        columns.evaluate_threads(formulas, time, thread_count);
+        for index in 0..columns.len() {
+            let point = columns.point(index);
+            match args.format {
+                OutputFormat::Csv => writeln!(output, "{},{},{},{},{}", time, index, point.x, point.y, point.z)?,
+                OutputFormat::Json => writeln!(
+                    output,
+                    "{{\"time\":{},\"index\":{},\"x\":{},\"y\":{},\"z\":{}}}",
+                    json_number(time),
+                    index,
+                    json_number(point.x),
+                    json_number(point.y),
+                    json_number(point.z),
+                )?,
+            }
+        }
+        step_index += 1;
+    }
+}
+
+/// JSON has no NaN or infinity
+fn json_number(x: f32) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_string()
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Names of the files holding each axis's formula in a formula directory, in x, y, z order
+pub const FORMULA_FILE_NAMES: [&str; 3] = ["x.formula", "y.formula", "z.formula"];
+
+/// What a file looked like when it was last polled. The length is compared too, since a file rewritten twice
+/// within the filesystem's timestamp resolution keeps its modification time.
+#[derive(Clone, Copy, PartialEq)]
+struct FileStamp {
+    modified: SystemTime,
+    length: u64,
+}
+
+fn stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(FileStamp {
+        modified: metadata.modified().ok()?,
+        length: metadata.len(),
+    })
+}
+
+/// Notices files being created, changed or deleted by polling their metadata, which works the same on every
+/// platform and is cheap for the handful of files watched
+pub struct FileWatch {
+    files: Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+impl FileWatch {
+    /// Watches the given files. The first poll reports every one of them that exists as changed.
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        FileWatch {
+            files: paths.into_iter().map(|path| (path, None)).collect(),
+        }
+    }
+
+    /// Whether any file changed since the last poll
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, last) in self.files.iter_mut() {
+            let current = stamp(path);
+            if current != *last {
+                *last = current;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// The formula files of each axis in `directory`
+pub fn formula_file_paths(directory: &Path) -> [PathBuf; 3] {
+    FORMULA_FILE_NAMES.map(|name| directory.join(name))
+}
+
+/// Text of each axis's formula file in `directory`, or `None` for axes without a readable file
+pub fn read_formula_files(directory: &Path) -> [Option<String>; 3] {
+    formula_file_paths(directory).map(|path| fs::read_to_string(path).ok().map(|text| text.trim().to_string()))
+}
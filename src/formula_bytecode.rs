@@ -0,0 +1,293 @@
+use glam::Vec3;
+
+use super::formula_error::FormulaError;
+use super::formula_tree::{spherical_coordinates, Expr};
+use super::parsing_function::{FunctionType, Operator, MAX_ARITY};
+
+/// The number of values the stack machine can hold at once.
+/// Compilation fails for formulas that would need more than this.
+pub const STACK_SIZE: usize = 64;
+
+/// The number of points `Program::run_batch` pushes through each instruction at once.
+/// Operating on fixed-size arrays of this width lets the compiler vectorise the arithmetic.
+pub const LANES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    Constant(f32),
+    Time,
+    PointX,
+    PointY,
+    PointZ,
+    /// One of the `r`, `theta` and `phi` the point was placed with, by its index in that order
+    Layout(usize),
+    /// A value loaded with the point, by its index in `FormulaParser::columns`
+    Column(usize),
+    /// Pops the function's arguments, pushed in order, and pushes the result
+    Function(FunctionType),
+    /// Pops the right then left operands and pushes the result
+    Binary(Operator),
+}
+
+/// What a run of points carries besides their positions: the `r`, `theta` and `phi` each was placed with, and the
+/// values loaded with them, like the extra columns of a CSV file. The values are stored point by point with `width`
+/// values each so any run of whole points is a plain subslice.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PointData<'a> {
+    /// Layout parameters of every point, or empty to read the spherical coordinates of where the points are now
+    pub layout: &'a [[f32; 3]],
+    pub values: &'a [f32],
+    /// Number of values each point has
+    pub width: usize,
+}
+
+impl<'a> PointData<'a> {
+    pub fn new(layout: &'a [[f32; 3]], values: &'a [f32], width: usize) -> Self {
+        PointData { layout, values, width }
+    }
+
+    /// Layout parameters of the point at `index`, if the points have them
+    pub fn layout(&self, index: usize) -> Option<[f32; 3]> {
+        self.layout.get(index).copied()
+    }
+
+    /// Values of the point at `index`
+    pub fn row(&self, index: usize) -> &'a [f32] {
+        &self.values[index * self.width..(index + 1) * self.width]
+    }
+
+    /// Value `column` of the point at `index`
+    fn get(&self, index: usize, column: usize) -> f32 {
+        self.values[index * self.width + column]
+    }
+}
+
+/// A formula compiled to a flat list of stack machine instructions.
+/// Running it never allocates, which makes it much cheaper than walking an `Expr` for every point.
+#[derive(Clone, Debug)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn compile(expr: &Expr) -> Result<Program, FormulaError> {
+        let mut instructions = Vec::new();
+        let max_depth = emit(expr, &mut instructions, 0);
+        if max_depth > STACK_SIZE {
+            return Err(FormulaError::new(
+                format!("Formula is nested too deeply, it needs {} stack slots but only {} are available", max_depth, STACK_SIZE),
+                None,
+            ));
+        }
+        Ok(Program { instructions })
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Runs the program for a single point, reading columns as NaN
+    pub fn run(&self, time_elapsed: f32, point_pos: Vec3) -> f32 {
+        self.run_with_columns(time_elapsed, point_pos, &[])
+    }
+
+    /// Runs the program for a single point with the given column values, as if the point had been placed where it is
+    pub fn run_with_columns(&self, time_elapsed: f32, point_pos: Vec3, columns: &[f32]) -> f32 {
+        self.run_point(time_elapsed, point_pos, None, columns)
+    }
+
+    /// Runs the program for a single point with the `r`, `theta` and `phi` it was placed with and its column values
+    pub fn run_with_layout(&self, time_elapsed: f32, point_pos: Vec3, layout: [f32; 3], columns: &[f32]) -> f32 {
+        self.run_point(time_elapsed, point_pos, Some(layout), columns)
+    }
+
+    fn run_point(&self, time_elapsed: f32, point_pos: Vec3, layout: Option<[f32; 3]>, columns: &[f32]) -> f32 {
+        let mut stack = [0.0f32; STACK_SIZE];
+        // Index of the next free slot
+        let mut top = 0;
+        for instruction in self.instructions.iter() {
+            match *instruction {
+                Instruction::Constant(x) => {
+                    stack[top] = x;
+                    top += 1;
+                },
+                Instruction::Time => {
+                    stack[top] = time_elapsed;
+                    top += 1;
+                },
+                Instruction::PointX => {
+                    stack[top] = point_pos.x;
+                    top += 1;
+                },
+                Instruction::PointY => {
+                    stack[top] = point_pos.y;
+                    top += 1;
+                },
+                Instruction::PointZ => {
+                    stack[top] = point_pos.z;
+                    top += 1;
+                },
+                Instruction::Layout(index) => {
+                    stack[top] = layout.unwrap_or_else(|| spherical_coordinates(point_pos))[index];
+                    top += 1;
+                },
+                Instruction::Column(index) => {
+                    stack[top] = columns.get(index).copied().unwrap_or(f32::NAN);
+                    top += 1;
+                },
+                Instruction::Function(func_type) => {
+                    top -= func_type.arity();
+                    stack[top] = func_type.perform_f32_func(&stack[top..top + func_type.arity()]);
+                    top += 1;
+                },
+                Instruction::Binary(oper) => {
+                    top -= 1;
+                    stack[top - 1] = oper.run(stack[top - 1], stack[top]);
+                },
+            }
+        }
+        stack[0]
+    }
+
+    /// Evaluates the program for every point given as structure-of-arrays slices, writing one value per point to `output`.
+    /// Points are processed `LANES` at a time so each instruction is dispatched once per chunk rather than once per point.
+    /// Columns read as NaN.
+    pub fn run_batch(&self, time_elapsed: f32, xs: &[f32], ys: &[f32], zs: &[f32], output: &mut [f32]) {
+        self.run_batch_with_columns(time_elapsed, xs, ys, zs, PointData::default(), output)
+    }
+
+    /// Like `run_batch`, with every point's layout parameters and column values in `data`. Columns past its width
+    /// read as NaN.
+    pub fn run_batch_with_columns(&self, time_elapsed: f32, xs: &[f32], ys: &[f32], zs: &[f32], data: PointData, output: &mut [f32]) {
+        assert!(
+            xs.len() == output.len() && ys.len() == output.len() && zs.len() == output.len()
+                && (data.layout.is_empty() || data.layout.len() == output.len())
+                && (data.width == 0 || data.values.len() == data.width * output.len()),
+            "run_batch needs input and output slices of the same length"
+        );
+        let mut stack = [[0.0f32; LANES]; STACK_SIZE];
+        for start in (0..output.len()).step_by(LANES) {
+            let count = usize::min(LANES, output.len() - start);
+            let lanes_x = load_lanes(&xs[start..start + count]);
+            let lanes_y = load_lanes(&ys[start..start + count]);
+            let lanes_z = load_lanes(&zs[start..start + count]);
+            let mut top = 0;
+            for instruction in self.instructions.iter() {
+                match *instruction {
+                    Instruction::Constant(x) => {
+                        stack[top] = [x; LANES];
+                        top += 1;
+                    },
+                    Instruction::Time => {
+                        stack[top] = [time_elapsed; LANES];
+                        top += 1;
+                    },
+                    Instruction::PointX => {
+                        stack[top] = lanes_x;
+                        top += 1;
+                    },
+                    Instruction::PointY => {
+                        stack[top] = lanes_y;
+                        top += 1;
+                    },
+                    Instruction::PointZ => {
+                        stack[top] = lanes_z;
+                        top += 1;
+                    },
+                    Instruction::Layout(index) => {
+                        let mut lanes = [0.0f32; LANES];
+                        for (lane, value) in lanes[..count].iter_mut().enumerate() {
+                            *value = match data.layout(start + lane) {
+                                Some(layout) => layout[index],
+                                None => spherical_coordinates(Vec3::new(lanes_x[lane], lanes_y[lane], lanes_z[lane]))[index],
+                            };
+                        }
+                        stack[top] = lanes;
+                        top += 1;
+                    },
+                    Instruction::Column(index) => {
+                        let mut lanes = [f32::NAN; LANES];
+                        if index < data.width {
+                            for (lane, value) in lanes[..count].iter_mut().enumerate() {
+                                *value = data.get(start + lane, index);
+                            }
+                        }
+                        stack[top] = lanes;
+                        top += 1;
+                    },
+                    Instruction::Function(func_type) => {
+                        let arity = func_type.arity();
+                        top -= arity;
+                        let mut results = [0.0f32; LANES];
+                        for (lane, result) in results.iter_mut().enumerate() {
+                            let mut args = [0.0f32; MAX_ARITY];
+                            for (arg, values) in args.iter_mut().zip(stack[top..top + arity].iter()) {
+                                *arg = values[lane];
+                            }
+                            *result = func_type.perform_f32_func(&args[..arity]);
+                        }
+                        stack[top] = results;
+                        top += 1;
+                    },
+                    Instruction::Binary(oper) => {
+                        top -= 1;
+                        let right = stack[top];
+                        let left = &mut stack[top - 1];
+                        match oper {
+                            // The common operators are written out so each loop is a single vector instruction
+                            Operator::Addition => for lane in 0..LANES { left[lane] += right[lane] },
+                            Operator::Subtraction => for lane in 0..LANES { left[lane] -= right[lane] },
+                            Operator::Multiplication => for lane in 0..LANES { left[lane] *= right[lane] },
+                            Operator::Division => for lane in 0..LANES { left[lane] /= right[lane] },
+                            _ => for lane in 0..LANES { left[lane] = oper.run(left[lane], right[lane]) },
+                        }
+                    },
+                }
+            }
+            output[start..start + count].copy_from_slice(&stack[0][..count]);
+        }
+    }
+}
+
+/// Copies up to `LANES` values into a full lane array, padding the unused lanes with zero.
+fn load_lanes(values: &[f32]) -> [f32; LANES] {
+    let mut lanes = [0.0f32; LANES];
+    lanes[..values.len()].copy_from_slice(values);
+    lanes
+}
+
+/// Appends the instructions for `expr` in post-order, and returns the deepest the stack gets while running them.
+fn emit(expr: &Expr, instructions: &mut Vec<Instruction>, depth: usize) -> usize {
+    match expr {
+        Expr::Time => push(instructions, Instruction::Time, depth),
+        Expr::PointX => push(instructions, Instruction::PointX, depth),
+        Expr::PointY => push(instructions, Instruction::PointY, depth),
+        Expr::PointZ => push(instructions, Instruction::PointZ, depth),
+        Expr::PointR => push(instructions, Instruction::Layout(0), depth),
+        Expr::PointTheta => push(instructions, Instruction::Layout(1), depth),
+        Expr::PointPhi => push(instructions, Instruction::Layout(2), depth),
+        Expr::Column(index, _) => push(instructions, Instruction::Column(*index), depth),
+        // Parameters only change by parsing the formula again, so they compile to their value
+        Expr::Parameter(_, x) | Expr::Constant(x) => push(instructions, Instruction::Constant(*x), depth),
+        Expr::Function(func_type, args) => {
+            // Each argument is left on the stack above the ones before it
+            let mut max_depth = depth + 1;
+            for (index, arg) in args.iter().enumerate() {
+                max_depth = usize::max(max_depth, emit(arg, instructions, depth + index));
+            }
+            instructions.push(Instruction::Function(*func_type));
+            max_depth
+        },
+        Expr::Binary(oper, left, right) => {
+            let left_depth = emit(left, instructions, depth);
+            let right_depth = emit(right, instructions, depth + 1);
+            instructions.push(Instruction::Binary(*oper));
+            usize::max(left_depth, right_depth)
+        },
+    }
+}
+
+fn push(instructions: &mut Vec<Instruction>, instruction: Instruction, depth: usize) -> usize {
+    instructions.push(instruction);
+    depth + 1
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+/// A range of characters in a formula's text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    /// Index of the first character, counted in chars
+    pub start: usize,
+    /// Index one past the last character, counted in chars
+    pub end: usize,
+    /// Byte offset of the first character, for slicing the formula's text
+    pub byte_start: usize,
+    /// Byte offset one past the last character
+    pub byte_end: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Span {
+    pub fn display_location(&self) -> String {
+        format!("line: {}, column: {}", self.line, self.column)
+    }
+}
+
+/// A problem found while reading a formula, pointing at the text it came from when there is one
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormulaError {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl FormulaError {
+    pub fn new(message: impl Into<String>, span: Option<Span>) -> Self {
+        FormulaError {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} at {}", self.message, span.display_location()),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for FormulaError {}
+
+/// Number of single character insertions, deletions, substitutions and adjacent swaps needed to turn `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between the first i chars of `a` and the first j chars of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution_cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution_cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// The candidate closest to `name`, if any is close enough to be a likely typo.
+/// Ties go to the earliest candidate.
+pub fn closest_match<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let name = name.to_ascii_lowercase();
+    let length = name.chars().count();
+    let max_distance = usize::max(1, length / 3);
+    candidates.into_iter()
+        .map(|candidate| (edit_distance(&name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance && *distance < length)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
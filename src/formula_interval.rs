@@ -0,0 +1,278 @@
+use std::{
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    ops::{Add, Div, Mul, Sub},
+};
+
+use super::formula_tree::Expr;
+use super::noise::WORLEY_MAX;
+use super::parsing_function::{FunctionType, Operator};
+
+/// A closed range of values, used to bound a formula's output without sampling every point.
+/// Either end may be infinite. Results that could be NaN are widened to `Interval::ENTIRE`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Interval {
+    pub const ENTIRE: Interval = Interval { min: f32::NEG_INFINITY, max: f32::INFINITY };
+
+    pub fn new(min: f32, max: f32) -> Self {
+        Interval { min: f32::min(min, max), max: f32::max(min, max) }
+    }
+
+    pub fn point(x: f32) -> Self {
+        Interval { min: x, max: x }
+    }
+
+    pub fn contains(&self, x: f32) -> bool {
+        self.min <= x && x <= self.max
+    }
+
+    pub fn contains_zero(&self) -> bool {
+        self.contains(0.)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max - self.min
+    }
+
+    /// The smallest interval containing both intervals
+    pub fn hull(&self, other: Interval) -> Interval {
+        Interval { min: f32::min(self.min, other.min), max: f32::max(self.max, other.max) }
+    }
+
+    pub fn pow(self, other: Interval) -> Interval {
+        let base_min = self.min as f64;
+        let base_max = self.max as f64;
+        if other.min == other.max && other.min.fract() == 0. {
+            let exponent = other.min as f64;
+            if exponent == 0. {
+                return Interval::point(1.);
+            }
+            if !self.contains_zero() {
+                // x^n is monotonic on either side of zero
+                return round_outward(&[base_min.powf(exponent), base_max.powf(exponent)]);
+            }
+            if exponent < 0. {
+                return Interval::ENTIRE;
+            }
+            let at_ends = [base_min.powf(exponent), base_max.powf(exponent)];
+            if exponent % 2. == 0. {
+                return round_outward(&[0., f64::max(at_ends[0], at_ends[1])]);
+            }
+            return round_outward(&at_ends);
+        }
+        if self.min > 0. {
+            // For positive bases x^y is monotonic in both arguments, so the extremes are at the corners
+            let exponent_min = other.min as f64;
+            let exponent_max = other.max as f64;
+            return round_outward(&[
+                base_min.powf(exponent_min),
+                base_min.powf(exponent_max),
+                base_max.powf(exponent_min),
+                base_max.powf(exponent_max),
+            ]);
+        }
+        // Negative bases with fractional exponents are NaN
+        Interval::ENTIRE
+    }
+
+    pub fn sin(self) -> Interval {
+        self.periodic(0.)
+    }
+
+    pub fn cos(self) -> Interval {
+        // cos(x) = sin(x + pi / 2)
+        self.periodic(FRAC_PI_2)
+    }
+
+    /// Bounds sin(x + phase), which peaks at pi / 2 and bottoms out at 3 pi / 2 in each period
+    fn periodic(self, phase: f64) -> Interval {
+        if !self.min.is_finite() || !self.max.is_finite() || self.width() as f64 >= TAU {
+            return Interval::new(-1., 1.);
+        }
+        let start = self.min as f64 + phase;
+        let end = self.max as f64 + phase;
+        let mut min = f64::min(start.sin(), end.sin());
+        let mut max = f64::max(start.sin(), end.sin());
+        if contains_periodic_point(start, end, FRAC_PI_2) {
+            max = 1.;
+        }
+        if contains_periodic_point(start, end, 3. * FRAC_PI_2) {
+            min = -1.;
+        }
+        let output = round_outward(&[min, max]);
+        Interval::new(f32::max(output.min, -1.), f32::min(output.max, 1.))
+    }
+
+    /// tan is unbounded across its poles at pi / 2 + k pi, and increasing between them
+    pub fn tan(self) -> Interval {
+        if !self.min.is_finite() || !self.max.is_finite() || self.width() as f64 >= PI {
+            return Interval::ENTIRE;
+        }
+        let start = self.min as f64;
+        let end = self.max as f64;
+        if contains_periodic_point(start, end, FRAC_PI_2) || contains_periodic_point(start, end, 3. * FRAC_PI_2) {
+            return Interval::ENTIRE;
+        }
+        round_outward(&[start.tan(), end.tan()])
+    }
+
+    /// Square roots of negative numbers are NaN, so any negative part widens the result to `ENTIRE`
+    pub fn sqrt(self) -> Interval {
+        if self.min < 0. {
+            return Interval::ENTIRE;
+        }
+        round_outward(&[(self.min as f64).sqrt(), (self.max as f64).sqrt()])
+    }
+
+    pub fn abs(self) -> Interval {
+        if self.min >= 0. {
+            self
+        } else if self.max <= 0. {
+            Interval::new(-self.max, -self.min)
+        } else {
+            Interval::new(0., f32::max(-self.min, self.max))
+        }
+    }
+}
+
+// f32 arithmetic rounds monotonically, so combining the endpoints with the same operations the evaluator
+// uses is already conservative for the basic operators
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, other: Interval) -> Interval {
+        from_candidates(&[self.min + other.min, self.max + other.max])
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, other: Interval) -> Interval {
+        from_candidates(&[self.min - other.max, self.max - other.min])
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, other: Interval) -> Interval {
+        from_candidates(&[
+            self.min * other.min,
+            self.min * other.max,
+            self.max * other.min,
+            self.max * other.max,
+        ])
+    }
+}
+
+/// Division by an interval touching zero at one end is unbounded on one side only.
+/// Division by an interval with zero strictly inside it can produce any value.
+impl Div for Interval {
+    type Output = Interval;
+
+    fn div(self, other: Interval) -> Interval {
+        if !other.contains_zero() {
+            return from_candidates(&[
+                self.min / other.min,
+                self.min / other.max,
+                self.max / other.min,
+                self.max / other.max,
+            ]);
+        }
+        if self == Interval::point(0.) || other == Interval::point(0.) {
+            // 0 / 0 is NaN, and x / 0 is infinite with the sign of x
+            return Interval::ENTIRE;
+        }
+        if other.min == 0. {
+            // Divisor in [0, b], so 1 / divisor is in [1 / b, inf]
+            if self.min >= 0. {
+                return Interval::new(self.min / other.max, f32::INFINITY);
+            } else if self.max <= 0. {
+                return Interval::new(f32::NEG_INFINITY, self.max / other.max);
+            }
+        } else if other.max == 0. {
+            // Divisor in [a, 0], so 1 / divisor is in [-inf, 1 / a]
+            if self.min >= 0. {
+                return Interval::new(f32::NEG_INFINITY, self.min / other.min);
+            } else if self.max <= 0. {
+                return Interval::new(self.max / other.min, f32::INFINITY);
+            }
+        }
+        Interval::ENTIRE
+    }
+}
+
+/// Computes a conservative range for the formula's output when each input varies over its interval
+pub fn evaluate_interval(expr: &Expr, time: Interval, x: Interval, y: Interval, z: Interval) -> Interval {
+    match expr {
+        Expr::Time => time,
+        Expr::PointX => x,
+        Expr::PointY => y,
+        Expr::PointZ => z,
+        // Stored when the point was placed, so they only have the ranges of spherical coordinates
+        Expr::PointR => Interval::new(0., f32::INFINITY),
+        Expr::PointTheta => Interval::new(-std::f32::consts::PI, std::f32::consts::PI),
+        Expr::PointPhi => Interval::new(0., std::f32::consts::PI),
+        // Loaded data could hold anything
+        Expr::Column(_, _) => Interval::ENTIRE,
+        Expr::Parameter(_, value) | Expr::Constant(value) => Interval::point(*value),
+        Expr::Function(func_type, args) => {
+            let first = evaluate_interval(&args[0], time, x, y, z);
+            match func_type {
+                FunctionType::Sin => first.sin(),
+                FunctionType::Cos => first.cos(),
+                FunctionType::Tan => first.tan(),
+                FunctionType::Abs => first.abs(),
+                FunctionType::Sqrt => first.sqrt(),
+                FunctionType::Atan2 => Interval::new(-std::f32::consts::PI, std::f32::consts::PI),
+                // The noise functions have fixed output ranges whatever their inputs
+                FunctionType::Rand | FunctionType::Hash => Interval::new(0., 1.),
+                FunctionType::Perlin | FunctionType::Simplex | FunctionType::Fbm => Interval::new(-1., 1.),
+                FunctionType::Worley => Interval::new(0., WORLEY_MAX),
+            }
+        },
+        Expr::Binary(oper, left, right) => {
+            let left = evaluate_interval(left, time, x, y, z);
+            let right = evaluate_interval(right, time, x, y, z);
+            match oper {
+                Operator::Addition => left + right,
+                Operator::Subtraction => left - right,
+                Operator::Multiplication => left * right,
+                Operator::Division => left / right,
+                Operator::Exponentiation => left.pow(right),
+            }
+        },
+    }
+}
+
+/// Whether `offset + 2 k pi` falls inside [start, end] for some integer k
+fn contains_periodic_point(start: f64, end: f64, offset: f64) -> bool {
+    let k = ((start - offset) / TAU).ceil();
+    offset + k * TAU <= end
+}
+
+fn from_candidates(candidates: &[f32]) -> Interval {
+    if candidates.iter().any(|x| x.is_nan()) {
+        return Interval::ENTIRE;
+    }
+    Interval {
+        min: candidates.iter().copied().fold(f32::INFINITY, f32::min),
+        max: candidates.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+    }
+}
+
+/// Converts values computed in f64 to an f32 interval, stepping one ulp outwards to cover
+/// rounding differences between these functions and the f32 ones the evaluator calls
+fn round_outward(candidates: &[f64]) -> Interval {
+    if candidates.iter().any(|x| x.is_nan()) {
+        return Interval::ENTIRE;
+    }
+    let min = candidates.iter().copied().fold(f64::INFINITY, f64::min) as f32;
+    let max = candidates.iter().copied().fold(f64::NEG_INFINITY, f64::max) as f32;
+    Interval { min: min.next_down(), max: max.next_up() }
+}
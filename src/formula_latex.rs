@@ -0,0 +1,83 @@
+use super::formula_tree::Expr;
+use super::parsing_function::{FunctionType, Operator};
+
+/// Writes the formula as LaTeX math. Division becomes `\frac`, `^` a superscript, and parentheses are only
+/// added where precedence needs them, so the output is for reading rather than for parsing back.
+pub fn to_latex(expr: &Expr) -> String {
+    match expr {
+        Expr::Time => "t".to_string(),
+        Expr::PointX => "x".to_string(),
+        Expr::PointY => "y".to_string(),
+        Expr::PointZ => "z".to_string(),
+        Expr::PointR => "r".to_string(),
+        Expr::PointTheta => "\\theta".to_string(),
+        Expr::PointPhi => "\\phi".to_string(),
+        Expr::Column(_, name) | Expr::Parameter(name, _) => format!("\\mathrm{{{}}}", name.replace('_', "\\_")),
+        Expr::Constant(x) => constant_latex(*x),
+        Expr::Function(func_type, args) => {
+            let args: Vec<String> = args.iter().map(to_latex).collect();
+            match func_type {
+                FunctionType::Sin | FunctionType::Cos | FunctionType::Tan => format!("\\{}\\left({}\\right)", func_type.name(), args[0]),
+                FunctionType::Abs => format!("\\left|{}\\right|", args[0]),
+                FunctionType::Sqrt => format!("\\sqrt{{{}}}", args[0]),
+                _ => format!("\\operatorname{{{}}}\\left({}\\right)", func_type.name(), args.join(", ")),
+            }
+        },
+        Expr::Binary(Operator::Division, left, right) => format!("\\frac{{{}}}{{{}}}", to_latex(left), to_latex(right)),
+        Expr::Binary(Operator::Exponentiation, base, exponent) => {
+            let base = if is_atomic_base(base) { to_latex(base) } else { format!("\\left({}\\right)", to_latex(base)) };
+            format!("{}^{{{}}}", base, to_latex(exponent))
+        },
+        Expr::Binary(oper, left, right) => {
+            let symbol = match oper {
+                Operator::Multiplication => "\\cdot",
+                Operator::Subtraction => "-",
+                _ => "+",
+            };
+            format!("{} {} {}", operand_latex(left, *oper, false), symbol, operand_latex(right, *oper, true))
+        },
+    }
+}
+
+fn constant_latex(x: f32) -> String {
+    if x.is_nan() {
+        "\\mathrm{NaN}".to_string()
+    } else if x == f32::INFINITY {
+        "\\infty".to_string()
+    } else if x == f32::NEG_INFINITY {
+        "-\\infty".to_string()
+    } else {
+        // Adding zero turns negative zero into zero
+        (x + 0.).to_string()
+    }
+}
+
+fn operand_latex(expr: &Expr, parent: Operator, is_right: bool) -> String {
+    let latex = to_latex(expr);
+    let needs_parentheses = match expr {
+        // A fraction is already grouped by its bar
+        Expr::Binary(Operator::Division, _, _) => false,
+        Expr::Binary(oper, _, _) => {
+            oper.get_precedence() < parent.get_precedence()
+                || (is_right && parent == Operator::Subtraction && oper.get_precedence() == parent.get_precedence())
+        },
+        // Keeps `x - -2` and `x \cdot -2` readable
+        _ => is_right && latex.starts_with('-'),
+    };
+    if needs_parentheses {
+        format!("\\left({}\\right)", latex)
+    } else {
+        latex
+    }
+}
+
+/// Whether a superscript can be attached to the expression without wrapping it in parentheses
+fn is_atomic_base(expr: &Expr) -> bool {
+    match expr {
+        Expr::Time | Expr::PointX | Expr::PointY | Expr::PointZ => true,
+        Expr::PointR | Expr::PointTheta | Expr::PointPhi | Expr::Column(_, _) | Expr::Parameter(_, _) => true,
+        Expr::Constant(x) => x.is_finite() && *x >= 0.,
+        Expr::Function(func_type, _) => matches!(func_type, FunctionType::Abs | FunctionType::Sqrt),
+        Expr::Binary(_, _, _) => false,
+    }
+}
@@ -0,0 +1,276 @@
+//! The original formula evaluator, which re-reads a flat list of parsed nodes for every point. It is no longer used
+//! by the app and is kept only so benchmarks can measure the tree and bytecode backends against it. It still
+//! applies operators in its original, not quite correct order, so its results can differ from theirs.
+
+use std::sync::{Arc, RwLock};
+
+use glam::Vec3;
+
+use super::parsing::*;
+use super::parsing_function::{FunctionType, Operator, MAX_ARITY};
+
+#[derive(Clone, Debug)]
+pub enum FuncNode {
+    Time,
+    PointX,
+    PointY,
+    PointZ,
+    Int(i64),
+    Float(f32),
+    /// A function call, holding the nodes of each comma separated argument
+    BuiltinFunction(FunctionType, Vec<Vec<FuncNode>>),
+    Parentheses(Vec<FuncNode>),
+    BinaryOperationSymbol(Operator),
+}
+
+impl FuncNode {
+    fn simplify_to_f32(&self, time_elapsed: f32, point_pos: Vec3) -> FuncNodeCleaned {
+        match self {
+            FuncNode::Time => FuncNodeCleaned::Float(time_elapsed),
+            FuncNode::PointX => FuncNodeCleaned::Float(point_pos.x),
+            FuncNode::PointY => FuncNodeCleaned::Float(point_pos.y),
+            FuncNode::PointZ => FuncNodeCleaned::Float(point_pos.z),
+            FuncNode::Int(x) => FuncNodeCleaned::Int(*x),
+            FuncNode::Float(x) => FuncNodeCleaned::Float(*x),
+            FuncNode::BuiltinFunction(func_type, args) => FuncNodeCleaned::BuiltinFunction(*func_type, args.iter().map(|arg| arg.iter().map(|x| x.simplify_to_f32(time_elapsed, point_pos)).collect()).collect()),
+            FuncNode::Parentheses(nodes) => FuncNodeCleaned::Parentheses(nodes.iter().map(|x| x.simplify_to_f32(time_elapsed, point_pos)).collect()),
+            FuncNode::BinaryOperationSymbol(oper) => FuncNodeCleaned::BinaryOperationSymbol(*oper),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum FuncNodeCleaned {
+    Int(i64),
+    Float(f32),
+    BuiltinFunction(FunctionType, Vec<Vec<FuncNodeCleaned>>),
+    BinaryOperationSymbol(Operator),
+    Parentheses(Vec<FuncNodeCleaned>),
+}
+
+type NodeParser = Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String> + Send + Sync>;
+
+fn parse_time(input: &mut ParseInput) -> Result<FuncNode, String> {
+    input.skip_word("time")?;
+    Ok(FuncNode::Time)
+}
+
+fn parse_point_x(input: &mut ParseInput) -> Result<FuncNode, String> {
+    input.skip_char('x')?;
+    Ok(FuncNode::PointX)
+}
+
+fn parse_point_y(input: &mut ParseInput) -> Result<FuncNode, String> {
+    input.skip_char('y')?;
+    Ok(FuncNode::PointY)
+}
+
+fn parse_point_z(input: &mut ParseInput) -> Result<FuncNode, String> {
+    input.skip_char('z')?;
+    Ok(FuncNode::PointZ)
+}
+
+fn parse_binary_operation_symbol(input: &mut ParseInput) -> Result<FuncNode, String> {
+    if input.skip_char('+').is_ok() {
+        Ok(FuncNode::BinaryOperationSymbol(Operator::Addition))
+    } else if input.skip_char('*').is_ok() {
+        Ok(FuncNode::BinaryOperationSymbol(Operator::Multiplication))
+    } else if input.skip_char('-').is_ok() {
+        Ok(FuncNode::BinaryOperationSymbol(Operator::Subtraction))
+    } else if input.skip_char('/').is_ok() {
+        Ok(FuncNode::BinaryOperationSymbol(Operator::Division))
+    } else if input.skip_char('^').is_ok() {
+        Ok(FuncNode::BinaryOperationSymbol(Operator::Exponentiation))
+    } else {
+        Err(format!("Expected +, -, *, or /, but found {:?}", input.get_next_char()))
+    }
+}
+
+fn parse_integer(input: &mut ParseInput) -> Result<FuncNode, String> {
+    let first_char = input.pop_next_char_numerical()?;
+    let mut output = first_char.to_string();
+    while let Ok(next_char) = input.pop_next_char_numerical() {
+        output += &next_char.to_string();
+    }
+    Ok(FuncNode::Int(str::parse::<i64>(&output).map_err(|err| err.to_string())?))
+}
+
+fn parse_builtin_func(interior_parser: NodeParser) -> NodeParser {
+    Arc::new(move | input: &mut ParseInput | {
+        // Prefer the longest matching name, so one function name can never shadow a longer one
+        let func_type = FunctionType::ALL.iter()
+            .filter(|func_type| input.match_word_ci(func_type.name()))
+            .max_by_key(|func_type| func_type.name().len())
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = FunctionType::ALL.iter().map(|func_type| func_type.name()).collect();
+                format!("Expected one of {}, but found {:?}", names.join(", "), input.get_next_char())
+            })?;
+        input.skip_word(func_type.name())?;
+        input.skip_char('(')?;
+        let mut args: Vec<Vec<FuncNode>> = vec![Vec::new()];
+        loop {
+            input.skip_spaces_and_newlines();
+            match interior_parser(input) {
+                Ok(value) => args.last_mut().unwrap().push(value),
+                Err(e) => {
+                    if input.skip_char(')').is_ok() {
+                        break;
+                    } else if input.skip_char(',').is_ok() {
+                        args.push(Vec::new());
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        if args.len() != func_type.arity() {
+            return Err(format!("{}() takes {} argument(s), but was given {}", func_type.name(), func_type.arity(), args.len()));
+        }
+        Ok(FuncNode::BuiltinFunction(func_type, args))
+    })
+}
+
+fn parse_parentheses(interior_parser: NodeParser) -> NodeParser {
+    Arc::new(move | input: &mut ParseInput | {
+        input.skip_char('(')?;
+        let mut output: Vec<FuncNode> = Vec::new();
+        loop {
+            input.skip_spaces_and_newlines();
+            match interior_parser(input) {
+                Ok(value) => {
+                    output.push(value);
+                },
+                Err(e) => {
+                    if input.skip_char(')').is_ok() {
+                        break;
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(FuncNode::Parentheses(output))
+    })
+}
+
+fn try_parsers_with_list(parsers: Arc<RwLock<Vec<NodeParser>>>) -> NodeParser {
+    Arc::new(move | input: &mut ParseInput | -> Result<FuncNode, String> {
+        let save_point = input.create_save_point();
+        let mut last_err = String::new();
+        match parsers.read() {
+            Ok(parser_list) => {
+                for parser in parser_list.iter() {
+                    input.skip_spaces_and_newlines();
+                    match parser(input) {
+                        Ok(x) => return Ok(x),
+                        Err(err) => {
+                            last_err = err;
+                            input.load_save_point(save_point);
+                        }
+                    }
+                }
+            },
+            Err(e) => {
+                return Err(e.to_string())
+            },
+        }
+        Err(last_err)
+    })
+}
+
+/// Evaluates the nodes of a formula at one point, redoing the operator precedence pass every time
+pub fn translate_nodes(nodes: &[FuncNode], time_elapsed: f32, point_pos: Vec3) -> Result<f32, String> {
+    let cleaned_nodes: Vec<FuncNodeCleaned> = nodes.iter().map(|n| n.simplify_to_f32(time_elapsed, point_pos)).collect();
+    compile_cleaned_nodes(cleaned_nodes)
+}
+
+fn compile_cleaned_nodes(nodes: Vec<FuncNodeCleaned>) -> Result<f32, String> {
+    let mut numbers: Vec<f32> = Vec::new();
+    let mut operators: Vec<Operator> = Vec::new();
+    for current in nodes.iter() {
+        match current {
+            FuncNodeCleaned::BinaryOperationSymbol(oper) => {
+                if let Some(stash_op) = operators.last() {
+                    if stash_op.get_precedence() > oper.get_precedence() {
+                        match (operators.pop(), numbers.pop(), numbers.pop()) {
+                            (Some(oper), Some(right_operand), Some(left_operand)) => numbers.push(oper.run(left_operand, right_operand)),
+                            _ => return Err("Erroneous binary operation attempted".to_string()),
+                        }
+                    }
+                }
+                operators.push(*oper);
+            },
+            FuncNodeCleaned::Float(x) => numbers.push(*x),
+            FuncNodeCleaned::Int(x) => numbers.push(*x as f32),
+            FuncNodeCleaned::BuiltinFunction(func_type, args) => {
+                let mut values = [0.0f32; MAX_ARITY];
+                for (value, arg) in values.iter_mut().zip(args.iter()) {
+                    *value = compile_cleaned_nodes(arg.to_vec())?;
+                }
+                numbers.push(func_type.perform_f32_func(&values[..args.len()]))
+            },
+            FuncNodeCleaned::Parentheses(inner_nodes) => numbers.push(compile_cleaned_nodes(inner_nodes.to_vec())?),
+        }
+    }
+
+    // Cleanup remaining operations
+    for operation in operators {
+        match (numbers.pop(), numbers.pop()) {
+            (Some(right_operand), Some(left_operand)) => numbers.push(operation.run(left_operand, right_operand)),
+            _ => return Err("Erroneous binary operation attempted".to_string()),
+        }
+    }
+
+    numbers.first().copied().ok_or_else(|| { "Input is empty.".to_owned() })
+}
+
+/// The original parser, reading a formula into the flat list of nodes `translate_nodes` evaluates
+pub struct LegacyParser {
+    main_parser: NodeParser,
+}
+
+impl LegacyParser {
+    pub fn new() -> Self {
+        let parsers: Arc<RwLock<Vec<NodeParser>>> = Arc::new(RwLock::new(vec!(
+            Arc::new(parse_binary_operation_symbol),
+            Arc::new(parse_time),
+            Arc::new(parse_point_x),
+            Arc::new(parse_point_y),
+            Arc::new(parse_point_z),
+            Arc::new(parse_integer),
+        )));
+
+        let main_parser = try_parsers_with_list(parsers.clone());
+
+        let mut edit_parser_list = parsers.write().unwrap();
+
+        let builtin_func_parser = parse_builtin_func(Arc::clone(&main_parser));
+        edit_parser_list.insert(0, Arc::clone(&builtin_func_parser));
+
+        let parentheses_parser = parse_parentheses(Arc::clone(&main_parser));
+        edit_parser_list.insert(1, Arc::clone(&parentheses_parser));
+
+        LegacyParser {
+            main_parser: Arc::clone(&main_parser),
+        }
+    }
+
+    /// Parses the input into the flat list of nodes consumed by `translate_nodes`
+    pub fn parse_nodes(&self, input: &str) -> Result<Vec<FuncNode>, String> {
+        let mut parse_input = ParseInput::new(input.to_owned());
+
+        let mut output_nodes: Vec<FuncNode> = Vec::new();
+
+        while !parse_input.finished() {
+            output_nodes.push((self.main_parser)(&mut parse_input)?);
+        }
+        Ok(output_nodes)
+    }
+}
+
+impl Default for LegacyParser {
+    fn default() -> Self {
+        LegacyParser::new()
+    }
+}
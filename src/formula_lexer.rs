@@ -0,0 +1,171 @@
+use super::formula_error::{FormulaError, Span};
+use super::parsing::ParseInput;
+use super::parsing_function::Operator;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Number(f32),
+    /// A variable or function name, taking every letter, digit and underscore that follows
+    Identifier(String),
+    Operator(Operator),
+    OpenParen,
+    CloseParen,
+    Comma,
+    /// `√`, taking the square root of the value after it
+    SquareRoot,
+    /// Text that couldn't be read as a token, already reported by the lexer
+    Invalid(String),
+}
+
+impl TokenKind {
+    /// How the token is referred to in error messages
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Number(x) => format!("number `{}`", x),
+            TokenKind::Identifier(name) => format!("`{}`", name),
+            TokenKind::Operator(oper) => format!("operator `{}`", oper.symbol()),
+            TokenKind::OpenParen => "`(`".to_string(),
+            TokenKind::CloseParen => "`)`".to_string(),
+            TokenKind::Comma => "`,`".to_string(),
+            TokenKind::SquareRoot => "`√`".to_string(),
+            TokenKind::Invalid(text) => format!("`{}`", text),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Splits a formula into tokens, skipping whitespace between them
+pub fn tokenize(text: &str) -> Result<Vec<Token>, FormulaError> {
+    let (tokens, errors) = tokenize_with_errors(text);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(tokens),
+    }
+}
+
+/// Splits a formula into tokens, carrying on past unreadable text.
+/// Each problem is returned as an error and left in the tokens as `TokenKind::Invalid`.
+pub fn tokenize_with_errors(text: &str) -> (Vec<Token>, Vec<FormulaError>) {
+    let mut input = ParseInput::new(text.to_owned());
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut errors: Vec<FormulaError> = Vec::new();
+    loop {
+        input.skip_any_of_chars(&[' ', '\t', '\n', '\r']);
+        let start = input.position;
+        let first_char = match input.get_next_char() {
+            Some(parsed_char) => parsed_char,
+            None => return (tokens, errors),
+        };
+        let span_from_start = |input: &ParseInput| Span {
+            start,
+            end: input.position,
+            byte_start: first_char.byte_offset,
+            byte_end: input.byte_position(),
+            line: first_char.line,
+            column: first_char.column,
+        };
+
+        let kind = if first_char.char.is_ascii_digit() || first_char.char == '.' {
+            let text = pop_number(&mut input);
+            match str::parse::<f32>(&text) {
+                Ok(value) => TokenKind::Number(value),
+                Err(_) => {
+                    errors.push(FormulaError::new(format!("Invalid number `{}`", text), Some(span_from_start(&input))));
+                    TokenKind::Invalid(text)
+                },
+            }
+        } else if is_identifier_start(first_char.char) {
+            let mut name = String::new();
+            while let Some(c) = input.get_next_plain_char().filter(|c| is_identifier_continue(*c)) {
+                name.push(c);
+                input.skip_next_char();
+            }
+            TokenKind::Identifier(name)
+        } else if superscript_digit(first_char.char).is_some() {
+            // `x²` is read as `x ^ 2`, both tokens pointing at the superscript
+            let mut digits = String::new();
+            while let Some(digit) = input.get_next_plain_char().and_then(superscript_digit) {
+                digits.push(digit);
+                input.skip_next_char();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Operator(Operator::Exponentiation),
+                span: span_from_start(&input),
+            });
+            match str::parse::<f32>(&digits) {
+                Ok(value) => TokenKind::Number(value),
+                Err(_) => TokenKind::Invalid(digits),
+            }
+        } else {
+            input.skip_next_char();
+            match first_char.char {
+                '+' => TokenKind::Operator(Operator::Addition),
+                '-' => TokenKind::Operator(Operator::Subtraction),
+                '*' | '×' | '·' | '⋅' => TokenKind::Operator(Operator::Multiplication),
+                '/' | '÷' => TokenKind::Operator(Operator::Division),
+                '−' => TokenKind::Operator(Operator::Subtraction),
+                '^' => TokenKind::Operator(Operator::Exponentiation),
+                '(' => TokenKind::OpenParen,
+                ')' => TokenKind::CloseParen,
+                ',' => TokenKind::Comma,
+                '√' => TokenKind::SquareRoot,
+                'π' => TokenKind::Identifier("pi".to_string()),
+                'τ' => TokenKind::Identifier("tau".to_string()),
+                other => {
+                    errors.push(FormulaError::new(format!("Unexpected character `{}`", other), Some(span_from_start(&input))));
+                    TokenKind::Invalid(other.to_string())
+                },
+            }
+        };
+        tokens.push(Token {
+            kind,
+            span: span_from_start(&input),
+        });
+    }
+}
+
+/// Pops digits with at most one decimal point
+fn pop_number(input: &mut ParseInput) -> String {
+    let mut output = String::new();
+    let mut seen_point = false;
+    while let Some(c) = input.get_next_plain_char() {
+        if c.is_ascii_digit() || (c == '.' && !seen_point) {
+            seen_point |= c == '.';
+            output.push(c);
+            input.skip_next_char();
+        } else {
+            break;
+        }
+    }
+    output
+}
+
+/// `π` and `τ` are letters, but stand alone as constants so `2π` and `πx` split the way they read
+fn is_constant_symbol(c: char) -> bool {
+    c == 'π' || c == 'τ'
+}
+
+fn is_identifier_start(c: char) -> bool {
+    (c.is_alphabetic() || c == '_') && !is_constant_symbol(c)
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    (c.is_alphanumeric() || c == '_') && !is_constant_symbol(c) && superscript_digit(c).is_none()
+}
+
+/// The plain digit for a superscript digit such as `²`
+fn superscript_digit(c: char) -> Option<char> {
+    match c {
+        '⁰' => Some('0'),
+        '¹' => Some('1'),
+        '²' => Some('2'),
+        '³' => Some('3'),
+        '⁴'..='⁹' => char::from_u32(c as u32 - '⁴' as u32 + '4' as u32),
+        _ => None,
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+
+use glam::Vec3;
+
+use super::noise::MAX_OCTAVES;
+use super::parsing_function::{FunctionType, Operator, MAX_ARITY};
+
+/// A formula as a tree of operations, as produced by the parser.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Time,
+    PointX,
+    PointY,
+    PointZ,
+    /// `r`, `theta` and `phi`, the parameters of the layout the point was placed by, like its ring's radius and its
+    /// angle around the ring in the cylindrical layout. They are stored when the point is placed, so they stay the
+    /// same however far the formulas move it. Points placed without them read the spherical coordinates of where
+    /// they are now.
+    PointR,
+    PointTheta,
+    PointPhi,
+    /// A value loaded with each point, like a column of a CSV file, as its index in `FormulaParser::columns` and
+    /// its name
+    Column(usize, String),
+    /// A value set outside the formula, like a slider in the app, as its name and the value it had when the formula
+    /// was parsed. Evaluates like a constant, but keeps its name so the WGSL generator can read it from the uniforms.
+    Parameter(String, f32),
+    Constant(f32),
+    Function(FunctionType, Vec<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Walks the tree to compute the value of the formula at a single point, reading columns as NaN
+    pub fn evaluate(&self, time_elapsed: f32, point_pos: Vec3) -> f32 {
+        self.evaluate_with_columns(time_elapsed, point_pos, &[])
+    }
+
+    /// Walks the tree to compute the value of the formula at a single point with the given column values, as if
+    /// the point had been placed where it is
+    pub fn evaluate_with_columns(&self, time_elapsed: f32, point_pos: Vec3, columns: &[f32]) -> f32 {
+        self.evaluate_with_layout(time_elapsed, point_pos, spherical_coordinates(point_pos), columns)
+    }
+
+    /// Walks the tree to compute the value of the formula at a single point with the `r`, `theta` and `phi` it was
+    /// placed with and its column values
+    pub fn evaluate_with_layout(&self, time_elapsed: f32, point_pos: Vec3, layout: [f32; 3], columns: &[f32]) -> f32 {
+        match self {
+            Expr::Time => time_elapsed,
+            Expr::PointX => point_pos.x,
+            Expr::PointY => point_pos.y,
+            Expr::PointZ => point_pos.z,
+            Expr::PointR => layout[0],
+            Expr::PointTheta => layout[1],
+            Expr::PointPhi => layout[2],
+            Expr::Column(index, _) => columns.get(*index).copied().unwrap_or(f32::NAN),
+            Expr::Parameter(_, x) | Expr::Constant(x) => *x,
+            Expr::Function(func_type, args) => {
+                let mut values = [0.0f32; MAX_ARITY];
+                for (value, arg) in values.iter_mut().zip(args.iter()) {
+                    *value = arg.evaluate_with_layout(time_elapsed, point_pos, layout, columns);
+                }
+                func_type.perform_f32_func(&values[..args.len()])
+            },
+            Expr::Binary(oper, left, right) => oper.run(
+                left.evaluate_with_layout(time_elapsed, point_pos, layout, columns),
+                right.evaluate_with_layout(time_elapsed, point_pos, layout, columns),
+            ),
+        }
+    }
+
+    /// Rough work needed to evaluate the formula at one point, in units of one addition
+    pub fn cost(&self) -> u32 {
+        match self {
+            Expr::Time | Expr::PointX | Expr::PointY | Expr::PointZ => 1,
+            Expr::PointR | Expr::PointTheta | Expr::PointPhi => 1,
+            Expr::Column(_, _) | Expr::Parameter(_, _) | Expr::Constant(_) => 1,
+            Expr::Function(func_type, args) => {
+                let args_cost = args.iter().fold(0u32, |total, arg| total.saturating_add(arg.cost()));
+                let calls = match (func_type, args.get(3)) {
+                    (FunctionType::Fbm, Some(Expr::Constant(octaves))) => octaves.floor().clamp(1., MAX_OCTAVES) as u32,
+                    (FunctionType::Fbm, _) => MAX_OCTAVES as u32,
+                    _ => 1,
+                };
+                args_cost.saturating_add(func_type.cost().saturating_mul(calls))
+            },
+            Expr::Binary(oper, left, right) => left.cost().saturating_add(right.cost()).saturating_add(oper.cost()),
+        }
+    }
+
+    /// Folds operations whose operands are all constants and drops ones that leave their operand unchanged,
+    /// like `x * 1`, `x + 0` and `x ^ 1`. The result evaluates to the same value as the original at every point.
+    pub fn simplify(&self) -> Expr {
+        match self {
+            Expr::Function(func_type, args) => {
+                let args: Vec<Expr> = args.iter().map(Expr::simplify).collect();
+                match args.iter().map(Expr::constant_value).collect::<Option<Vec<f32>>>() {
+                    Some(values) => Expr::Constant(func_type.perform_f32_func(&values)),
+                    None => Expr::Function(*func_type, args),
+                }
+            },
+            Expr::Binary(oper, left, right) => {
+                let (left, right) = (left.simplify(), right.simplify());
+                match (oper, left.constant_value(), right.constant_value()) {
+                    (_, Some(left), Some(right)) => Expr::Constant(oper.run(left, right)),
+                    (Operator::Addition, Some(0.), _) => right,
+                    (Operator::Addition | Operator::Subtraction, _, Some(0.)) => left,
+                    (Operator::Multiplication, Some(1.), _) => right,
+                    (Operator::Multiplication | Operator::Division | Operator::Exponentiation, _, Some(1.)) => left,
+                    // powf gives 1 for these even when the other operand is NaN
+                    (Operator::Exponentiation, _, Some(0.)) => Expr::Constant(1.),
+                    (Operator::Exponentiation, Some(1.), _) => Expr::Constant(1.),
+                    _ => Expr::Binary(*oper, Box::new(left), Box::new(right)),
+                }
+            },
+            leaf => leaf.clone(),
+        }
+    }
+
+    fn constant_value(&self) -> Option<f32> {
+        match self {
+            Expr::Constant(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Draws the tree with one node per line and each node's operands indented below it
+    pub fn tree_diagram(&self) -> String {
+        let mut output = String::new();
+        self.write_tree(&mut output, "", "");
+        output
+    }
+
+    fn write_tree(&self, output: &mut String, first_prefix: &str, prefix: &str) {
+        output.push_str(first_prefix);
+        let children: Vec<&Expr> = match self {
+            Expr::Constant(x) => {
+                output.push_str(&x.to_string());
+                Vec::new()
+            },
+            Expr::Function(func_type, args) => {
+                output.push_str(func_type.name());
+                args.iter().collect()
+            },
+            Expr::Binary(oper, left, right) => {
+                output.push(oper.symbol());
+                vec![left, right]
+            },
+            leaf => {
+                output.push_str(&leaf.to_string());
+                Vec::new()
+            },
+        };
+        output.push('\n');
+        for (index, child) in children.iter().enumerate() {
+            let (branch, continuation) = if index + 1 == children.len() { ("└── ", "    ") } else { ("├── ", "│   ") };
+            child.write_tree(output, &format!("{}{}", prefix, branch), &format!("{}{}", prefix, continuation));
+        }
+    }
+}
+
+/// `r`, `theta` and `phi` of a point: its distance from the origin, its angle around the y axis from the positive x
+/// axis towards the positive z axis, and its angle down from the positive y axis, from 0 straight up to pi straight
+/// down
+pub fn spherical_coordinates(point: Vec3) -> [f32; 3] {
+    let (x, y, z) = (point.x, point.y, point.z);
+    [(x * x + y * y + z * z).sqrt(), z.atan2(x), (x * x + z * z).sqrt().atan2(y)]
+}
+
+/// Prints the formula in the syntax the parser reads, so parsing the text gives back the same tree.
+/// Parentheses are only added where precedence or associativity needs them. Constants the syntax can't write
+/// directly come out as arithmetic: `(0 - 2.5)` for negatives, `(1 / 0)` for infinity and `(0 / 0)` for NaN.
+/// Negative zero is printed as `0`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Time => write!(f, "time"),
+            Expr::PointX => write!(f, "x"),
+            Expr::PointY => write!(f, "y"),
+            Expr::PointZ => write!(f, "z"),
+            Expr::PointR => write!(f, "r"),
+            Expr::PointTheta => write!(f, "theta"),
+            Expr::PointPhi => write!(f, "phi"),
+            Expr::Column(_, name) | Expr::Parameter(name, _) => write!(f, "{}", name),
+            Expr::Constant(x) => write_constant(f, *x),
+            Expr::Function(func_type, args) => {
+                write!(f, "{}(", func_type.name())?;
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            },
+            Expr::Binary(oper, left, right) => {
+                write_operand(f, left, *oper, false)?;
+                write!(f, " {} ", oper.symbol())?;
+                write_operand(f, right, *oper, true)
+            },
+        }
+    }
+}
+
+fn write_constant(f: &mut fmt::Formatter, x: f32) -> fmt::Result {
+    if x.is_nan() {
+        write!(f, "(0 / 0)")
+    } else if x == f32::INFINITY {
+        write!(f, "(1 / 0)")
+    } else if x == f32::NEG_INFINITY {
+        write!(f, "(0 - 1 / 0)")
+    } else if x < 0. {
+        write!(f, "(0 - {})", -x)
+    } else {
+        // Display never uses exponent notation, which the lexer doesn't read, and round trips exactly
+        write!(f, "{}", x.abs())
+    }
+}
+
+fn write_operand(f: &mut fmt::Formatter, expr: &Expr, parent: Operator, is_right: bool) -> fmt::Result {
+    let needs_parentheses = match expr {
+        Expr::Binary(oper, _, _) => {
+            oper.get_precedence() < parent.get_precedence()
+                || (oper.get_precedence() == parent.get_precedence() && is_right != parent.is_right_associative())
+        },
+        _ => false,
+    };
+    if needs_parentheses {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
@@ -0,0 +1,213 @@
+use super::formula_tree::Expr;
+use super::parsing_function::{FunctionType, Operator};
+
+/// Name of the uniform variable generated functions read `time` and the parameters from
+pub const UNIFORM_VARIABLE: &str = "uniforms";
+/// Name of the point parameter of generated functions
+pub const POINT_PARAMETER: &str = "point";
+/// Name of the parameter holding the `r`, `theta` and `phi` the point was placed with, as x, y and z
+pub const LAYOUT_PARAMETER: &str = "point_layout";
+/// Prefix of the parameters columns are passed in, which keeps column names clear of WGSL keywords and builtins
+pub const COLUMN_PREFIX: &str = "col_";
+/// Prefix of the uniform fields parameters are passed in, which keeps them apart from `time`
+pub const PARAMETER_PREFIX: &str = "param_";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniformField {
+    pub name: String,
+    /// Byte offset of the field inside the uniform buffer
+    pub offset: u32,
+}
+
+/// Layout of the uniform buffer shared by every generated formula function: `time`, then one `f32` per parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniformLayout {
+    pub fields: Vec<UniformField>,
+    /// Size of the buffer in bytes, padded to the 16 byte alignment uniform buffers require
+    pub size: u32,
+}
+
+impl UniformLayout {
+    pub fn new() -> Self {
+        Self::with_parameters(&[])
+    }
+
+    /// Layout with a field for each of the parameters, named with `PARAMETER_PREFIX` in front and in the given order
+    pub fn with_parameters(parameters: &[&str]) -> Self {
+        let names = std::iter::once("time".to_string())
+            .chain(parameters.iter().map(|name| format!("{}{}", PARAMETER_PREFIX, name)));
+        let fields: Vec<UniformField> = names.enumerate()
+            .map(|(index, name)| UniformField { name, offset: index as u32 * 4 })
+            .collect();
+        let size = (fields.len() as u32 * 4).div_ceil(16) * 16;
+        UniformLayout { fields, size }
+    }
+
+    /// WGSL declaration of the uniform struct and its binding
+    pub fn declaration(&self, group: u32, binding: u32) -> String {
+        let mut output = String::from("struct FormulaUniforms {\n");
+        for field in self.fields.iter() {
+            output += &format!("    {}: f32;\n", field.name);
+        }
+        output += "};\n\n";
+        output += &format!("[[group({}), binding({})]]\nvar<uniform> {}: FormulaUniforms;\n", group, binding, UNIFORM_VARIABLE);
+        output
+    }
+}
+
+impl Default for UniformLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Generates a WGSL function named `function_name` that computes the formula for a single point.
+/// `^` becomes WGSL's `pow`, which unlike `f32::powf` is undefined for negative bases.
+/// Formulas reading `r`, `theta` or `phi` take them as a `vec3<f32>` after the point. Columns the formula reads
+/// become `f32` parameters after those, named after the column with `COLUMN_PREFIX` in front and in column order.
+pub fn generate_function(function_name: &str, expr: &Expr) -> String {
+    let mut columns = Vec::new();
+    collect_columns(expr, &mut columns);
+    columns.sort();
+    columns.dedup();
+    let mut parameters = format!("{}: vec3<f32>", POINT_PARAMETER);
+    if uses_layout(expr) {
+        parameters += &format!(", {}: vec3<f32>", LAYOUT_PARAMETER);
+    }
+    for (_, name) in columns {
+        parameters += &format!(", {}{}: f32", COLUMN_PREFIX, name);
+    }
+    format!(
+        "fn {}({}) -> f32 {{\n    return {};\n}}\n",
+        function_name,
+        parameters,
+        generate_expression(expr),
+    )
+}
+
+fn collect_columns<'a>(expr: &'a Expr, columns: &mut Vec<(usize, &'a str)>) {
+    match expr {
+        Expr::Column(index, name) => columns.push((*index, name)),
+        Expr::Function(_, args) => args.iter().for_each(|arg| collect_columns(arg, columns)),
+        Expr::Binary(_, left, right) => {
+            collect_columns(left, columns);
+            collect_columns(right, columns);
+        },
+        _ => {},
+    }
+}
+
+fn uses_layout(expr: &Expr) -> bool {
+    match expr {
+        Expr::PointR | Expr::PointTheta | Expr::PointPhi => true,
+        Expr::Function(_, args) => args.iter().any(uses_layout),
+        Expr::Binary(_, left, right) => uses_layout(left) || uses_layout(right),
+        _ => false,
+    }
+}
+
+/// WGSL implementations of the random and noise functions, matching `noise.rs`
+pub const NOISE_PRELUDE: &str = include_str!("noise.wgsl");
+
+/// Generates a complete shader module containing the uniform declaration and one function per formula.
+/// The noise prelude is only included when a formula calls one of the noise functions.
+pub fn generate_module(layout: &UniformLayout, functions: &[(&str, &Expr)]) -> String {
+    let mut output = layout.declaration(0, 0);
+    if functions.iter().any(|(_, expr)| uses_noise(expr)) {
+        output += "\n";
+        output += NOISE_PRELUDE;
+    }
+    for (function_name, expr) in functions.iter() {
+        output += "\n";
+        output += &generate_function(function_name, expr);
+    }
+    output
+}
+
+/// Generates a WGSL expression, only adding parentheses where precedence requires them
+pub fn generate_expression(expr: &Expr) -> String {
+    match expr {
+        Expr::Time => format!("{}.time", UNIFORM_VARIABLE),
+        Expr::PointX => format!("{}.x", POINT_PARAMETER),
+        Expr::PointY => format!("{}.y", POINT_PARAMETER),
+        Expr::PointZ => format!("{}.z", POINT_PARAMETER),
+        Expr::PointR => format!("{}.x", LAYOUT_PARAMETER),
+        Expr::PointTheta => format!("{}.y", LAYOUT_PARAMETER),
+        Expr::PointPhi => format!("{}.z", LAYOUT_PARAMETER),
+        Expr::Column(_, name) => format!("{}{}", COLUMN_PREFIX, name),
+        Expr::Parameter(name, _) => format!("{}.{}{}", UNIFORM_VARIABLE, PARAMETER_PREFIX, name),
+        Expr::Constant(x) => float_literal(*x),
+        Expr::Function(func_type, args) => {
+            let args: Vec<String> = args.iter().map(generate_expression).collect();
+            format!("{}({})", function_name(*func_type), args.join(", "))
+        },
+        Expr::Binary(Operator::Exponentiation, left, right) => format!("pow({}, {})", generate_expression(left), generate_expression(right)),
+        Expr::Binary(oper, left, right) => format!(
+            "{} {} {}",
+            generate_operand(left, *oper, false),
+            oper.symbol(),
+            generate_operand(right, *oper, true),
+        ),
+    }
+}
+
+fn generate_operand(expr: &Expr, parent: Operator, is_right: bool) -> String {
+    let needs_parentheses = match expr {
+        // Exponentiation is emitted as a function call, so it never needs parentheses
+        Expr::Binary(Operator::Exponentiation, _, _) => false,
+        Expr::Binary(oper, _, _) => {
+            oper.get_precedence() < parent.get_precedence()
+                || (is_right && oper.get_precedence() == parent.get_precedence())
+        },
+        _ => false,
+    };
+    if needs_parentheses {
+        format!("({})", generate_expression(expr))
+    } else {
+        generate_expression(expr)
+    }
+}
+
+fn function_name(func_type: FunctionType) -> &'static str {
+    match func_type {
+        FunctionType::Sin => "sin",
+        FunctionType::Cos => "cos",
+        FunctionType::Tan => "tan",
+        FunctionType::Abs => "abs",
+        FunctionType::Sqrt => "sqrt",
+        FunctionType::Atan2 => "atan2",
+        FunctionType::Rand => "noise_rand",
+        FunctionType::Hash => "noise_hash",
+        FunctionType::Perlin => "noise_perlin",
+        FunctionType::Simplex => "noise_simplex",
+        FunctionType::Fbm => "noise_fbm",
+        FunctionType::Worley => "noise_worley",
+    }
+}
+
+fn uses_noise(expr: &Expr) -> bool {
+    match expr {
+        Expr::Function(func_type, args) => {
+            !matches!(func_type, FunctionType::Sin | FunctionType::Cos | FunctionType::Tan | FunctionType::Abs | FunctionType::Sqrt | FunctionType::Atan2)
+                || args.iter().any(uses_noise)
+        },
+        Expr::Binary(_, left, right) => uses_noise(left) || uses_noise(right),
+        _ => false,
+    }
+}
+
+/// WGSL float literals need a decimal point or exponent, and negative values are wrapped so they can follow an operator.
+/// WGSL has no literal for NaN or infinity, so those are built from their bits. Every NaN comes out as the same quiet
+/// NaN, since the sign and payload arithmetic leaves on one differ between CPUs.
+fn float_literal(x: f32) -> String {
+    if !x.is_finite() {
+        let bits = if x.is_nan() { f32::NAN.to_bits() } else { x.to_bits() };
+        return format!("bitcast<f32>({:#010x}u)", bits);
+    }
+    let literal = format!("{:?}", x);
+    if x.is_sign_negative() {
+        format!("({})", literal)
+    } else {
+        literal
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use bevy::{app::PluginGroupBuilder, prelude::*};
+
+use super::hot_reload::HotReload;
+use super::move_spheres::MoveSpheres;
+use super::pan_camera::AddOrbitCamera;
+use super::persist_settings::PersistSettings;
+use super::scene::Scene;
+use super::spawn_spheres::SpawnSpheres;
+
+/// Everything the graph simulation needs on top of Bevy's `DefaultPlugins`: the orbit camera, a light,
+/// the grid of spheres and the formula window moving them.
+#[derive(Default)]
+pub struct GraphSimPlugin {
+    /// Grid, formulas and camera the simulation starts with
+    pub scene: Scene,
+    /// Number of threads formulas are evaluated on. Uses every logical core when `None`.
+    pub thread_count: Option<usize>,
+    /// Scene file or directory of formula files to apply changes from as they are saved
+    pub watch: Option<PathBuf>,
+    /// Where the "Simulation Options" window starts, or `None` to let egui place it
+    pub options_window_position: Option<[f32; 2]>,
+    /// Store the window layout and simulation as `Settings` to pick up from next launch
+    pub persist_settings: bool,
+}
+
+impl PluginGroup for GraphSimPlugin {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        let scene = &self.scene;
+        let [red, green, blue, alpha] = scene.sphere_color;
+        group
+            .add(AddOrbitCamera {
+                focus: Vec3::from(scene.camera.focus),
+                radius: scene.camera.radius,
+                rotation: Quat::from_array(scene.camera.rotation).normalize(),
+            })
+            .add(AddLight)
+            .add(SpawnSpheres {
+                layout: scene.grid.layout,
+                sphere_x_count: scene.grid.counts[0],
+                sphere_y_count: scene.grid.counts[1],
+                sphere_z_count: scene.grid.counts[2],
+                spacing: scene.grid.spacing,
+                radius: scene.grid.sphere_radius,
+                subdivisions: scene.grid.sphere_subdivisions,
+                color: Color::rgba(red, green, blue, alpha),
+                seed: scene.grid.layout_seed,
+                point_file: scene.grid.point_file.clone(),
+            })
+            .add(MoveSpheres {
+                thread_count: self.thread_count,
+                formulas: scene.formulas.clone(),
+                mode: scene.mode,
+                options_window_position: self.options_window_position,
+            });
+        if self.persist_settings {
+            group.add(PersistSettings);
+        }
+        if let Some(path) = &self.watch {
+            group.add(HotReload { path: path.clone() });
+        }
+    }
+}
+
+/// Lights the scene with a single directional light
+pub struct AddLight;
+
+impl Plugin for AddLight {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_light);
+    }
+}
+
+fn spawn_light(mut commands: Commands) {
+    commands.spawn_bundle(DirectionalLightBundle {
+        ..Default::default()
+    });
+}
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use super::file_watch::{formula_file_paths, read_formula_files, FileWatch};
+use super::move_spheres::UiState;
+use super::pan_camera::PanOrbitCamera;
+use super::parsing_function::FormulaParser;
+use super::scene::Scene;
+use super::scene_files::{place_camera, sphere_grid};
+use super::spawn_spheres::SphereGrid;
+
+/// Applies changes to a scene file, or to the `x.formula`, `y.formula` and `z.formula` files of a directory, as
+/// they are saved. A formula that no longer parses keeps its previous version, with the problem shown in the
+/// "Simulation Options" window. Needs `MoveSpheres`.
+pub struct HotReload {
+    /// Scene file or formula directory to watch
+    pub path: PathBuf,
+}
+
+enum Watched {
+    Scene {
+        path: PathBuf,
+        /// The scene as it was last applied, so the grid and camera are only reset when their part of the file
+        /// changes rather than on every formula edit
+        last: Option<Scene>,
+    },
+    Formulas(PathBuf),
+}
+
+struct Watcher {
+    watched: Watched,
+    files: FileWatch,
+    timer: Timer,
+}
+
+impl Plugin for HotReload {
+    fn build(&self, app: &mut App) {
+        let (watched, files) = if self.path.is_dir() {
+            (Watched::Formulas(self.path.clone()), FileWatch::new(formula_file_paths(&self.path)))
+        } else {
+            (Watched::Scene { path: self.path.clone(), last: None }, FileWatch::new([self.path.clone()]))
+        };
+        app
+            .insert_resource(Watcher {
+                watched,
+                files,
+                timer: Timer::from_seconds(0.25, true),
+            })
+            .add_system(reload_changed_files.after("ui_update"));
+    }
+}
+
+fn reload_changed_files(
+    time: Res<Time>,
+    mut watcher: ResMut<Watcher>,
+    mut ui_state: ResMut<UiState>,
+    mut parser: ResMut<FormulaParser>,
+    mut grid: Option<ResMut<SphereGrid>>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform)>,
+) {
+    let Watcher { watched, files, timer } = &mut *watcher;
+    if !timer.tick(time.delta()).just_finished() || !files.poll() {
+        return;
+    }
+
+    match watched {
+        Watched::Formulas(directory) => {
+            let errors = ui_state.update_valid_formulas(read_formula_files(directory), &parser);
+            ui_state.scene_message = reload_message(directory, &errors);
+        },
+        Watched::Scene { path, last } => match Scene::load(path) {
+            Ok(scene) => {
+                // Formulas that keep their previous text are compiled again against the reloaded parameters, so
+                // none of them runs with values the sliders no longer show
+                scene.formulas.apply_to(&mut parser);
+                ui_state.parameters = scene.formulas.parameters.clone();
+                ui_state.mode = scene.mode;
+                let formulas = &scene.formulas;
+                let texts = [Some(formulas.x.clone()), Some(formulas.y.clone()), Some(formulas.z.clone())];
+                let errors = ui_state.update_valid_formulas(texts, &parser);
+                ui_state.scene_message = reload_message(path, &errors);
+
+                let grid_changed = last.as_ref().is_none_or(|last| last.grid != scene.grid || last.sphere_color != scene.sphere_color);
+                if let (true, Some(grid)) = (grid_changed, grid.as_mut()) {
+                    **grid = sphere_grid(&scene);
+                }
+                if last.as_ref().is_none_or(|last| last.camera != scene.camera) {
+                    for (mut camera, mut transform) in cameras.iter_mut() {
+                        place_camera(&scene.camera, &mut camera, &mut transform);
+                    }
+                }
+                *last = Some(scene);
+            },
+            Err(error) => {
+                ui_state.scene_message = format!("Couldn't reload {}, keeping the previous scene: {}", path.display(), error);
+            },
+        },
+    }
+}
+
+fn reload_message(path: &Path, errors: &[String]) -> String {
+    if errors.is_empty() {
+        format!("Reloaded {}", path.display())
+    } else {
+        format!("Reloaded {}, keeping the previous formula where the new one has a problem:\n{}", path.display(), errors.join("\n"))
+    }
+}
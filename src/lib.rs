@@ -0,0 +1,39 @@
+pub mod parsing;
+pub mod parsing_function;
+pub mod formula_error;
+pub mod formula_lexer;
+pub mod formula_tree;
+pub mod formula_bytecode;
+pub mod formula_wgsl;
+pub mod formula_latex;
+pub mod formula_interval;
+#[doc(hidden)]
+pub mod formula_legacy;
+pub mod noise;
+pub mod point_evaluation;
+pub mod point_layout;
+#[cfg(feature = "import")]
+pub mod point_files;
+pub mod file_watch;
+#[cfg(feature = "scene")]
+pub mod scene;
+#[cfg(feature = "settings")]
+pub mod storage;
+#[cfg(feature = "settings")]
+pub mod settings;
+#[cfg(feature = "settings")]
+pub mod presets;
+#[cfg(feature = "bevy")]
+pub mod pan_camera;
+#[cfg(feature = "bevy")]
+pub mod spawn_spheres;
+#[cfg(feature = "bevy")]
+pub mod move_spheres;
+#[cfg(feature = "bevy")]
+pub mod graph_sim_plugin;
+#[cfg(feature = "bevy")]
+pub mod scene_files;
+#[cfg(feature = "bevy")]
+pub mod hot_reload;
+#[cfg(feature = "bevy")]
+pub mod persist_settings;
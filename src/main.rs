@@ -1,28 +1,201 @@
+use std::{path::PathBuf, process::ExitCode};
+
 use bevy::{
     prelude::*,
     window::{WindowMode, WindowResizeConstraints},
 };
+use bevy_graph_sim::{
+    graph_sim_plugin::GraphSimPlugin,
+    point_evaluation::{parse_grid_counts, SimulationMode},
+    point_layout::PointLayout,
+    scene::Scene,
+    settings::Settings,
+    spawn_spheres::MAX_SUBDIVISIONS,
+};
+use clap::{Parser, ValueEnum};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    /// Apply the formulas to where the previous frame left the points
+    Iterate,
+    /// Apply the formulas to the points' starting positions
+    Plot,
+}
 
-mod pan_camera;
-mod move_spheres;
-mod spawn_spheres;
-mod parsing;
-mod parsing_function;
+#[derive(Clone, Copy, ValueEnum)]
+enum Layout {
+    /// A lattice of X by Y by Z spheres
+    Grid,
+    /// X rings of Z spheres around the y axis, in Y layers
+    Cylindrical,
+    /// X shells of Y × Z spheres around the origin
+    Spherical,
+    /// X by Z spheres on a hexagonal grid, in Y layers
+    Hexagonal,
+    /// X × Y × Z spheres scattered through the lattice's box
+    Random,
+    /// X × Y × Z spheres scattered through the lattice's box, keeping their distance
+    PoissonDisk,
+}
+
+/// Flags left out keep the value from `--scene`, or from the last session when there is no scene
+#[derive(Parser)]
+#[command(name = "bevy_graph_sim", about = "Move a grid of spheres around with formulas")]
+struct Args {
+    /// Scene file to start from, as written by the Save button
+    #[arg(long)]
+    scene: Option<PathBuf>,
+    /// Scene file, or directory of x.formula, y.formula and z.formula files, to apply changes from as they are saved
+    #[arg(long, value_name = "PATH")]
+    watch: Option<PathBuf>,
+    /// Window width in logical pixels
+    #[arg(long)]
+    width: Option<f32>,
+    /// Window height in logical pixels
+    #[arg(long)]
+    height: Option<f32>,
+    /// Cover the whole screen instead of opening a window
+    #[arg(long)]
+    fullscreen: bool,
+    /// How the spheres are laid out
+    #[arg(long, value_enum)]
+    layout: Option<Layout>,
+    /// Number of spheres along each axis, or however the layout uses them
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_grid_counts)]
+    grid: Option<[u32; 3]>,
+    /// Seed scattering the spheres of the random layouts
+    #[arg(long)]
+    layout_seed: Option<u32>,
+    /// CSV, TSV, PLY or PNG heightmap to load the spheres from instead of the layout.
+    /// Extra columns become formula variables.
+    #[arg(long, value_name = "FILE")]
+    points: Option<PathBuf>,
+    /// Distance between neighbouring spheres
+    #[arg(long)]
+    spacing: Option<u32>,
+    /// Radius of each sphere
+    #[arg(long)]
+    sphere_radius: Option<f32>,
+    /// How finely each sphere's mesh is divided, from 0 to 10
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=MAX_SUBDIVISIONS as i64))]
+    sphere_subdivisions: Option<u32>,
+    /// Formula for the new x coordinate
+    #[arg(short = 'x', long)]
+    x_formula: Option<String>,
+    /// Formula for the new y coordinate
+    #[arg(short = 'y', long)]
+    y_formula: Option<String>,
+    /// Formula for the new z coordinate
+    #[arg(short = 'z', long)]
+    z_formula: Option<String>,
+    /// Read `2x` and `(x+1)(x-1)` as multiplication
+    #[arg(long)]
+    implicit_multiplication: bool,
+    /// How the formulas move the spheres each frame
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
+    /// Value of the `seed` constant in formulas
+    #[arg(long)]
+    seed: Option<f32>,
+    /// Threads to evaluate formulas on, every logical core by default
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+}
 
-use pan_camera::AddOrbitCamera;
-use move_spheres::MoveSpheres;
-use spawn_spheres::SpawnSpheres;
+impl Args {
+    /// Overrides the parts of the scene given on the command line
+    fn apply_to(&self, scene: &mut Scene) {
+        if let Some(layout) = self.layout {
+            scene.grid.layout = match layout {
+                Layout::Grid => PointLayout::Grid,
+                Layout::Cylindrical => PointLayout::Cylindrical,
+                Layout::Spherical => PointLayout::Spherical,
+                Layout::Hexagonal => PointLayout::Hexagonal,
+                Layout::Random => PointLayout::Random,
+                Layout::PoissonDisk => PointLayout::PoissonDisk,
+            };
+        }
+        if let Some(layout_seed) = self.layout_seed {
+            scene.grid.layout_seed = layout_seed;
+        }
+        if let Some(points) = &self.points {
+            scene.grid.point_file = Some(points.clone());
+        }
+        if let Some(grid) = self.grid {
+            scene.grid.counts = grid;
+        }
+        if let Some(spacing) = self.spacing {
+            scene.grid.spacing = spacing;
+        }
+        if let Some(sphere_radius) = self.sphere_radius {
+            scene.grid.sphere_radius = sphere_radius;
+        }
+        if let Some(sphere_subdivisions) = self.sphere_subdivisions {
+            scene.grid.sphere_subdivisions = sphere_subdivisions;
+        }
+        if let Some(x_formula) = &self.x_formula {
+            scene.formulas.x = x_formula.clone();
+        }
+        if let Some(y_formula) = &self.y_formula {
+            scene.formulas.y = y_formula.clone();
+        }
+        if let Some(z_formula) = &self.z_formula {
+            scene.formulas.z = z_formula.clone();
+        }
+        if self.implicit_multiplication {
+            scene.formulas.implicit_multiplication = true;
+        }
+        if let Some(mode) = self.mode {
+            scene.mode = match mode {
+                Mode::Iterate => SimulationMode::Iterate,
+                Mode::Plot => SimulationMode::Plot,
+            };
+        }
+        if let Some(seed) = self.seed {
+            scene.formulas.seed = seed;
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if [args.width, args.height].iter().flatten().any(|size| !(size.is_finite() && *size >= 400.)) {
+        eprintln!("--width and --height must be at least 400");
+        return ExitCode::from(2);
+    }
+    if args.sphere_radius.is_some_and(|radius| !(radius.is_finite() && radius > 0.)) {
+        eprintln!("--sphere-radius must be finite and greater than zero");
+        return ExitCode::from(2);
+    }
+
+    let settings = match Settings::load() {
+        Ok(settings) => settings.unwrap_or_default(),
+        Err(error) => {
+            eprintln!("Couldn't read settings, starting from the defaults: {}", error);
+            Settings::default()
+        },
+    };
+    let mut scene = match &args.scene {
+        Some(path) => match Scene::load(path) {
+            Ok(scene) => scene,
+            Err(error) => {
+                eprintln!("Couldn't load scene {}: {}", path.display(), error);
+                return ExitCode::FAILURE;
+            },
+        },
+        None => settings.scene,
+    };
+    args.apply_to(&mut scene);
 
-fn main() {
     App::new()
         .insert_resource(ClearColor(Color::rgba(0.0, 0.0, 0.0, 1.0)))
         .insert_resource(WindowDescriptor {
             transparent: false,
             decorations: true,
-            mode: WindowMode::Windowed,
+            mode: if args.fullscreen { WindowMode::BorderlessFullscreen } else { WindowMode::Windowed },
             title: "Graph Sim".to_string(),
-            width: 1200.,
-            height: 800.,
+            width: args.width.unwrap_or(settings.window_size[0]),
+            height: args.height.unwrap_or(settings.window_size[1]),
             resize_constraints: WindowResizeConstraints {
                 min_height: 400.0,
                 min_width: 400.0,
@@ -31,24 +204,13 @@ fn main() {
             ..Default::default()
         })
         .add_plugins(DefaultPlugins)
-        .add_plugin(AddOrbitCamera)
-        .add_plugin(SpawnSpheres { 
-            sphere_x_count: 25,
-            sphere_y_count: 1,
-            sphere_z_count: 25,
-         })
-        .add_plugin(MoveSpheres)
-        .add_startup_system(setup)
+        .add_plugins(GraphSimPlugin {
+            scene,
+            thread_count: args.threads,
+            watch: args.watch,
+            options_window_position: settings.options_window_position,
+            persist_settings: true,
+        })
         .run();
-}
-
-fn setup(mut commands: Commands) {
-    // commands.spawn_bundle(PerspectiveCameraBundle {
-    //     transform: Transform::from_xyz(0., 0., 500.).looking_at(Vec3::ZERO, Vec3::Y),
-    //     ..Default::default()
-    // });
-
-    commands.spawn_bundle(DirectionalLightBundle {
-        ..Default::default()
-    });
+    ExitCode::SUCCESS
 }
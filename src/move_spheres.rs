@@ -1,81 +1,343 @@
-use crate::{spawn_spheres::OriginalPosition, parsing_function::FormulaParser};
-
-use super::spawn_spheres::Sphere;
-use bevy::prelude::*;
+use super::parsing_function::FormulaParser;
+use super::point_evaluation::{AxisFormulas, PointColumns, SimulationMode};
+use super::point_layout::PointLayout;
+use super::scene::{check_parameter_name, parameter_values, FormulaSettings, GridSettings, Parameter};
+use super::persist_settings::ResetSettings;
+use super::presets::{builtin_presets, categories, load_user_presets, Preset};
+use super::scene_files::{grid_settings, grid_with_settings, ApplyScene, SceneFileEvent, SceneFiles};
+use super::spawn_spheres::{ColumnValues, LayoutParameters, LoadedPoints, OriginalPosition, Sphere, SphereGrid, MAX_SUBDIVISIONS};
+use bevy::{
+    prelude::*,
+    tasks::{TaskPool, TaskPoolBuilder},
+};
 
 use bevy_egui::{egui::{self}, EguiContext, EguiPlugin};
 
-use std::sync::{Arc};
+#[derive(Default)]
+pub struct MoveSpheres {
+    /// Number of threads formulas are evaluated on. Uses every logical core when `None`.
+    pub thread_count: Option<usize>,
+    /// The formulas the window starts with
+    pub formulas: FormulaSettings,
+    pub mode: SimulationMode,
+    /// Where the "Simulation Options" window starts, or `None` to let egui place it
+    pub options_window_position: Option<[f32; 2]>,
+}
 
-pub struct MoveSpheres;
+/// Task pool dedicated to formula evaluation, so its size can be configured separately from Bevy's own pools
+struct EvaluationTaskPool(TaskPool);
 
 impl Plugin for MoveSpheres {
     fn build(&self, app: &mut App) {
 
-        let parser = FormulaParser::new();
+        let parser = self.formulas.parser();
+        let mut ui_state = UiState::new(&self.formulas, &parser);
+        ui_state.mode = self.mode;
+        ui_state.place_options_window = self.options_window_position;
+        match load_user_presets() {
+            Ok(presets) => ui_state.user_presets = presets,
+            Err(error) => ui_state.scene_message = format!("Couldn't load your presets: {}", error),
+        }
+
+        let mut pool_builder = TaskPoolBuilder::new().thread_name("Formula Evaluation".to_string());
+        if let Some(thread_count) = self.thread_count {
+            pool_builder = pool_builder.num_threads(thread_count);
+        }
 
         app
             .add_plugin(EguiPlugin)
+            .add_plugin(SceneFiles)
+            .insert_resource(EvaluationTaskPool(pool_builder.build()))
             .add_event::<ResetEvent>()
+            .insert_resource(ui_state)
             .insert_resource(parser)
-            .add_startup_system(setup_ui_state)
             .add_system(ui_setup.label("ui_update"))
             .add_system(move_spheres.after("ui_update"));
     }
 }
 
-fn setup_ui_state(
-    mut commands: Commands,
-    parser: Res<FormulaParser>,
-) {
-    commands.insert_resource(UiState {
-            x_func: Arc::clone(&parser.parse("x")),
-            // Cool Y formula: sin(x / time * 25) * 25
-            y_func: Arc::clone(&parser.parse("sin(x - time) * 10")),
-            z_func: Arc::clone(&parser.parse("z")),
-            x_string: String::from("x"),
-            y_string: String::from("sin(x - time) * 10"),
-            z_string: String::from("z"),
-            error: "".to_string()
-        });
+pub(crate) struct UiState {
+    pub(crate) formulas: AxisFormulas,
+    pub(crate) x_string: String,
+    pub(crate) y_string: String,
+    pub(crate) z_string: String,
+    /// Values the formulas read by name, with the range of their sliders
+    pub(crate) parameters: Vec<Parameter>,
+    /// Name typed for the next parameter to add
+    pub(crate) new_parameter: String,
+    pub(crate) error: String,
+    pub(crate) mode: SimulationMode,
+    /// File the Save and Load buttons use
+    pub(crate) scene_path: String,
+    /// Outcome of the last save or load
+    pub(crate) scene_message: String,
+    /// Where the options window was drawn last frame
+    pub(crate) options_position: Option<[f32; 2]>,
+    /// Where to move the options window to on the next frame
+    pub(crate) place_options_window: Option<[f32; 2]>,
+    /// Puts the options window back where egui places it by default on the next frame
+    pub(crate) reset_layout: bool,
+    pub(crate) builtin_presets: Vec<Preset>,
+    pub(crate) user_presets: Vec<Preset>,
+    /// Name of the preset last applied or saved
+    pub(crate) preset_name: String,
+    /// Sphere lattice being edited, built by the "Respawn" button
+    pub(crate) grid: GridSettings,
+    /// Text of the point file field, which replaces the layout when it isn't empty
+    pub(crate) point_file: String,
 }
 
-struct UiState {
-    x_func: Arc<dyn Fn(f32, Vec3) -> Result<f32, String> + Send + Sync>,
-    y_func: Arc<dyn Fn(f32, Vec3) -> Result<f32, String> + Send + Sync>,
-    z_func: Arc<dyn Fn(f32, Vec3) -> Result<f32, String> + Send + Sync>,
-    x_string: String,
-    y_string: String,
-    z_string: String,
-    error: String,
+impl UiState {
+    fn new(formulas: &FormulaSettings, parser: &FormulaParser) -> Self {
+        UiState {
+            formulas: AxisFormulas {
+                x_func: parser.parse_formula(&formulas.x),
+                y_func: parser.parse_formula(&formulas.y),
+                z_func: parser.parse_formula(&formulas.z),
+            },
+            x_string: formulas.x.clone(),
+            y_string: formulas.y.clone(),
+            z_string: formulas.z.clone(),
+            parameters: formulas.parameters.clone(),
+            new_parameter: String::new(),
+            error: "".to_string(),
+            mode: SimulationMode::default(),
+            scene_path: String::from("scene.ron"),
+            scene_message: String::new(),
+            options_position: None,
+            place_options_window: None,
+            reset_layout: false,
+            builtin_presets: builtin_presets(),
+            user_presets: Vec::new(),
+            preset_name: String::new(),
+            grid: GridSettings::default(),
+            point_file: String::new(),
+        }
+    }
+
+    /// Replaces the text of every formula and the parameters, sets how `parser` reads them, and parses them again
+    pub(crate) fn set_formulas(&mut self, formulas: &FormulaSettings, parser: &mut FormulaParser) {
+        formulas.apply_to(parser);
+        self.x_string = formulas.x.clone();
+        self.y_string = formulas.y.clone();
+        self.z_string = formulas.z.clone();
+        self.parameters = formulas.parameters.clone();
+        self.reparse(parser);
+    }
+
+    pub(crate) fn reparse(&mut self, parser: &FormulaParser) {
+        self.formulas.x_func = parser.parse_formula(&self.x_string);
+        self.formulas.y_func = parser.parse_formula(&self.y_string);
+        self.formulas.z_func = parser.parse_formula(&self.z_string);
+    }
+
+    /// Replaces each formula given with the new text when it parses, and keeps the previous one when it doesn't.
+    /// Every formula is compiled again against `parser`, so kept ones read its current parameters too.
+    /// Returns the problems with the new text of the formulas that were kept.
+    pub(crate) fn update_valid_formulas(&mut self, texts: [Option<String>; 3], parser: &FormulaParser) -> Vec<String> {
+        let reload = parser.reload_formulas([&self.x_string, &self.y_string, &self.z_string], texts);
+        let [x_string, y_string, z_string] = reload.texts;
+        let [x_func, y_func, z_func] = reload.formulas;
+        self.x_string = x_string;
+        self.y_string = y_string;
+        self.z_string = z_string;
+        self.formulas = AxisFormulas { x_func, y_func, z_func };
+        reload.errors
+    }
+
+    pub(crate) fn formula_settings(&self, parser: &FormulaParser) -> FormulaSettings {
+        FormulaSettings {
+            x: self.x_string.clone(),
+            y: self.y_string.clone(),
+            z: self.z_string.clone(),
+            implicit_multiplication: parser.implicit_multiplication,
+            seed: parser.seed,
+            parameters: self.parameters.clone(),
+        }
+    }
 }
 
 struct ResetEvent;
 
+#[allow(clippy::too_many_arguments)]
 fn ui_setup(
     mut egui_context: ResMut<EguiContext>,
     mut ui_state: ResMut<UiState>,
     mut ev_reset: EventWriter<ResetEvent>,
-    parser: Res<FormulaParser>,
+    mut ev_scene: EventWriter<SceneFileEvent>,
+    mut ev_apply: EventWriter<ApplyScene>,
+    mut parser: ResMut<FormulaParser>,
+    mut ev_reset_settings: Option<ResMut<Events<ResetSettings>>>,
+    mut sphere_grid: Option<ResMut<SphereGrid>>,
+    loaded_points: Option<Res<LoadedPoints>>,
 ) {
-    egui::Window::new("Simulation Options").show(egui_context.ctx_mut(), |ui| {
+    // Picks up lattices set from elsewhere, like a loaded scene, so the next respawn starts from them
+    if let Some(sphere_grid) = sphere_grid.as_ref().filter(|sphere_grid| sphere_grid.is_changed()) {
+        ui_state.grid = grid_settings(sphere_grid);
+        ui_state.point_file = ui_state.grid.point_file.as_ref().map_or(String::new(), |path| path.display().to_string());
+    }
+    // The columns of a newly loaded point file become variables, which can change how the formulas read
+    if let Some(loaded_points) = loaded_points.filter(|loaded_points| loaded_points.is_changed()) {
+        if parser.columns != loaded_points.column_names {
+            parser.columns = loaded_points.column_names.clone();
+            ui_state.reparse(&parser);
+        }
+        if let Some(error) = &loaded_points.error {
+            ui_state.scene_message = format!("Couldn't load the points, using the layout instead: {}", error);
+        } else if ui_state.grid.point_file.is_some() {
+            ui_state.scene_message = match loaded_points.column_names.is_empty() {
+                true => format!("Loaded {} points", loaded_points.count),
+                false => format!("Loaded {} points with columns {}", loaded_points.count, loaded_points.column_names.join(", ")),
+            };
+        }
+    }
+    if ui_state.reset_layout {
+        ui_state.reset_layout = false;
+        egui_context.ctx_mut().memory().reset_areas();
+    }
+    let mut window = egui::Window::new("Simulation Options");
+    if let Some(position) = ui_state.place_options_window.take() {
+        window = window.current_pos(position);
+    }
+    let response = window.show(egui_context.ctx_mut(), |ui| {
 
         let mut style: egui::Style = (*ui.ctx().style()).clone();
         style.override_text_style = Some(egui::TextStyle::Heading);
         ui.ctx().set_style(style);
 
+        let mut chosen = None;
+        egui::ComboBox::from_label("Preset")
+            .selected_text(ui_state.preset_name.clone())
+            .show_ui(ui, |ui| {
+                let presets: Vec<&Preset> = ui_state.builtin_presets.iter().chain(&ui_state.user_presets).collect();
+                for category in categories(presets.iter().copied()) {
+                    ui.label(category);
+                    for preset in presets.iter().filter(|preset| preset.category == category) {
+                        let selected = preset.name == ui_state.preset_name;
+                        if ui.selectable_label(selected, preset.name.as_str()).on_hover_text(preset.preview()).clicked() {
+                            chosen = Some((*preset).clone());
+                        }
+                    }
+                }
+            });
+        if let Some(preset) = chosen {
+            ui_state.scene_message = format!("Applied the {} preset", preset.name);
+            ui_state.preset_name = preset.name;
+            ev_apply.send(ApplyScene(preset.scene));
+        }
+
         ui.vertical(|ui| {
             ui.label("X Graph Function: ");
             if ui.text_edit_singleline(&mut ui_state.x_string).changed() {
-                ui_state.x_func = Arc::clone(&parser.parse(&ui_state.x_string))
+                ui_state.formulas.x_func = parser.parse_formula(&ui_state.x_string)
             }
             ui.label("Y Graph Function: ");
             if ui.text_edit_singleline(&mut ui_state.y_string).changed() {
-                ui_state.y_func = Arc::clone(&parser.parse(&ui_state.y_string))
+                ui_state.formulas.y_func = parser.parse_formula(&ui_state.y_string)
             }
             ui.label("Z Graph Function: ");
             if ui.text_edit_singleline(&mut ui_state.z_string).changed() {
-                ui_state.z_func = Arc::clone(&parser.parse(&ui_state.z_string))
+                ui_state.formulas.z_func = parser.parse_formula(&ui_state.z_string)
+            }
+            if ui.checkbox(&mut parser.implicit_multiplication, "Implicit multiplication, like 2x").changed() {
+                ui_state.reparse(&parser);
+            }
+            ui.horizontal(|ui| {
+                ui.label("Seed: ");
+                if ui.add(egui::DragValue::new(&mut parser.seed)).changed() {
+                    ui_state.reparse(&parser);
+                }
+            });
+
+            ui.label("Parameters:");
+            let mut parameters_changed = false;
+            let mut removed = None;
+            for (index, parameter) in ui_state.parameters.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(&parameter.name);
+                    parameters_changed |= ui.add(egui::Slider::new(&mut parameter.value, parameter.min..=parameter.max)).changed();
+                    ui.label("from");
+                    let min = egui::DragValue::new(&mut parameter.min).speed(0.1).clamp_range(f32::MIN..=parameter.max);
+                    let min_changed = ui.add(min).changed();
+                    ui.label("to");
+                    let max = egui::DragValue::new(&mut parameter.max).speed(0.1).clamp_range(parameter.min..=f32::MAX);
+                    let max_changed = ui.add(max).changed();
+                    if min_changed || max_changed {
+                        parameter.value = parameter.value.clamp(parameter.min, parameter.max);
+                        parameters_changed = true;
+                    }
+                    if ui.button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                ui_state.parameters.remove(index);
+                parameters_changed = true;
+            }
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut ui_state.new_parameter);
+                if ui.button("Add Parameter").clicked() {
+                    let name = ui_state.new_parameter.trim().to_string();
+                    match check_parameter_name(&name, &ui_state.parameters) {
+                        Ok(()) => {
+                            ui_state.parameters.push(Parameter::new(&name, 1., 0., 10.));
+                            ui_state.new_parameter.clear();
+                            parameters_changed = true;
+                        },
+                        Err(error) => ui_state.scene_message = error,
+                    }
+                }
+            });
+            if parameters_changed {
+                parser.parameters = parameter_values(&ui_state.parameters);
+                ui_state.reparse(&parser);
+            }
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut ui_state.mode, SimulationMode::Iterate, "Iterate");
+                ui.radio_value(&mut ui_state.mode, SimulationMode::Plot, "Plot");
+            });
+
+            if let Some(sphere_grid) = sphere_grid.as_mut() {
+                ui.label("Spheres:");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Layout")
+                        .selected_text(ui_state.grid.layout.name())
+                        .show_ui(ui, |ui| {
+                            for layout in PointLayout::ALL {
+                                ui.selectable_value(&mut ui_state.grid.layout, layout, layout.name());
+                            }
+                        });
+                    if ui_state.grid.layout.is_random() {
+                        ui.label("Seed: ");
+                        ui.add(egui::DragValue::new(&mut ui_state.grid.layout_seed));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Counts: ");
+                    for count in &mut ui_state.grid.counts {
+                        ui.add(egui::DragValue::new(count).clamp_range(1..=500));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Spacing: ");
+                    ui.add(egui::DragValue::new(&mut ui_state.grid.spacing).clamp_range(1..=100));
+                    ui.label("Radius: ");
+                    ui.add(egui::DragValue::new(&mut ui_state.grid.sphere_radius).speed(0.1).clamp_range(0.1..=50.));
+                    ui.label("Subdivisions: ");
+                    ui.add(egui::DragValue::new(&mut ui_state.grid.sphere_subdivisions).clamp_range(0..=MAX_SUBDIVISIONS));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Points file: ");
+                    ui.text_edit_singleline(&mut ui_state.point_file)
+                        .on_hover_text("CSV, TSV, PLY or PNG heightmap to load the points from. Leave empty to use the layout.");
+                });
+                if ui.button("Respawn").clicked() {
+                    let point_file = ui_state.point_file.trim().to_string();
+                    ui_state.grid.point_file = (!point_file.is_empty()).then(|| point_file.into());
+                    let color = sphere_grid.color;
+                    **sphere_grid = grid_with_settings(&ui_state.grid, color);
+                }
             }
         });
 
@@ -83,12 +345,27 @@ fn ui_setup(
             ui.label("Variables:");
             ui.label("  time: Time passed since simulation start.");
             ui.label("  x, y, and z: The axes of the current graph point.");
+            ui.label("  r, theta and phi: Where the layout placed the point, its distance from the origin, angle around the y axis");
+            ui.label("    and angle down from it. Cylindrical layouts give the radius and angle of the point's ring instead.");
+            ui.label("  pi (π) and tau (τ)");
+            ui.label("  seed: The seed set above.");
+            if !ui_state.parameters.is_empty() {
+                let names: Vec<&str> = ui_state.parameters.iter().map(|parameter| parameter.name.as_str()).collect();
+                ui.label(format!("  {}: The parameters set above.", names.join(", ")));
+            }
+            if !parser.columns.is_empty() {
+                ui.label(format!("  {}: Columns of the points file.", parser.columns.join(", ")));
+            }
 
             ui.label("Functions:");
-            ui.label("  sin() cos() tan()");
+            ui.label("  sin() cos() tan() abs() sqrt() or √ atan2(y, x)");
+            ui.label("  rand(seed) hash(i, j, k)");
+            ui.label("  perlin(x, y, z) simplex(x, y, z)");
+            ui.label("  fbm(x, y, z, octaves) worley(x, y, z)");
 
             ui.label("Binary Operations:");
-            ui.label("  + - * /");
+            ui.label("  + - * / ^");
+            ui.label("  × ÷ and powers like x² also work");
 
             // ui.label("Unary Operations:");
             // ui.label("  -");
@@ -99,59 +376,80 @@ fn ui_setup(
             }
             
             if ui.button("Reset").clicked() {
-                *ui_state = UiState {
-                    x_func: Arc::clone(&parser.parse("x")),
-                    // Cool Y formula: sin(x / time * 25) * 25
-                    y_func: Arc::clone(&parser.parse("y")),
-                    z_func: Arc::clone(&parser.parse("z")),
-                    x_string: String::from("x"),
-                    y_string: String::from("y"),
-                    z_string: String::from("z"),
-                    error: "".to_string()
+                let identity = FormulaSettings {
+                    x: String::from("x"),
+                    y: String::from("y"),
+                    z: String::from("z"),
+                    ..ui_state.formula_settings(&parser)
                 };
+                ui_state.set_formulas(&identity, &mut parser);
+                ui_state.error = String::new();
+                ui_state.scene_message = String::new();
 
                 ev_reset.send(ResetEvent);
             }
+            if let Some(ev_reset_settings) = ev_reset_settings.as_mut() {
+                if ui.button("Reset Settings").clicked() {
+                    ev_reset_settings.send(ResetSettings);
+                }
+            }
+
+            ui.label("Scene File:");
+            ui.text_edit_singleline(&mut ui_state.scene_path);
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    ev_scene.send(SceneFileEvent::Save(ui_state.scene_path.clone().into()));
+                }
+                if ui.button("Load").clicked() {
+                    ev_scene.send(SceneFileEvent::Load(ui_state.scene_path.clone().into()));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut ui_state.preset_name);
+                if ui.button("Save Preset").clicked() {
+                    ev_scene.send(SceneFileEvent::SavePreset(ui_state.preset_name.clone()));
+                }
+            });
+            if !ui_state.scene_message.is_empty() {
+                ui.label(&ui_state.scene_message);
+            }
         });
     });
+    if let Some(response) = response {
+        let position = response.response.rect.min;
+        ui_state.options_position = Some([position.x, position.y]);
+    }
 }
 
 fn move_spheres(
-    mut spheres: Query<(&mut Transform, &OriginalPosition), With<Sphere>>,
+    mut spheres: Query<(&mut Transform, &OriginalPosition, &LayoutParameters, Option<&ColumnValues>), With<Sphere>>,
     time: ResMut<Time>,
     mut ui_state: ResMut<UiState>,
+    parser: Res<FormulaParser>,
     mut ev_reset: EventReader<ResetEvent>,
+    mut columns: Local<PointColumns>,
+    pool: Res<EvaluationTaskPool>,
 ) {
-    ui_state.error = String::new();
-    let reset_event = ev_reset.iter().next();
-    for (mut transform, original_transform) in spheres.iter_mut() {
-        if reset_event.is_some() {
+    if ev_reset.iter().next().is_some() {
+        ui_state.error = String::new();
+        for (mut transform, original_transform, _, _) in spheres.iter_mut() {
             *transform = original_transform.0;
-        } else {
-            match (ui_state.x_func)(time.seconds_since_startup() as f32, transform.translation) {
-                Ok(output) => {
-                    transform.translation.x = output;
-                },
-                Err(e) => {
-                    ui_state.error = e;
-                },
-            }
-            match (ui_state.y_func)(time.seconds_since_startup() as f32, transform.translation) {
-                Ok(output) => {
-                    transform.translation.y = output;
-                },
-                Err(e) => {
-                    ui_state.error = e;
-                },
-            }
-            match (ui_state.z_func)(time.seconds_since_startup() as f32, transform.translation) {
-                Ok(output) => {
-                    transform.translation.z = output;
-                },
-                Err(e) => {
-                    ui_state.error = e;
-                },
-            }
         }
-    }    
+        return;
+    }
+    let time_elapsed = time.seconds_since_startup() as f32;
+
+    columns.clear_with_data_columns(parser.columns.len());
+    for (transform, original_transform, layout, values) in spheres.iter_mut() {
+        let values = values.map_or(&[][..], |values| &values.0);
+        match ui_state.mode {
+            SimulationMode::Iterate => columns.push_with_layout(transform.translation, layout.0, values),
+            SimulationMode::Plot => columns.push_with_layout(original_transform.0.translation, layout.0, values),
+        }
+    }
+    ui_state.error = columns.evaluate(&ui_state.formulas, time_elapsed, &pool.0).join("\n");
+
+    for (index, (mut transform, _, _, _)) in spheres.iter_mut().enumerate() {
+        transform.translation = columns.point(index);
+    }
 }
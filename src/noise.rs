@@ -0,0 +1,174 @@
+//! Deterministic random and noise functions available inside formulas.
+//!
+//! Everything is built on integer hashing plus f32 addition, multiplication, `floor` and `sqrt`,
+//! which IEEE 754 defines exactly, so the same inputs give bit-identical results on every platform.
+//! `noise.wgsl` mirrors these functions for generated shaders.
+
+/// Mixes the bits of a 32 bit integer so nearby inputs give unrelated outputs
+fn mix(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+/// Hash of an integer lattice point
+fn hash_lattice(i: i32, j: i32, k: i32) -> u32 {
+    mix(i as u32 ^ mix(j as u32 ^ mix(k as u32)))
+}
+
+/// Maps a hash to [0, 1) using its top 24 bits, which f32 represents exactly
+fn to_unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 * (1. / 16_777_216.)
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Dot product of the offset with one of twelve edge gradients picked by the hash, as in Ken Perlin's improved noise
+fn gradient(hash: u32, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// A pseudo-random value in [0, 1) determined entirely by `seed`
+pub fn rand(seed: f32) -> f32 {
+    // Adding zero turns -0 into +0, so both give the same value
+    to_unit(mix((seed + 0.).to_bits()))
+}
+
+/// A pseudo-random value in [0, 1) for the integer lattice cell containing (i, j, k)
+pub fn hash(i: f32, j: f32, k: f32) -> f32 {
+    to_unit(hash_lattice(i.floor() as i32, j.floor() as i32, k.floor() as i32))
+}
+
+/// A pseudo-random value in [0, 1) for the integers (i, j, k). Used to scatter points rather than in formulas,
+/// so it has no WGSL counterpart.
+pub(crate) fn hash_integers(i: u32, j: u32, k: u32) -> f32 {
+    to_unit(mix(i ^ mix(j ^ mix(k))))
+}
+
+/// Gradient noise with features about one unit apart, in [-1, 1]
+pub fn perlin(x: f32, y: f32, z: f32) -> f32 {
+    let (floor_x, floor_y, floor_z) = (x.floor(), y.floor(), z.floor());
+    let (i, j, k) = (floor_x as i32, floor_y as i32, floor_z as i32);
+    let (x, y, z) = (x - floor_x, y - floor_y, z - floor_z);
+    let corner = |di: i32, dj: i32, dk: i32| {
+        gradient(
+            hash_lattice(i.wrapping_add(di), j.wrapping_add(dj), k.wrapping_add(dk)),
+            x - di as f32,
+            y - dj as f32,
+            z - dk as f32,
+        )
+    };
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+    let value = lerp(
+        w,
+        lerp(v, lerp(u, corner(0, 0, 0), corner(1, 0, 0)), lerp(u, corner(0, 1, 0), corner(1, 1, 0))),
+        lerp(v, lerp(u, corner(0, 0, 1), corner(1, 0, 1)), lerp(u, corner(0, 1, 1), corner(1, 1, 1))),
+    );
+    value.clamp(-1., 1.)
+}
+
+/// Simplex noise, which has fewer directional artifacts than `perlin`, in [-1, 1]
+pub fn simplex(x: f32, y: f32, z: f32) -> f32 {
+    const SKEW: f32 = 1. / 3.;
+    const UNSKEW: f32 = 1. / 6.;
+
+    // Find the simplex cell containing the point
+    let skew = (x + y + z) * SKEW;
+    let (floor_i, floor_j, floor_k) = ((x + skew).floor(), (y + skew).floor(), (z + skew).floor());
+    let unskew = (floor_i + floor_j + floor_k) * UNSKEW;
+    let (x0, y0, z0) = (x - (floor_i - unskew), y - (floor_j - unskew), z - (floor_k - unskew));
+    let (i, j, k) = (floor_i as i32, floor_j as i32, floor_k as i32);
+
+    // Offsets of the second and third corners depend on which of the six tetrahedra the point is in
+    let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+        if y0 >= z0 {
+            (1, 0, 0, 1, 1, 0)
+        } else if x0 >= z0 {
+            (1, 0, 0, 1, 0, 1)
+        } else {
+            (0, 0, 1, 1, 0, 1)
+        }
+    } else if y0 < z0 {
+        (0, 0, 1, 0, 1, 1)
+    } else if x0 < z0 {
+        (0, 1, 0, 0, 1, 1)
+    } else {
+        (0, 1, 0, 1, 1, 0)
+    };
+
+    let corner = |di: i32, dj: i32, dk: i32, x: f32, y: f32, z: f32| {
+        let t = 0.6 - x * x - y * y - z * z;
+        if t < 0. {
+            0.
+        } else {
+            let t = t * t;
+            t * t * gradient(hash_lattice(i.wrapping_add(di), j.wrapping_add(dj), k.wrapping_add(dk)), x, y, z)
+        }
+    };
+    let value = corner(0, 0, 0, x0, y0, z0)
+        + corner(i1, j1, k1, x0 - i1 as f32 + UNSKEW, y0 - j1 as f32 + UNSKEW, z0 - k1 as f32 + UNSKEW)
+        + corner(i2, j2, k2, x0 - i2 as f32 + 2. * UNSKEW, y0 - j2 as f32 + 2. * UNSKEW, z0 - k2 as f32 + 2. * UNSKEW)
+        + corner(1, 1, 1, x0 - 1. + 3. * UNSKEW, y0 - 1. + 3. * UNSKEW, z0 - 1. + 3. * UNSKEW);
+    (32. * value).clamp(-1., 1.)
+}
+
+/// The most octaves `fbm` will sum
+pub const MAX_OCTAVES: f32 = 16.;
+
+/// Fractal Brownian motion: `octaves` layers of `perlin`, each at double the frequency and half the amplitude, in [-1, 1]
+pub fn fbm(x: f32, y: f32, z: f32, octaves: f32) -> f32 {
+    let octaves = octaves.floor().clamp(1., MAX_OCTAVES) as u32;
+    let mut sum = 0.;
+    let mut total_amplitude = 0.;
+    let mut amplitude = 1.;
+    let mut frequency = 1.;
+    for _ in 0..octaves {
+        sum += amplitude * perlin(x * frequency, y * frequency, z * frequency);
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.;
+    }
+    sum / total_amplitude
+}
+
+/// The largest distance `worley` can return, from a cell corner to the far side of the neighbouring cells
+pub const WORLEY_MAX: f32 = 1.732_051;
+
+/// Cellular noise: the distance to the nearest of one random feature point per unit cell, in [0, `WORLEY_MAX`]
+pub fn worley(x: f32, y: f32, z: f32) -> f32 {
+    let (floor_x, floor_y, floor_z) = (x.floor(), y.floor(), z.floor());
+    let (i, j, k) = (floor_x as i32, floor_y as i32, floor_z as i32);
+    let (x, y, z) = (x - floor_x, y - floor_y, z - floor_z);
+    let mut nearest = f32::INFINITY;
+    for dk in -1..=1 {
+        for dj in -1..=1 {
+            for di in -1..=1 {
+                let cell_hash = hash_lattice(i.wrapping_add(di), j.wrapping_add(dj), k.wrapping_add(dk));
+                let feature_x = di as f32 + to_unit(cell_hash);
+                let feature_y = dj as f32 + to_unit(mix(cell_hash));
+                let feature_z = dk as f32 + to_unit(mix(mix(cell_hash)));
+                let (offset_x, offset_y, offset_z) = (feature_x - x, feature_y - y, feature_z - z);
+                nearest = f32::min(nearest, offset_x * offset_x + offset_y * offset_y + offset_z * offset_z);
+            }
+        }
+    }
+    f32::min(nearest.sqrt(), WORLEY_MAX)
+}
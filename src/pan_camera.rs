@@ -3,11 +3,41 @@ use bevy::{
     input::mouse::{MouseMotion, MouseWheel},
 };
 
-pub struct AddOrbitCamera;
+pub struct AddOrbitCamera {
+    /// The point the camera starts out orbiting
+    pub focus: Vec3,
+    /// Starting distance from `focus`
+    pub radius: f32,
+    /// Starting rotation, which also decides which side of `focus` the camera is on
+    pub rotation: Quat,
+}
+
+impl Default for AddOrbitCamera {
+    fn default() -> Self {
+        let translation = Vec3::new(350., 250., 350.);
+        AddOrbitCamera {
+            focus: Vec3::ZERO,
+            radius: translation.length(),
+            rotation: Transform::from_translation(translation).looking_at(Vec3::ZERO, Vec3::Y).rotation,
+        }
+    }
+}
+
+/// Where `spawn_camera` puts the camera
+struct CameraStart {
+    focus: Vec3,
+    radius: f32,
+    rotation: Quat,
+}
 
 impl Plugin for AddOrbitCamera {
     fn build(&self, app: &mut App) {
         app
+            .insert_resource(CameraStart {
+                focus: self.focus,
+                radius: self.radius,
+                rotation: self.rotation,
+            })
             .add_startup_system(spawn_camera)
             .add_system(pan_orbit_camera);
     }
@@ -15,7 +45,7 @@ impl Plugin for AddOrbitCamera {
 
 /// Tags an entity as capable of panning and orbiting.
 #[derive(Component)]
-struct PanOrbitCamera {
+pub struct PanOrbitCamera {
     /// The "focus point" to orbit around. It is automatically updated when panning the camera
     pub focus: Vec3,
     pub radius: f32,
@@ -86,7 +116,7 @@ fn pan_orbit_camera(
             let yaw = Quat::from_rotation_y(-delta_x);
             let pitch = Quat::from_rotation_x(-delta_y);
             transform.rotation = yaw * transform.rotation; // rotate around global y axis
-            transform.rotation = transform.rotation * pitch; // rotate around local x axis
+            transform.rotation *= pitch; // rotate around local x axis
         } else if pan.length_squared() > 0.0 {
             any = true;
             // make panning distance independent of resolution and FOV,
@@ -109,34 +139,39 @@ fn pan_orbit_camera(
             // emulating parent/child to make the yaw/y-axis rotation behave like a turntable
             // parent = x and y rotation
             // child = z-offset
-            let rot_matrix = Mat3::from_quat(transform.rotation);
-            transform.translation = pan_orbit.focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+            *transform = orbit_transform(pan_orbit.focus, pan_orbit.radius, transform.rotation);
         }
     }
 }
 
+/// Placement of a camera orbiting `focus` at `radius`, turned by `rotation`
+pub fn orbit_transform(focus: Vec3, radius: f32, rotation: Quat) -> Transform {
+    let rot_matrix = Mat3::from_quat(rotation);
+    Transform {
+        translation: focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, radius)),
+        rotation,
+        ..Default::default()
+    }
+}
+
 fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
     let window = windows.get_primary().unwrap();
-    let window = Vec2::new(window.width() as f32, window.height() as f32);
-    window
+    Vec2::new(window.width(), window.height())
 }
 
 /// Spawn a camera like this
-fn spawn_camera(mut commands: Commands) {
-    let translation = Vec3::new(350., 250., 350.);
-    let radius = translation.length();
-
+fn spawn_camera(mut commands: Commands, start: Res<CameraStart>) {
     commands.spawn_bundle(PerspectiveCameraBundle {
         perspective_projection: PerspectiveProjection {
             // Setting a custom far value to increase view distance
             far: 100000.,
             ..Default::default()
         },
-        transform: Transform::from_translation(translation)
-            .looking_at(Vec3::ZERO, Vec3::Y),
+        transform: orbit_transform(start.focus, start.radius, start.rotation),
         ..Default::default()
     }).insert(PanOrbitCamera {
-        radius,
+        focus: start.focus,
+        radius: start.radius,
         ..Default::default()
     });
-}
\ No newline at end of file
+}
@@ -1,43 +1,126 @@
+//! A character cursor with save points and location tracking, plus combinators for building parsers on top of it.
+//!
+//! A parser is any `Fn(&mut ParseInput) -> Result<T, E>`. The error type is left to the caller: the primitives
+//! here fail with `ParseError`, and the combinators accept any error type that primitive errors convert into.
+
+use std::fmt;
+
+/// Cursor over the characters of a text. `position` counts chars, not bytes; use `byte_offset` to index into `text`.
 pub struct ParseInput {
     pub position: usize,
     pub chars: Vec<ParsedChar>,
+    pub text: String,
 }
 
 #[derive(Clone, Copy)]
 pub struct ParseSavePoint(pub usize);
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ParsedChar {
     pub char: char,
     pub line: u32,
+    /// Counted in chars from 1
     pub column: u32,
+    /// Where the char starts in the input text, in bytes
+    pub byte_offset: usize,
 }
 
-impl ToString for ParsedChar {
+impl fmt::Display for ParsedChar {
     // Implementing this allows parsing functions to quickly convert ParsedChar to char without worrying about the other fields.
-    fn to_string(&self) -> String {
-        self.char.to_string()
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.char)
     }
 }
 
 impl ParsedChar {
-    fn display_location(&self) -> String {
+    pub fn display_location(&self) -> String {
         format!("line: {}, column: {}", self.line, self.column)
     }
 }
 
+/// Error produced by the primitive parsers in this module
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// Description of what the parser was looking for
+    pub expected: String,
+    /// The character found instead, or `None` at the end of the input
+    pub found: Option<ParsedChar>,
+}
+
+impl ParseError {
+    pub fn new(expected: impl Into<String>, found: Option<ParsedChar>) -> Self {
+        ParseError {
+            expected: expected.into(),
+            found,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.found {
+            Some(parsed_char) => write!(f, "Expected {} at {}, but found '{}'", self.expected, parsed_char.display_location(), parsed_char.char),
+            None => write!(f, "Expected {}, but found end of parser input", self.expected),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> Self {
+        error.to_string()
+    }
+}
+
 impl ParseInput {
+    /// Line breaks are kept as chars at the end of the line they finish. `\n`, `\r\n` and a lone `\r` all count as one break.
+    pub fn new(text: String) -> Self {
+        let mut chars: Vec<ParsedChar> = Vec::new();
+        let mut line = 1;
+        let mut column = 1;
+        let mut char_indices = text.char_indices().peekable();
+        while let Some((byte_offset, char)) = char_indices.next() {
+            chars.push(
+                ParsedChar {
+                    char,
+                    line,
+                    column,
+                    byte_offset,
+                }
+            );
+            let is_line_break = char == '\n' || (char == '\r' && char_indices.peek().map(|(_, next)| *next) != Some('\n'));
+            if is_line_break {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        ParseInput {
+            position: 0,
+            chars,
+            text,
+        }
+    }
+
+    /// Byte offset into `text` of the char at the given position, or the length of `text` past the end
+    pub fn byte_offset(&self, position: usize) -> usize {
+        self.chars.get(position).map(|parsed_char| parsed_char.byte_offset).unwrap_or(self.text.len())
+    }
+
+    /// Byte offset into `text` of the cursor
+    pub fn byte_position(&self) -> usize {
+        self.byte_offset(self.position)
+    }
+
     /// Gets an option of the next character without moving the cursor
     pub fn get_next_char(&self) -> Option<ParsedChar> {
-        // Cloning the ParsedChar on every get isn't great, but it avoids lots of lifetime headaches.
-        // It may be unavoidable, but there could be a way to work with refernces and juggle the lifetimes better.
-        self.chars.get(self.position).map(|x| x.clone())
+        self.chars.get(self.position).copied()
     }
 
     pub fn get_next_plain_char(&self) -> Option<char> {
-        // Cloning the ParsedChar on every get isn't great, but it avoids lots of lifetime headaches.
-        // It may be unavoidable, but there could be a way to work with refernces and juggle the lifetimes better.
-        self.chars.get(self.position).map(|x| x.clone().char)
+        self.chars.get(self.position).map(|x| x.char)
     }
 
     pub fn create_save_point(&self) -> ParseSavePoint {
@@ -48,253 +131,159 @@ impl ParseInput {
         self.position = save_point;
     }
 
-    pub fn get_next_char_result(&self) -> Result<ParsedChar, String> {
-        match self.get_next_char() {
-            Some(parsed_char) => Ok(parsed_char),
-            None => Err(String::from("Expected character, but found end of parser input"))
-        }
+    pub fn finished(&self) -> bool {
+        self.get_next_char().is_none()
     }
 
-    pub fn get_next_char_predicate(&self, predicate: &dyn Fn(ParsedChar) -> Result<ParsedChar, String>) -> Result<ParsedChar, String> {
+    /// Builds an error saying `expected` was wanted at the cursor
+    pub fn error_expected(&self, expected: impl Into<String>) -> ParseError {
+        ParseError::new(expected, self.get_next_char())
+    }
+
+    pub fn get_next_char_result(&self) -> Result<ParsedChar, ParseError> {
+        self.get_next_char().ok_or_else(|| self.error_expected("character"))
+    }
+
+    /// Gets the next character if it satisfies the predicate, without moving the cursor.
+    /// `expected` describes the accepted characters for the error message.
+    pub fn get_next_char_predicate(&self, predicate: &dyn Fn(char) -> bool, expected: &str) -> Result<ParsedChar, ParseError> {
         match self.get_next_char() {
-            Some(parsed_char) => predicate(parsed_char),
-            None => Err(String::from("Expected character, but found end of parser input"))
+            Some(parsed_char) if predicate(parsed_char.char) => Ok(parsed_char),
+            _ => Err(self.error_expected(expected)),
         }
     }
 
-    pub fn get_next_char_alphabetical(&self) -> Result<ParsedChar, String> {
-        self.get_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_alphabetic() { Ok(parsed_char) } else { Err(format!("Expected alphabetical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
+    pub fn get_next_char_alphabetical(&self) -> Result<ParsedChar, ParseError> {
+        self.get_next_char_predicate(&char::is_alphabetic, "alphabetical character")
     }
 
-    pub fn get_next_char_numerical(&self) -> Result<ParsedChar, String> {
-        self.get_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_numeric() { Ok(parsed_char) } else { Err(format!("Expected numerical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
+    pub fn get_next_char_numerical(&self) -> Result<ParsedChar, ParseError> {
+        self.get_next_char_predicate(&char::is_numeric, "numerical character")
     }
 
-    pub fn get_next_char_alphabetical_or_in_group(&self, accepted_chars: &Vec<char>) -> Result<ParsedChar, String> {
+    pub fn get_next_char_alphabetical_or_in_group(&self, accepted_chars: &[char]) -> Result<ParsedChar, ParseError> {
         self.get_next_char_predicate(
-            &|parsed_char: ParsedChar| {
-                if parsed_char.char.is_alphabetic() || accepted_chars.contains(&parsed_char.char) {
-                    Ok(parsed_char)
-                } else { 
-                    Err(format!("Expected alphabetical character or one of {:?} at {}, but found {}", accepted_chars, parsed_char.display_location(), parsed_char.char)) 
-                }
-            }
+            &|c: char| c.is_alphabetic() || accepted_chars.contains(&c),
+            &format!("alphabetical character or one of {:?}", accepted_chars),
         )
     }
 
-    /// Uses char indices now
     /// Gets an option of the next character and advances the cursor position by one
     pub fn pop_next_char(&mut self) -> Option<ParsedChar> {
-        if let Some(parsed_char) = self.chars.get(self.position) {
-            // Can't borrow self as mutable after immutable borrow by using self.skip_next_char(), but can modify directly?
-            self.position += 1;
-            Some(parsed_char.clone())
-        } else {
-            None
-        }
+        let parsed_char = self.get_next_char()?;
+        self.skip_next_char();
+        Some(parsed_char)
     }
 
-    pub fn pop_next_char_result(&mut self) -> Result<ParsedChar, String> {
-        match self.get_next_char() {
-            Some(parsed_char) => {
-                self.skip_next_char();
-                Ok(parsed_char.clone())
-            },
-            None => Err(String::from("Expected character, but found end of parser input"))
-        }  
+    pub fn pop_next_char_result(&mut self) -> Result<ParsedChar, ParseError> {
+        let parsed_char = self.get_next_char_result()?;
+        self.skip_next_char();
+        Ok(parsed_char)
     }
 
-    pub fn pop_next_char_predicate(&mut self, predicate: &dyn Fn(ParsedChar) -> Result<ParsedChar, String>) -> Result<ParsedChar, String> {
-        match self.get_next_char() {
-            Some(parsed_char) => {
-                let x = predicate(parsed_char);
-                if x.is_ok() {
-                    self.skip_next_char();
-                }
-                x
-            },
-            None => Err(String::from("Expected character, but found end of parser input"))
-        }
+    /// Pops the next character if it satisfies the predicate, leaving the cursor in place otherwise
+    pub fn pop_next_char_predicate(&mut self, predicate: &dyn Fn(char) -> bool, expected: &str) -> Result<ParsedChar, ParseError> {
+        let parsed_char = self.get_next_char_predicate(predicate, expected)?;
+        self.skip_next_char();
+        Ok(parsed_char)
     }
 
-    pub fn pop_next_char_alphabetical(&mut self) -> Result<ParsedChar, String> {
-        self.pop_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_alphabetic() { Ok(parsed_char) } else { Err(format!("Expected alphabetical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
+    pub fn pop_next_char_alphabetical(&mut self) -> Result<ParsedChar, ParseError> {
+        self.pop_next_char_predicate(&char::is_alphabetic, "alphabetical character")
     }
 
-    pub fn pop_next_char_numerical(&mut self) -> Result<ParsedChar, String> {
-        self.pop_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_numeric() { Ok(parsed_char) } else { Err(format!("Expected numerical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
+    pub fn pop_next_char_numerical(&mut self) -> Result<ParsedChar, ParseError> {
+        self.pop_next_char_predicate(&char::is_numeric, "numerical character")
     }
 
-    pub fn pop_next_char_alphabetical_or_in_group(&mut self, accepted_chars: &Vec<char>) -> Result<ParsedChar, String> {
+    pub fn pop_next_char_alphabetical_or_in_group(&mut self, accepted_chars: &[char]) -> Result<ParsedChar, ParseError> {
         self.pop_next_char_predicate(
-            &|parsed_char: ParsedChar| {
-                if parsed_char.char.is_alphabetic() || accepted_chars.contains(&parsed_char.char) {
-                    Ok(parsed_char)
-                } else { 
-                    Err(format!("Expected alphabetical character or one of {:?} at {}, but found {}", accepted_chars, parsed_char.display_location(), parsed_char.char)) 
-                }
-            }
+            &|c: char| c.is_alphabetic() || accepted_chars.contains(&c),
+            &format!("alphabetical character or one of {:?}", accepted_chars),
         )
     }
 
+    /// Moves the cursor forward by one character, stopping at the end of the input
     pub fn skip_next_char(&mut self) {
-        if self.position + 1 < self.chars.len() {
-            self.position += 1;
-        } else {
-            self.position = self.chars.len();
-        }
+        self.position = usize::min(self.position + 1, self.chars.len());
     }
 
-    /// Skips the next x number of characters
+    /// Skips the next x number of characters, stopping at the end of the input
     pub fn skip_x_chars(&mut self, x: usize) {
-        for _ in 0..x {
-            if let Some(_) = self.chars.get(self.position + 1) {
-                self.skip_next_char();
-            }
-        }
+        self.position = usize::min(self.position.saturating_add(x), self.chars.len());
     }
 
-    /// Uses char indices properly.
     /// Gets the text from the cursor position onwards
-    pub fn get_remaining_text(&self) -> Result<String, String> {
-        match self.chars.get(self.position..) {
-            Some(parsed_chars) => {
-                let mut output = String::new();
-                for c in parsed_chars {
-                    output += &c.char.to_string();
-                }
-                Ok(output)
-            },
-            None => {
-                Err("Invalid input access".to_string())
-            }
-        }
+    pub fn get_remaining_text(&self) -> String {
+        self.text[self.byte_position()..].to_string()
     }
 
     /// Gets the next x number of characters without moving the cursor
     pub fn get_next_x_chars(&self, x: usize) -> Option<Vec<ParsedChar>> {
-        let mut output = Vec::new();
-        let mut current_index = self.position;
-        for _ in self.position..x+self.position {
-            if let Some(parsed_char) = self.chars.get(current_index) {
-                output.push(parsed_char.clone());
-                current_index += 1;
-            } else {
-                return None;
-            }
-        }
-        Some(output)
+        self.chars.get(self.position..self.position.checked_add(x)?).map(|parsed_chars| parsed_chars.to_vec())
+    }
+
+    /// Gets the next x number of characters as a string without moving the cursor
+    fn get_next_x_string(&self, x: usize) -> Option<&str> {
+        let end = self.position.checked_add(x).filter(|end| *end <= self.chars.len())?;
+        Some(&self.text[self.byte_position()..self.byte_offset(end)])
     }
 
     /// Determines if the next block of characters is equal to the predicate string
     pub fn match_word(&self, predicate: &str) -> bool {
-        // This is way too complicated, but it should work
-        let input_string = 
-            self.get_next_x_chars(predicate.len())
-                .map(|parsed_chars|
-                    parsed_chars.into_iter().map(|c| c.to_string())
-                        .reduce(|a, b| a + &b)
-                )
-                .flatten();
-        input_string == Some(predicate.to_string())
+        self.get_next_x_string(predicate.chars().count()) == Some(predicate)
     }
 
+    /// Case insensitive version of `match_word`, only ASCII letters are compared without case
     pub fn match_word_ci(&self, predicate: &str) -> bool {
-        // This is way too complicated, but it should work
-        let input_string = 
-            self.get_next_x_chars(predicate.len())
-                .map(|parsed_chars|
-                    parsed_chars.into_iter().map(|c| c.to_string())
-                        .reduce(|a, b| a + &b)
-                )
-                .flatten();
-        input_string.clone().map(|x| x.to_ascii_lowercase()) == Some(predicate.to_string().to_ascii_lowercase())
-    }
-
-    pub fn skip_word(&mut self, predicate: &str) -> Result<(), String> {
-        // This is way too complicated, but it should work
-        let input_string = 
-            self.get_next_x_chars(predicate.len())
-                .map(|parsed_chars|
-                    parsed_chars.into_iter().map(|c| c.to_string())
-                        .reduce(|a, b| a + &b)
-                )
-                .flatten();
-        if input_string.clone().map(|x| x.to_ascii_lowercase()) == Some(predicate.to_string().to_ascii_lowercase()) {
-            for _i in 0..predicate.len() {
-                self.skip_next_char();
-            }
-            return Ok(());
-        } else {
-            return Err(format!("Expected string: '{}', but found '{:?}' instead", predicate, input_string));
-        }
+        self.get_next_x_string(predicate.chars().count())
+            .map(|word| word.eq_ignore_ascii_case(predicate))
+            .unwrap_or(false)
     }
 
-    pub fn finished(&self) -> bool {
-        self.get_next_char().is_none()
+    /// Skips the cursor past a case insensitive match of the predicate string
+    pub fn skip_word(&mut self, predicate: &str) -> Result<(), ParseError> {
+        if self.match_word_ci(predicate) {
+            self.skip_x_chars(predicate.chars().count());
+            Ok(())
+        } else {
+            Err(self.error_expected(format!("'{}'", predicate)))
+        }
     }
 
-    pub fn pop_char(&mut self, predicate: char) -> Result<ParsedChar, String> {
-        match self.get_next_char() {
-            Some(parsed_char) => {
-                if parsed_char.char == predicate {
-                    self.skip_next_char();
-                    Ok(parsed_char)
-                } else {
-                    Err(format!("Expected: '{predicate}' at {}, but found '{}'", parsed_char.display_location(), parsed_char.char))
-                }
-            },
-            None => Err(format!("Expected: '{predicate}', but found end of parse text"))
-        }
+    pub fn pop_char(&mut self, predicate: char) -> Result<ParsedChar, ParseError> {
+        self.pop_next_char_predicate(&|c: char| c == predicate, &format!("'{}'", predicate))
     }
 
-    /// Skips the cursor past an expected character, and returns an error message if the expected character is not found.
-    pub fn skip_char(&mut self, predicate: char) -> Result<(), String> {
-        match self.get_next_char() {
-            Some(parsed_char) => {
-                if parsed_char.char == predicate {
-                    self.skip_next_char();
-                    Ok(())
-                } else {
-                    Err(format!("Expected: '{predicate}' at {}, but found '{}'", parsed_char.display_location(), parsed_char.char))
-                }
-            },
-            None => Err(format!("Expected: '{predicate}', but found end of parse text"))
-        }
+    /// Skips the cursor past an expected character, and returns an error if the expected character is not found.
+    pub fn skip_char(&mut self, predicate: char) -> Result<(), ParseError> {
+        self.pop_char(predicate).map(|_| ())
     }
 
-    /// Skips the cursor past an expected string, and returns an error message if the expected string is not found.
-    pub fn skip_string(&mut self, predicate: &str) -> Result<(), String> {
+    /// Skips the cursor past an expected string, and returns an error if the expected string is not found.
+    pub fn skip_string(&mut self, predicate: &str) -> Result<(), ParseError> {
         if self.match_word(predicate) {
-            self.skip_x_chars(predicate.len());
+            self.skip_x_chars(predicate.chars().count());
             Ok(())
         } else {
-            if let Some(next_char) = self.get_next_char() {
-                Err(format!("Expected keyword '{}' at, {}", predicate, next_char.display_location()))
-            } else {
-                Err(format!("Expected keyword '{}'", predicate))
-            }
+            Err(self.error_expected(format!("keyword '{}'", predicate)))
         }
     }
 
+    /// Skips every consecutive occurrence of the character
     pub fn skip_any_of_char(&mut self, skip_char: char) {
-        while let Some(parsed_char) = self.get_next_char()  {
+        while self.get_next_plain_char() == Some(skip_char) {
             self.skip_next_char();
         }
     }
 
-    pub fn skip_any_of_chars(&mut self, skip_chars: Vec<char>) {
-        loop {
-            match self.get_next_char() {
-                Some(parsed_char) => {
-                    if skip_chars.contains(&parsed_char.char) {
-                        self.skip_next_char()
-                    } else {
-                        break;
-                    }
-                },
-                None => break,
+    /// Skips every consecutive character that is one of the given characters
+    pub fn skip_any_of_chars(&mut self, skip_chars: &[char]) {
+        while let Some(c) = self.get_next_plain_char() {
+            if !skip_chars.contains(&c) {
+                break;
             }
+            self.skip_next_char();
         }
     }
 
@@ -303,55 +292,206 @@ impl ParseInput {
     }
 
     pub fn skip_spaces_and_newlines(&mut self) {
-        self.skip_any_of_chars(vec!(' ', '\n', '\r'));
+        self.skip_any_of_chars(&[' ', '\n', '\r']);
     }
 
     pub fn pop_until_char(&mut self, stop_char: char) -> String {
-        let mut output = "".to_string();
-        while let Some(parsed_char) = self.get_next_char() {
-            if parsed_char.char == stop_char {
-                return output;
-            } else {
-                self.skip_next_char();
-                output += &parsed_char.char.to_string();
+        self.pop_until_chars(&[stop_char])
+    }
+
+    /// Pops characters up to, but not including, the first one of the stop characters
+    pub fn pop_until_chars(&mut self, stop_chars: &[char]) -> String {
+        let mut output = String::new();
+        while let Some(c) = self.get_next_plain_char() {
+            if stop_chars.contains(&c) {
+                break;
             }
+            self.skip_next_char();
+            output.push(c);
         }
         output
     }
+}
 
-    pub fn pop_until_chars(&mut self, stop_chars: Vec<char>) -> String {
-        let mut output = "".to_string();
-        while let Some(parsed_char) = self.get_next_char() {
-            if stop_chars.contains(&parsed_char.char) {
-                return output;
-            } else {
-                self.skip_next_char();
-                output += &parsed_char.char.to_string();
-            }
+/// Anything that can parse a `T` from the input, failing with an `E`
+pub trait Parser<T, E>: Fn(&mut ParseInput) -> Result<T, E> {}
+
+impl<T, E, F: Fn(&mut ParseInput) -> Result<T, E>> Parser<T, E> for F {}
+
+/// Parses a single character matching the predicate
+pub fn satisfy<E: From<ParseError>>(predicate: impl Fn(char) -> bool, expected: impl Into<String>) -> impl Parser<char, E> {
+    let expected = expected.into();
+    move |input: &mut ParseInput| {
+        input.pop_next_char_predicate(&predicate, &expected)
+            .map(|parsed_char| parsed_char.char)
+            .map_err(E::from)
+    }
+}
+
+/// Parses exactly the given character
+pub fn expect_char<E: From<ParseError>>(expected: char) -> impl Parser<char, E> {
+    satisfy(move |c| c == expected, format!("'{}'", expected))
+}
+
+/// Parses exactly the given string, case sensitively
+pub fn expect_string<E: From<ParseError>>(expected: &str) -> impl Parser<String, E> {
+    let expected = expected.to_string();
+    move |input: &mut ParseInput| {
+        input.skip_string(&expected).map_err(E::from)?;
+        Ok(expected.clone())
+    }
+}
+
+/// Skips any spaces, tabs and newlines, never failing
+pub fn whitespace<E>() -> impl Parser<(), E> {
+    |input: &mut ParseInput| {
+        input.skip_any_of_chars(&[' ', '\t', '\n', '\r']);
+        Ok(())
+    }
+}
+
+/// Transforms the output of a successful parse
+pub fn map<T, U, E>(parser: impl Parser<T, E>, f: impl Fn(T) -> U) -> impl Parser<U, E> {
+    move |input: &mut ParseInput| parser(input).map(&f)
+}
+
+/// Runs a parser on the output of a successful parse that can itself fail, such as converting digits into a number
+pub fn and_then<T, U, E>(parser: impl Parser<T, E>, f: impl Fn(T) -> Result<U, E>) -> impl Parser<U, E> {
+    move |input: &mut ParseInput| parser(input).and_then(&f)
+}
+
+/// Tries `first`, then `second` from the same position if it failed.
+/// When both fail, the error of whichever got further into the input is returned and, like `opt` and `many`,
+/// nothing is consumed.
+/// Nest calls to choose between more than two parsers.
+pub fn alt<T, E>(first: impl Parser<T, E>, second: impl Parser<T, E>) -> impl Parser<T, E> {
+    move |input: &mut ParseInput| {
+        let save_point = input.create_save_point();
+        let first_error = match first(input) {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        let first_position = input.position;
+        input.load_save_point(save_point);
+        match second(input) {
+            Ok(value) => Ok(value),
+            Err(second_error) => {
+                let second_position = input.position;
+                input.load_save_point(save_point);
+                if first_position > second_position {
+                    Err(first_error)
+                } else {
+                    Err(second_error)
+                }
+            },
         }
-        output
     }
+}
 
-    pub fn new(text: String) -> Self {
-        let mut chars: Vec<ParsedChar> = Vec::new();
-        let mut line: u32 = 1;
-        for line_text in text.split('\n') {
-            let mut column: u32 = 1;
-            for char in line_text.chars() {
-                chars.push(
-                    ParsedChar {
-                        char,
-                        column,
-                        line,
-                    }
-                );
-                column += 1;
+/// Parses `parser` zero or more times, stopping at the first failure without consuming any of it
+pub fn many<T, E>(parser: impl Parser<T, E>) -> impl Parser<Vec<T>, E> {
+    move |input: &mut ParseInput| {
+        let mut output = Vec::new();
+        repeat(&parser, input, &mut output);
+        Ok(output)
+    }
+}
+
+/// Parses `parser` one or more times, failing with its error if it doesn't match at least once
+pub fn many1<T, E>(parser: impl Parser<T, E>) -> impl Parser<Vec<T>, E> {
+    move |input: &mut ParseInput| {
+        let mut output = vec![parser(input)?];
+        repeat(&parser, input, &mut output);
+        Ok(output)
+    }
+}
+
+fn repeat<T, E>(parser: &impl Parser<T, E>, input: &mut ParseInput, output: &mut Vec<T>) {
+    loop {
+        let save_point = input.create_save_point();
+        match parser(input) {
+            Ok(value) => {
+                output.push(value);
+                // A parser that succeeds without consuming anything would loop forever
+                if input.position == save_point.0 {
+                    return;
+                }
+            },
+            Err(_) => {
+                input.load_save_point(save_point);
+                return;
+            },
+        }
+    }
+}
+
+/// Parses zero or more `item`s separated by `separator`.
+/// A separator not followed by an item is left unconsumed.
+pub fn sep_by<T, S, E>(item: impl Parser<T, E>, separator: impl Parser<S, E>) -> impl Parser<Vec<T>, E> {
+    move |input: &mut ParseInput| {
+        let mut output = Vec::new();
+        let save_point = input.create_save_point();
+        match item(input) {
+            Ok(value) => output.push(value),
+            Err(_) => {
+                input.load_save_point(save_point);
+                return Ok(output);
+            },
+        }
+        loop {
+            let save_point = input.create_save_point();
+            let next = separator(input).and_then(|_| item(input));
+            match next {
+                Ok(value) => output.push(value),
+                Err(_) => {
+                    input.load_save_point(save_point);
+                    return Ok(output);
+                },
             }
-            line += 1;
         }
-        ParseInput {
-            position: 0,
-            chars,
+    }
+}
+
+/// Parses `open`, `inner` and `close` in sequence, keeping only the output of `inner`
+pub fn delimited<O, T, C, E>(open: impl Parser<O, E>, inner: impl Parser<T, E>, close: impl Parser<C, E>) -> impl Parser<T, E> {
+    move |input: &mut ParseInput| {
+        open(input)?;
+        let value = inner(input)?;
+        close(input)?;
+        Ok(value)
+    }
+}
+
+/// Parses `first` then `second`, keeping both outputs
+pub fn pair<A, B, E>(first: impl Parser<A, E>, second: impl Parser<B, E>) -> impl Parser<(A, B), E> {
+    move |input: &mut ParseInput| {
+        let a = first(input)?;
+        let b = second(input)?;
+        Ok((a, b))
+    }
+}
+
+/// Makes a parser optional, giving `None` and consuming nothing when it fails
+pub fn opt<T, E>(parser: impl Parser<T, E>) -> impl Parser<Option<T>, E> {
+    move |input: &mut ParseInput| {
+        let save_point = input.create_save_point();
+        match parser(input) {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => {
+                input.load_save_point(save_point);
+                Ok(None)
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+/// Runs a parser against a whole string, failing if any input is left over
+pub fn parse_all<T, E: From<ParseError>>(parser: impl Parser<T, E>, text: &str) -> Result<T, E> {
+    let mut input = ParseInput::new(text.to_owned());
+    let value = parser(&mut input)?;
+    if input.finished() {
+        Ok(value)
+    } else {
+        Err(E::from(input.error_expected("end of input")))
+    }
+}
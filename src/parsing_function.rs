@@ -1,30 +1,113 @@
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
-use bevy::math::Vec3;
+use glam::Vec3;
 
-use super::parsing::*;
+use super::formula_bytecode::Program;
+use super::formula_error::{closest_match, FormulaError, Span};
+use super::formula_lexer::{tokenize, tokenize_with_errors, Token, TokenKind};
+use super::formula_tree::Expr;
+use super::noise;
 
-#[derive(Clone, Copy, Debug)]
-enum FunctionType {
+/// The most arguments any builtin function takes
+pub const MAX_ARITY: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FunctionType {
     Sin,
     Cos,
     Tan,
     Abs,
+    Sqrt,
+    Atan2,
+    Rand,
+    Hash,
+    Perlin,
+    Simplex,
+    Fbm,
+    Worley,
 }
 
 impl FunctionType {
-    fn perform_f32_func(&self, x: f32) -> f32 {
+    pub const ALL: [FunctionType; 12] = [
+        FunctionType::Sin,
+        FunctionType::Cos,
+        FunctionType::Tan,
+        FunctionType::Abs,
+        FunctionType::Sqrt,
+        FunctionType::Atan2,
+        FunctionType::Rand,
+        FunctionType::Hash,
+        FunctionType::Perlin,
+        FunctionType::Simplex,
+        FunctionType::Fbm,
+        FunctionType::Worley,
+    ];
+
+    /// The name used to call the function in a formula
+    pub fn name(&self) -> &'static str {
+        match self {
+            FunctionType::Sin => "sin",
+            FunctionType::Cos => "cos",
+            FunctionType::Tan => "tan",
+            FunctionType::Abs => "abs",
+            FunctionType::Sqrt => "sqrt",
+            FunctionType::Atan2 => "atan2",
+            FunctionType::Rand => "rand",
+            FunctionType::Hash => "hash",
+            FunctionType::Perlin => "perlin",
+            FunctionType::Simplex => "simplex",
+            FunctionType::Fbm => "fbm",
+            FunctionType::Worley => "worley",
+        }
+    }
+
+    /// The number of arguments the function takes
+    pub fn arity(&self) -> usize {
         match self {
-            FunctionType::Sin => f32::sin(x),
-            FunctionType::Cos => f32::cos(x),
-            FunctionType::Tan => f32::tan(x),
-            FunctionType::Abs => f32::abs(x),
+            FunctionType::Sin | FunctionType::Cos | FunctionType::Tan | FunctionType::Abs | FunctionType::Sqrt | FunctionType::Rand => 1,
+            FunctionType::Atan2 => 2,
+            FunctionType::Hash | FunctionType::Perlin | FunctionType::Simplex | FunctionType::Worley => 3,
+            FunctionType::Fbm => 4,
+        }
+    }
+
+    /// Rough cost of one call in units of one addition, not counting its arguments.
+    /// `fbm` is charged per octave and its octave count is only known when it is a constant.
+    pub fn cost(&self) -> u32 {
+        match self {
+            FunctionType::Abs => 1,
+            FunctionType::Sqrt | FunctionType::Rand => 4,
+            FunctionType::Hash => 6,
+            FunctionType::Sin | FunctionType::Cos | FunctionType::Tan => 8,
+            FunctionType::Atan2 => 12,
+            FunctionType::Simplex => 30,
+            FunctionType::Perlin | FunctionType::Fbm => 40,
+            FunctionType::Worley => 300,
+        }
+    }
+
+    /// Runs the function, `args` must hold exactly `arity()` values
+    pub fn perform_f32_func(&self, args: &[f32]) -> f32 {
+        match self {
+            FunctionType::Sin => f32::sin(args[0]),
+            FunctionType::Cos => f32::cos(args[0]),
+            FunctionType::Tan => f32::tan(args[0]),
+            FunctionType::Abs => f32::abs(args[0]),
+            FunctionType::Sqrt => f32::sqrt(args[0]),
+            // Angle of the point (args[1], args[0]) from the positive x axis, like `atan2(y, x)` elsewhere
+            FunctionType::Atan2 => f32::atan2(args[0], args[1]),
+            FunctionType::Rand => noise::rand(args[0]),
+            FunctionType::Hash => noise::hash(args[0], args[1], args[2]),
+            FunctionType::Perlin => noise::perlin(args[0], args[1], args[2]),
+            FunctionType::Simplex => noise::simplex(args[0], args[1], args[2]),
+            FunctionType::Fbm => noise::fbm(args[0], args[1], args[2], args[3]),
+            FunctionType::Worley => noise::worley(args[0], args[1], args[2]),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Operator {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
     Addition,
     Multiplication,
     Subtraction,
@@ -33,7 +116,7 @@ enum Operator {
 }
 
 impl Operator {
-    fn get_precedence(&self) -> u8 {
+    pub fn get_precedence(&self) -> u8 {
         match self {
             Operator::Addition => 5,
             Operator::Multiplication => 10,
@@ -43,7 +126,29 @@ impl Operator {
         }
     }
 
-    fn run(&self, x: f32, y: f32) -> f32 {
+    pub fn symbol(&self) -> char {
+        match self {
+            Operator::Addition => '+',
+            Operator::Multiplication => '*',
+            Operator::Subtraction => '-',
+            Operator::Division => '/',
+            Operator::Exponentiation => '^',
+        }
+    }
+
+    /// Rough cost of applying the operator in units of one addition
+    pub fn cost(&self) -> u32 {
+        match self {
+            Operator::Exponentiation => 8,
+            _ => 1,
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, Operator::Exponentiation)
+    }
+
+    pub fn run(&self, x: f32, y: f32) -> f32 {
         match *self {
             Operator::Addition => x + y,
             Operator::Multiplication => x * y,
@@ -54,366 +159,519 @@ impl Operator {
     }
 }
 
-#[derive(Clone, Debug)]
-enum FuncNode {
-    Time,
-    PointX,
-    PointY,
-    PointZ,
-    Int(i64),
-    Float(f32),
-    // BinaryOperation(OperationType, Box<FuncNode>, Box<FuncNode>),
-    BuiltinFunction(FunctionType, Vec<FuncNode>),
-    Parentheses(Vec<FuncNode>),
-    BinaryOperationSymbol(Operator),
-}
-
-impl FuncNode {
-    fn simplify_to_f32(&self, time_elapsed: f32, point_pos: Vec3) -> FuncNodeCleaned {
-        match self {
-            FuncNode::Time => FuncNodeCleaned::Float(time_elapsed),
-            FuncNode::PointX => FuncNodeCleaned::Float(point_pos.x),
-            FuncNode::PointY => FuncNodeCleaned::Float(point_pos.y),
-            FuncNode::PointZ => FuncNodeCleaned::Float(point_pos.z),
-            FuncNode::Int(x) => FuncNodeCleaned::Int(*x),
-            FuncNode::Float(x) => FuncNodeCleaned::Float(*x),
-            FuncNode::BuiltinFunction(func_type, node) => FuncNodeCleaned::BuiltinFunction(*func_type, (*node).iter().map(|x| x.simplify_to_f32(time_elapsed, point_pos)).collect()),
-            FuncNode::Parentheses(nodes) => FuncNodeCleaned::Parentheses((*nodes).iter().map(|x| x.simplify_to_f32(time_elapsed, point_pos)).collect()),
-            FuncNode::BinaryOperationSymbol(oper) => FuncNodeCleaned::BinaryOperationSymbol(*oper),
-        }
-    }
-}
+/// Names usable as values in a formula
+const VARIABLE_NAMES: [&str; 10] = ["time", "x", "y", "z", "r", "theta", "phi", "pi", "tau", "seed"];
 
-#[derive(Clone)]
-enum FuncNodeCleaned {
-    Int(i64),
-    Float(f32),
-    BuiltinFunction(FunctionType, Vec<FuncNodeCleaned>),
-    BinaryOperationSymbol(Operator),
-    Parentheses(Vec<FuncNodeCleaned>),
+/// Whether formulas read `name` whole as a single name, like a variable, rather than as a number or several tokens
+pub fn is_identifier(name: &str) -> bool {
+    matches!(tokenize(name).as_deref(), Ok([Token { kind: TokenKind::Identifier(word), .. }]) if word == name)
 }
 
-fn parse_time(input: &mut ParseInput) -> Result<FuncNode, String> {
-    input.skip_word("time")?;
-    Ok(FuncNode::Time)
+/// Whether formulas already read `name` as a built-in variable or function, ignoring case. Parameters and columns
+/// with these names could never be used.
+pub fn is_reserved_name(name: &str) -> bool {
+    VARIABLE_NAMES.iter().any(|variable| variable.eq_ignore_ascii_case(name))
+        || FunctionType::ALL.iter().any(|func_type| func_type.name().eq_ignore_ascii_case(name))
 }
 
-fn parse_point_x(input: &mut ParseInput) -> Result<FuncNode, String> {
-    input.skip_char('x')?;
-    Ok(FuncNode::PointX)
+/// Recursive descent parser over the tokens of a single formula.
+/// Rather than stopping at the first problem it records an error, skips ahead to the next operator, `,` or `)`,
+/// and carries on with a placeholder value so that every problem is reported in one pass.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    /// How many parentheses or argument lists enclose the cursor
+    paren_depth: usize,
+    implicit_multiplication: bool,
+    seed: f32,
+    parameters: &'a [(String, f32)],
+    columns: &'a [String],
+    /// How many expressions enclose the cursor, each one being a level of recursion
+    depth: usize,
+    max_depth: usize,
+    errors: Vec<FormulaError>,
+    /// Whether a value has been read since the last error, errors without one in between describe the same mistake
+    progressed: bool,
+    /// Set once a limit is hit, after which the rest of the input is skipped and nothing more is reported
+    aborted: bool,
 }
 
-fn parse_point_y(input: &mut ParseInput) -> Result<FuncNode, String> {
-    input.skip_char('y')?;
-    Ok(FuncNode::PointY)
-}
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
 
-fn parse_point_z(input: &mut ParseInput) -> Result<FuncNode, String> {
-    input.skip_char('z')?;
-    Ok(FuncNode::PointZ)
-}
+    fn peek_kind(&self) -> Option<&'a TokenKind> {
+        self.peek().map(|token| &token.kind)
+    }
 
-fn parse_binary_operation_symbol(input: &mut ParseInput) -> Result<FuncNode, String> {
-    if input.skip_char('+').is_ok() {
-        Ok(FuncNode::BinaryOperationSymbol(Operator::Addition))
-    } else if input.skip_char('*').is_ok() {
-        Ok(FuncNode::BinaryOperationSymbol(Operator::Multiplication))
-    } else if input.skip_char('-').is_ok() {
-        Ok(FuncNode::BinaryOperationSymbol(Operator::Subtraction))
-    } else if input.skip_char('/').is_ok() {
-        Ok(FuncNode::BinaryOperationSymbol(Operator::Division))
-    } else if input.skip_char('^').is_ok() {
-        Ok(FuncNode::BinaryOperationSymbol(Operator::Exponentiation))
-    } else {
-        Err(format!("Expected +, -, *, or /, but found {:?}", input.get_next_char()))
+    /// Span just past the last token, used to point at the end of the input
+    fn end_span(&self) -> Option<Span> {
+        self.tokens.last().map(|token| Span {
+            start: token.span.end,
+            end: token.span.end,
+            byte_start: token.span.byte_end,
+            byte_end: token.span.byte_end,
+            line: token.span.line,
+            column: token.span.column + (token.span.end - token.span.start) as u32,
+        })
     }
-}
 
-fn parse_integer(input: &mut ParseInput) -> Result<FuncNode, String> {
-    let first_char = input.pop_next_char_numerical()?;
-    let mut output = first_char.to_string();
-    while let Ok(next_char) = input.pop_next_char_numerical() {
-        output += &next_char.to_string();
+    fn error_expected(&self, expected: &str) -> FormulaError {
+        match self.peek() {
+            Some(token) => FormulaError::new(format!("Expected {}, but found {}", expected, token.kind.describe()), Some(token.span)),
+            None => FormulaError::new(format!("Expected {}, but found end of input", expected), self.end_span()),
+        }
     }
-    Ok(FuncNode::Int(str::parse::<i64>(&output).map_err(|err| err.to_string())?))
-}
 
-// fn parse_negation(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String>>) -> Box<dyn Fn(&mut ParseInput) -> Result<FuncNode, String>> {
-//     Box::new(move | input: &mut ParseInput | {
-//         input.skip_char('-')?;
-//         Ok(FuncNode::NegationOperation(Box::new(interior_parser(input)?)))
-//     })
-// }
-
-fn parse_builtin_func(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String> + Send + Sync>) -> Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String> + Send + Sync> {
-    Arc::new(move | input: &mut ParseInput | {
-        let func_type =
-            if input.match_word_ci("sin") {
-                FunctionType::Sin
-            } else if input.match_word_ci("cos") {
-                FunctionType::Cos
-            } else if input.match_word_ci("tan") {
-                FunctionType::Tan
-            } else if input.match_word_ci("abs") {
-                FunctionType::Abs
-            } else {
-                return Err(format!("Expected sin, cos, or tan, but found {:?}", input.get_next_char()))
-            };
-        input.skip_x_chars(3);
-        input.skip_char('(')?;
-        let mut output: Vec<FuncNode> = Vec::new();
-        loop {
-            input.skip_spaces_and_newlines();
-            match interior_parser(input) {
-                Ok(value) => output.push(value),
-                Err(e) => {
-                    if input.skip_char(')').is_ok() {
-                        break;
-                    } else {
-                        return Err(e);
-                    }
+    /// Records an error. When nothing was read since the previous error the two are different explanations
+    /// of one mistake, so only the one that got furthest into the input is kept.
+    fn report(&mut self, error: FormulaError) {
+        if self.aborted {
+            return;
+        }
+        let start = |error: &FormulaError| error.span.map(|span| span.start).unwrap_or(0);
+        match self.errors.last_mut() {
+            Some(last) if !self.progressed => {
+                if start(&error) > start(last) {
+                    *last = error;
                 }
+            },
+            _ => self.errors.push(error),
+        }
+        self.progressed = false;
+    }
+
+    /// Whether the next token multiplies the one before it without a `*`, see `FormulaParser::implicit_multiplication`
+    fn is_implicit_multiplication(&self) -> bool {
+        if !self.implicit_multiplication || self.position == 0 {
+            return false;
+        }
+        let (previous, next) = match (self.tokens.get(self.position - 1), self.peek()) {
+            (Some(previous), Some(next)) => (previous, next),
+            _ => return false,
+        };
+        let touching = previous.span.end == next.span.start;
+        touching && matches!(
+            (&previous.kind, &next.kind),
+            (TokenKind::Number(_), TokenKind::Identifier(_) | TokenKind::SquareRoot) | (TokenKind::CloseParen, TokenKind::OpenParen)
+        )
+    }
+
+    /// Skips to the next operator, `,` or `)`, passing over any parenthesised groups on the way
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        while let Some(kind) = self.peek_kind() {
+            match kind {
+                TokenKind::OpenParen => depth += 1,
+                TokenKind::CloseParen if depth > 0 => depth -= 1,
+                TokenKind::CloseParen | TokenKind::Comma | TokenKind::Operator(_) if depth == 0 => return,
+                _ => (),
             }
+            self.position += 1;
         }
-        Ok(FuncNode::BuiltinFunction(func_type, output))
-    })
-}
+    }
+
+    /// Stands in for a value that couldn't be parsed, the tree is discarded once errors are reported
+    fn placeholder() -> Expr {
+        Expr::Constant(f32::NAN)
+    }
 
-fn parse_parentheses(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String> + Send + Sync>) -> Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String> + Send + Sync> {
-    Arc::new(move | input: &mut ParseInput | {
-        input.skip_char('(')?;
-        let mut output: Vec<FuncNode> = Vec::new();
+    /// Parses an expression one level deeper, giving up on the whole formula at the depth limit before the stack runs out
+    fn parse_expression(&mut self, min_precedence: u8) -> Expr {
+        if self.depth >= self.max_depth {
+            if !self.aborted {
+                let span = self.peek().map(|token| token.span).or_else(|| self.end_span());
+                self.errors.push(FormulaError::new(format!("Formula is nested more than {} levels deep", self.max_depth), span));
+                self.aborted = true;
+            }
+            self.position = self.tokens.len();
+            return Self::placeholder();
+        }
+        self.depth += 1;
+        let expr = self.parse_binary(min_precedence);
+        self.depth -= 1;
+        expr
+    }
+
+    /// Precedence climbing: only operators binding at least as tightly as `min_precedence` are consumed at this level
+    fn parse_binary(&mut self, min_precedence: u8) -> Expr {
+        let mut left = self.parse_operand();
         loop {
-            input.skip_spaces_and_newlines();
-            match interior_parser(input) {
-                Ok(value) => {
-                    output.push(value);
+            let token = match self.peek() {
+                Some(token) => token,
+                None => return left,
+            };
+            let oper = match &token.kind {
+                TokenKind::Operator(oper) => *oper,
+                _ if self.is_implicit_multiplication() => Operator::Multiplication,
+                TokenKind::CloseParen | TokenKind::Comma if self.paren_depth > 0 => return left,
+                TokenKind::CloseParen | TokenKind::Comma => {
+                    // Nothing is open for these to close, drop them and keep going
+                    self.report(self.error_expected("an operator"));
+                    self.position += 1;
+                    continue;
                 },
-                Err(e) => {
-                    if input.skip_char(')').is_ok() {
-                        break;
-                    } else {
-                        return Err(e);
-                    }
-                }
+                TokenKind::Invalid(_) => {
+                    // Already reported by the lexer, read it as an unknown operator between two values
+                    self.position += 1;
+                    self.progressed = true;
+                    self.parse_operand();
+                    left = Self::placeholder();
+                    continue;
+                },
+                _ => {
+                    self.report(self.error_expected("an operator"));
+                    self.synchronize();
+                    continue;
+                },
+            };
+            let precedence = oper.get_precedence();
+            if precedence < min_precedence {
+                return left;
             }
+            if let TokenKind::Operator(_) = token.kind {
+                self.position += 1;
+            }
+            let next_min_precedence = if oper.is_right_associative() { precedence } else { precedence + 1 };
+            let right = self.parse_expression(next_min_precedence);
+            left = Expr::Binary(oper, Box::new(left), Box::new(right));
         }
-        Ok(FuncNode::Parentheses(output))
-    })
-}
+    }
 
-// fn parse_binary_operation(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String>>) -> Box<dyn Fn(&mut ParseInput) -> Result<FuncNode, String>> {
-//     Box::new(move | input: &mut ParseInput | {
-//         let in_parens = input.skip_char('(').is_ok();
-//         let op_type =
-//             match input.get_next_plain_char() {
-//                 Some('+') => OperationType::Add,
-//                 Some('-') => OperationType::Sub,
-//                 Some('*') => OperationType::Mult,
-//                 Some('/') => OperationType::Div,
-//                 Some(c) => return Err(format!("Expected one of +, -, *, or /, but found {}", c)),
-//                 None => return Err(format!("Expected one of +, -, *, or /, but found end of input")),
-//             };
-//         input.skip_next_char();
-//         input.skip_spaces_and_newlines();
-//         let first_value = interior_parser(input)?;
-//         input.skip_spaces_and_newlines();
-//         let second_value = interior_parser(input)?;
-//         if in_parens {
-//             input.skip_char(')')?;
-//         }
-//         Ok(FuncNode::BinaryOperation(op_type, Box::new(first_value), Box::new(second_value)))
-//     })
-// }
-
-fn try_parsers_with_list<'a>(parsers: Arc<RwLock<Vec<Arc<(dyn for<'r> Fn(&'r mut ParseInput) -> Result<FuncNode, String> + Send + Sync)>>>>) -> Arc<dyn for<'r> Fn(&'r mut ParseInput) -> Result<FuncNode, String> + Sync + Send + 'a> {
-    Arc::new(move | input: &mut ParseInput | -> Result<FuncNode, String> {
-        let save_point = input.create_save_point();
-        let mut last_err = String::new();
-        match parsers.read() {
-            Ok(parser_list) => {
-                for parser in parser_list.iter() {
-                    input.skip_spaces_and_newlines();
-                    match parser(input) {
-                        Ok(x) => return Ok(x),
-                        Err(err) => {
-                            last_err = err;
-                            input.load_save_point(save_point);
-                        }
-                    }
+    fn parse_operand(&mut self) -> Expr {
+        let token = match self.peek() {
+            Some(token) => token,
+            None => {
+                self.report(self.error_expected("a value"));
+                return Self::placeholder();
+            },
+        };
+        match &token.kind {
+            TokenKind::Number(x) => {
+                self.position += 1;
+                self.progressed = true;
+                Expr::Constant(*x)
+            },
+            TokenKind::OpenParen => {
+                self.position += 1;
+                self.paren_depth += 1;
+                let inner = self.parse_expression(0);
+                while self.peek_kind() == Some(&TokenKind::Comma) {
+                    self.report(self.error_expected("`)` to close the parentheses"));
+                    self.position += 1;
+                    self.parse_expression(0);
                 }
+                self.paren_depth -= 1;
+                self.expect_close("`)` to close the parentheses");
+                inner
             },
-            Err(e) => {
-                return Err(e.to_string())
+            TokenKind::Identifier(name) => {
+                self.position += 1;
+                self.parse_identifier(name, token.span)
+            },
+            TokenKind::SquareRoot => {
+                // Binds like a function call on everything up to the next operator looser than `^`, so `√x^2` is `√(x^2)`
+                self.position += 1;
+                let operand = self.parse_expression(Operator::Exponentiation.get_precedence());
+                Expr::Function(FunctionType::Sqrt, vec![operand])
+            },
+            TokenKind::Invalid(_) => {
+                self.position += 1;
+                self.progressed = true;
+                Self::placeholder()
+            },
+            TokenKind::Operator(_) | TokenKind::CloseParen | TokenKind::Comma => {
+                // Left in place, the caller recovers from these
+                self.report(self.error_expected("a value"));
+                Self::placeholder()
             },
         }
-        Err(last_err.to_string())
-    })
-}
+    }
 
-fn translate_nodes(nodes: &Vec<FuncNode>, time_elapsed: f32, point_pos: Vec3) -> Result<f32, String> {
-    let cleaned_nodes: Vec<FuncNodeCleaned> = nodes.iter().map(|n| n.simplify_to_f32(time_elapsed, point_pos)).collect();
-    compile_cleaned_nodes(cleaned_nodes)
-}
+    /// Consumes a `)`, which is the only token left at the end of a group besides the end of input
+    fn expect_close(&mut self, expected: &str) {
+        if self.peek_kind() == Some(&TokenKind::CloseParen) {
+            self.position += 1;
+        } else {
+            self.report(self.error_expected(expected));
+        }
+    }
 
-fn compile_cleaned_nodes(nodes: Vec<FuncNodeCleaned>) -> Result<f32, String> {
-    let mut numbers: Vec<f32> = Vec::new();
-    let mut operators: Vec<Operator> = Vec::new();
-    for current in nodes.iter() {
-        match &*current {
-            FuncNodeCleaned::BinaryOperationSymbol(oper) => {
-                if let Some(stash_op) = operators.last() {
-                    if stash_op.get_precedence() > oper.get_precedence() {
-                        let oper = operators.pop();
-                        let right_operand = numbers.pop();
-                        let left_operand = numbers.pop();
-                        if oper.is_none() || right_operand.is_none() || left_operand.is_none() {
-                            return Err("Erroneous binary operation attempted".to_string())
-                        }
-                        numbers.push(oper.unwrap().run(left_operand.unwrap(), right_operand.unwrap()));
-                    }
+    fn parse_identifier(&mut self, name: &str, span: Span) -> Expr {
+        let lowercase_name = name.to_ascii_lowercase();
+        let variable = match lowercase_name.as_str() {
+            "time" => Some(Expr::Time),
+            "x" => Some(Expr::PointX),
+            "y" => Some(Expr::PointY),
+            "z" => Some(Expr::PointZ),
+            "r" => Some(Expr::PointR),
+            "theta" => Some(Expr::PointTheta),
+            "phi" => Some(Expr::PointPhi),
+            "pi" => Some(Expr::Constant(std::f32::consts::PI)),
+            "tau" => Some(Expr::Constant(std::f32::consts::TAU)),
+            "seed" => Some(Expr::Constant(self.seed)),
+            _ => None,
+        };
+        let parameter = || self.parameters.iter()
+            .find(|(parameter, _)| parameter.eq_ignore_ascii_case(name))
+            .map(|(parameter, value)| Expr::Parameter(parameter.clone(), *value));
+        let column = || self.columns.iter()
+            .position(|column| column.eq_ignore_ascii_case(name))
+            .map(|index| Expr::Column(index, self.columns[index].clone()));
+        if let Some(expr) = variable.or_else(parameter).or_else(column) {
+            self.progressed = true;
+            return expr;
+        }
+        let is_call = self.peek_kind() == Some(&TokenKind::OpenParen);
+        let func_type = match FunctionType::ALL.iter().find(|func_type| func_type.name() == lowercase_name) {
+            Some(func_type) => *func_type,
+            None => {
+                let (kind, suggestion) = if is_call {
+                    ("function", closest_match(name, FunctionType::ALL.iter().map(|func_type| func_type.name())))
+                } else {
+                    let names = VARIABLE_NAMES.into_iter()
+                        .chain(self.parameters.iter().map(|(parameter, _)| parameter.as_str()))
+                        .chain(self.columns.iter().map(String::as_str));
+                    ("variable", closest_match(name, names))
+                };
+                let message = match suggestion {
+                    Some(suggestion) => format!("Unknown {} `{}`, did you mean `{}`?", kind, name, suggestion),
+                    None => format!("Unknown {} `{}`", kind, name),
+                };
+                self.report(FormulaError::new(message, Some(span)));
+                self.progressed = true;
+                if is_call {
+                    self.position += 1;
+                    self.parse_arguments(name);
                 }
-                operators.push(*oper);
+                return Self::placeholder();
             },
-            FuncNodeCleaned::Float(x) => numbers.push(*x),
-            FuncNodeCleaned::Int(x) => numbers.push(*x as f32),
-            FuncNodeCleaned::BuiltinFunction(func_type, inner_nodes) => numbers.push(func_type.perform_f32_func(compile_cleaned_nodes(inner_nodes.to_vec())?)),
-            FuncNodeCleaned::Parentheses(inner_nodes) => numbers.push(compile_cleaned_nodes(inner_nodes.to_vec())?),
+        };
+        if !is_call {
+            self.report(self.error_expected(&format!("`(` after function `{}`", func_type.name())));
+            return Self::placeholder();
+        }
+        self.position += 1;
+        let args = self.parse_arguments(func_type.name());
+        if args.len() != func_type.arity() {
+            self.report(FormulaError::new(
+                format!("{}() takes {} argument(s), but was given {}", func_type.name(), func_type.arity(), args.len()),
+                Some(span),
+            ));
+            self.progressed = true;
+            return Self::placeholder();
         }
+        self.progressed = true;
+        Expr::Function(func_type, args)
     }
 
-    // Cleanup remaining operations
-    for operation in operators {
-        let right_operand = numbers.pop();
-        let left_operand = numbers.pop();
-        if right_operand.is_none() || left_operand.is_none() {
-            return Err("Erroneous binary operation attempted".to_string())
+    /// Parses comma separated arguments up to the closing `)`, the opening `(` having been consumed already
+    fn parse_arguments(&mut self, name: &str) -> Vec<Expr> {
+        self.paren_depth += 1;
+        let mut args: Vec<Expr> = Vec::new();
+        if self.peek_kind() != Some(&TokenKind::CloseParen) {
+            args.push(self.parse_expression(0));
+            while self.peek_kind() == Some(&TokenKind::Comma) {
+                self.position += 1;
+                args.push(self.parse_expression(0));
+            }
         }
-        numbers.push(operation.run(left_operand.unwrap(), right_operand.unwrap()));
+        self.paren_depth -= 1;
+        self.expect_close(&format!("`,` or `)` in the arguments of `{}`", name));
+        args
     }
+}
+
+/// A parsed formula, runnable one point at a time through `func`.
+/// When parsing succeeded `program` also holds the compiled bytecode for batched evaluation.
+#[derive(Clone)]
+pub struct GraphFormula {
+    pub func: Arc<dyn Fn(f32, Vec3) -> Result<f32, String> + Send + Sync>,
+    pub program: Option<Arc<Program>>,
+}
 
-    numbers.get(0).map(|x| *x).ok_or_else(|| { "Input is empty.".to_owned() })
+impl GraphFormula {
+    /// Wraps the result of compiling a formula. When it failed, `func` gives every error joined by newlines.
+    pub fn new(compiled: Result<Program, Vec<FormulaError>>) -> Self {
+        match compiled {
+            Ok(program) => {
+                let program = Arc::new(program);
+                let func_program = Arc::clone(&program);
+                GraphFormula {
+                    func: Arc::new(move | time_elapsed: f32, point_pos: Vec3 | -> Result<f32, String> {
+                        Ok(func_program.run(time_elapsed, point_pos))
+                    }),
+                    program: Some(program),
+                }
+            },
+            Err(errors) => {
+                let string = errors.iter().map(|error| error.to_string()).collect::<Vec<String>>().join("\n");
+                GraphFormula {
+                    func: Arc::new(move | _time_elapsed: f32, _point_pos: Vec3 | -> Result<f32, String> {
+                        Err(string.clone())
+                    }),
+                    program: None,
+                }
+            },
+        }
+    }
 }
 
-// fn translate_node(node: FuncNode, time_elapsed: f32, point_pos: Vec3) -> f32 {
-//     match node {
-//         FuncNode::Time => time_elapsed,
-//         FuncNode::PointX => point_pos.x,
-//         FuncNode::PointY => point_pos.y,
-//         FuncNode::PointZ => point_pos.z,
-//         FuncNode::Int(x) => x as f32,
-//         FuncNode::BuiltinFunction(func, value_node) => func.perform_f32_func(translate_node(*value_node, time_elapsed, point_pos)),
-//         // FuncNode::BinaryOperation(op, x_node, y_node) => {
-//         //     let x = translate_node(*x_node, time_elapsed, point_pos);
-//         //     let y = translate_node(*y_node, time_elapsed, point_pos);
-//         //     op.perform_func(x, y) 
-//         // },
-
-//     }
-// }
+/// The three axis formulas after new text for some of them was swapped in
+pub struct FormulaReload {
+    /// Text of each axis in x, y, z order: the new text where it compiled, the previous text otherwise
+    pub texts: [String; 3],
+    pub formulas: [GraphFormula; 3],
+    /// Problems with the new text of the axes that kept their previous text, and with previous text that no
+    /// longer compiles either
+    pub errors: Vec<String>,
+}
 
 pub struct FormulaParser {
-    main_parser: Arc<dyn Fn(&mut ParseInput) -> Result<FuncNode, String> + Send + Sync>,
+    /// Reads a value written directly after another as multiplication, off by default. It applies only when
+    /// the two touch with no space between, and only in these cases:
+    /// - a number then a name or `√`: `2x`, `2pi`, `2π`, `3sin(time)`, `2√x`
+    /// - a closing `)` then an opening `(`: `(x+1)(x-1)`, `sin(x)(y+1)`
+    ///
+    /// The product binds exactly like `*`, so `1/2x` is `(1/2)*x` and `2x^2` is `2*(x^2)`.
+    /// Names next to names (`x y`, and `xy` which is one name) and numbers after names (`x2`) are still errors.
+    pub implicit_multiplication: bool,
+    /// Value of the `seed` constant, for varying formulas built on `rand`, `hash` and the noise functions
+    /// without editing them, like `perlin(x / 50, z / 50, seed)`
+    pub seed: f32,
+    /// Names and values of values set outside the formula, like the app's sliders, which formulas read as
+    /// `Expr::Parameter`. Built-in variables take precedence over parameters with the same name.
+    pub parameters: Vec<(String, f32)>,
+    /// Names of values loaded with each point, like the extra columns of a CSV file, which formulas read as
+    /// `Expr::Column`. Built-in variables and parameters take precedence over columns with the same name.
+    pub columns: Vec<String>,
+    /// Deepest nesting of parentheses, function calls and chained operators like `x^x^x`.
+    /// Parsing and evaluation recurse once per level, so this keeps pasted formulas from overflowing the stack.
+    pub max_depth: usize,
+    /// Most tokens a formula may contain
+    pub max_tokens: usize,
+    /// Highest `Expr::cost` a formula may have, which bounds the work done per point
+    pub max_cost: u32,
+}
+
+impl Default for FormulaParser {
+    fn default() -> Self {
+        FormulaParser {
+            implicit_multiplication: false,
+            seed: 0.,
+            parameters: Vec::new(),
+            columns: Vec::new(),
+            max_depth: 64,
+            max_tokens: 1024,
+            max_cost: 10_000,
+        }
+    }
 }
 
 impl FormulaParser {
     pub fn new() -> Self {
-        let parsers: Arc<RwLock<Vec<Arc<dyn for<'r> Fn(&'r mut ParseInput) -> Result<FuncNode, String> + Send + Sync>>>> = Arc::new(RwLock::new(vec!(
-            Arc::new(parse_binary_operation_symbol),
-            Arc::new(parse_time),
-            Arc::new(parse_point_x),
-            Arc::new(parse_point_y),
-            Arc::new(parse_point_z),
-            Arc::new(parse_integer),
-        )));
-    
-        let main_parser = try_parsers_with_list(parsers.clone());
-    
-        // let negation_parser = parse_negation(Arc::new(main_parser));
-        // parsers.borrow_mut().insert(0, Box::new(negation_parser));
-    
-        let mut edit_parser_list = parsers.write().unwrap();
-
-        let builtin_func_parser = parse_builtin_func(Arc::clone(&main_parser));
-        edit_parser_list.insert(0, Arc::clone(&builtin_func_parser));
-    
-        let parentheses_parser = parse_parentheses(Arc::clone(&main_parser));
-        edit_parser_list.insert(1, Arc::clone(&parentheses_parser));
+        FormulaParser::default()
+    }
 
-        FormulaParser {
-            main_parser: Arc::clone(&main_parser),
+    /// Parses the input into an expression tree, giving the first problem found if there are any
+    pub fn parse_tree(&self, input: &str) -> Result<Expr, FormulaError> {
+        self.parse_tree_diagnostics(input).map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Parses the input into an expression tree, giving every problem found in the order they appear in the input
+    pub fn parse_tree_diagnostics(&self, input: &str) -> Result<Expr, Vec<FormulaError>> {
+        let (tokens, mut errors) = tokenize_with_errors(input);
+        if tokens.is_empty() {
+            return Err(vec![FormulaError::new("Input is empty.", None)]);
+        }
+        if let Some(token) = tokens.get(self.max_tokens) {
+            return Err(vec![FormulaError::new(
+                format!("Formula has {} tokens, more than the limit of {}", tokens.len(), self.max_tokens),
+                Some(token.span),
+            )]);
+        }
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            paren_depth: 0,
+            implicit_multiplication: self.implicit_multiplication,
+            seed: self.seed,
+            parameters: &self.parameters,
+            columns: &self.columns,
+            depth: 0,
+            max_depth: self.max_depth,
+            errors: Vec::new(),
+            progressed: false,
+            aborted: false,
+        };
+        let expr = parser.parse_expression(0);
+        errors.append(&mut parser.errors);
+        if errors.is_empty() {
+            let cost = expr.cost();
+            if cost > self.max_cost {
+                return Err(vec![FormulaError::new(
+                    format!("Formula costs {} to evaluate per point, more than the limit of {}", cost, self.max_cost),
+                    None,
+                )]);
+            }
+            Ok(expr)
+        } else {
+            errors.sort_by_key(|error| error.span.map(|span| span.start));
+            Err(errors)
         }
     }
 
+    /// Parses the input and compiles it to bytecode
+    pub fn compile(&self, input: &str) -> Result<Program, FormulaError> {
+        Program::compile(&self.parse_tree(input)?)
+    }
+
     pub fn parse(&self, input: &str) -> Arc<dyn Fn(f32, Vec3) -> Result<f32, String> + Sync + Send> {
-        let mut parse_input = ParseInput::new(input.to_owned());
-    
-        let mut output_nodes: Vec<FuncNode> = Vec::new();
+        self.parse_formula(input).func
+    }
 
-        loop {
-            if parse_input.finished() {
-                return Arc::new(move | time_elapsed: f32, point_pos: Vec3 | -> Result<f32, String> {
-                    translate_nodes(&output_nodes, time_elapsed, point_pos)
-                });
-            }
-            match (self.main_parser)(&mut parse_input) {
-                Ok(node) => {
-                    output_nodes.push(node);
-                },
-                Err(string) => {
-                    return Arc::new(move | time_elapsed: f32, point_pos: Vec3 | -> Result<f32, String> {
-                        Err(string.clone())
-                    });
+    /// Parses the input and compiles it to bytecode, giving every problem found if there are any
+    pub fn compile_diagnostics(&self, input: &str) -> Result<Program, Vec<FormulaError>> {
+        self.parse_tree_diagnostics(input)
+            .and_then(|expr| Program::compile(&expr).map_err(|error| vec![error]))
+    }
+
+    /// Parses the input into both its per-point closure and, if it compiled, its batch program
+    pub fn parse_formula(&self, input: &str) -> GraphFormula {
+        GraphFormula::new(self.compile_diagnostics(input))
+    }
+
+    /// Replaces the `previous` text of each axis with the new text given when it compiles, and keeps the previous
+    /// text when it doesn't. Kept text is compiled again too, so every axis reads this parser's parameters and
+    /// settings rather than the ones it was first compiled with.
+    pub fn reload_formulas(&self, previous: [&str; 3], new_texts: [Option<String>; 3]) -> FormulaReload {
+        let mut errors = Vec::new();
+        let mut texts = previous.map(str::to_string);
+        let mut formulas = previous.map(|text| self.compile_diagnostics(text));
+        let axes = ["X", "Y", "Z"].into_iter().zip(&mut texts).zip(&mut formulas);
+        for (((axis, text), formula), new_text) in axes.zip(new_texts) {
+            if let Some(new_text) = new_text {
+                match self.compile_diagnostics(&new_text) {
+                    Ok(program) => {
+                        *formula = Ok(program);
+                        *text = new_text;
+                    },
+                    Err(problems) => {
+                        errors.extend(problems.iter().map(|problem| format!("{} formula: {}", axis, problem)));
+                        if let Err(problems) = formula {
+                            errors.extend(problems.iter().map(|problem| {
+                                format!("{} formula: the previous formula doesn't compile either: {}", axis, problem)
+                            }));
+                        }
+                    },
                 }
             }
         }
+        FormulaReload {
+            texts,
+            formulas: formulas.map(GraphFormula::new),
+            errors,
+        }
     }
 }
-
-// pub fn parse_sim_func(input: &str) -> Arc<dyn Fn(f32, Vec3) -> Result<f32, String> + Sync + Send> {
-//     let parsers: Rc<RefCell<Vec<Box<dyn for<'r> Fn(&'r mut ParseInput) -> Result<FuncNode, String>>>>> = Rc::new(RefCell::new(vec!(
-//         Box::new(parse_binary_operation_symbol),
-//         Box::new(parse_time),
-//         Box::new(parse_point_x),
-//         Box::new(parse_point_y),
-//         Box::new(parse_point_z),
-//         Box::new(parse_integer),
-//     )));
-
-//     let main_parser = &*Box::leak(try_parsers_with_list(parsers.clone()));
-
-//     // let negation_parser = parse_negation(Arc::new(main_parser));
-//     // parsers.borrow_mut().insert(0, Box::new(negation_parser));
-
-//     let builtin_func_parser = parse_builtin_func(Arc::new(main_parser));
-//     parsers.borrow_mut().insert(0, Box::new(builtin_func_parser));
-
-//     let parentheses_parser = parse_parentheses(Arc::new(main_parser));
-//     parsers.borrow_mut().insert(1, Box::new(parentheses_parser));
-
-//     // let add_parser = parse_binary_operation(Arc::new(main_parser));
-//     // parsers.borrow_mut().push(add_parser);
-//     // parsers.borrow_mut().insert(1, Box::new(add_parser));
-
-//     let mut parse_input = ParseInput::new(input.to_owned());
-    
-//     let mut output_nodes: Vec<FuncNode> = Vec::new();
-
-//     loop {
-//         if parse_input.finished() {
-//             return Arc::new(move | time_elapsed: f32, point_pos: Vec3 | -> Result<f32, String> {
-//                 translate_nodes(&output_nodes, time_elapsed, point_pos)
-//             });
-//         }
-//         match main_parser(&mut parse_input) {
-//             Ok(node) => {
-//                 output_nodes.push(node);
-//             },
-//             Err(string) => {
-//                 return Arc::new(move | time_elapsed: f32, point_pos: Vec3 | -> Result<f32, String> {
-//                     Err(string.clone())
-//                 });
-//             }
-//         }
-//     }
-// }
\ No newline at end of file
@@ -0,0 +1,83 @@
+use bevy::{app::AppExit, prelude::*};
+
+use super::move_spheres::UiState;
+use super::pan_camera::PanOrbitCamera;
+use super::parsing_function::FormulaParser;
+use super::scene_files::{current_scene, ApplyScene};
+use super::settings::Settings;
+use super::spawn_spheres::SphereGrid;
+
+/// Stores the window size, the "Simulation Options" window's position and the running simulation as `Settings`
+/// when the app exits, and every second while they change since browsers give no dependable exit hook.
+/// Adds a "Reset Settings" button to the options window. Needs `MoveSpheres`.
+pub struct PersistSettings;
+
+/// Forgets the stored settings and puts the simulation, window size and layout back to their defaults
+pub struct ResetSettings;
+
+struct SaveTimer(Timer);
+
+impl Plugin for PersistSettings {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ResetSettings>()
+            .insert_resource(SaveTimer(Timer::from_seconds(1., true)))
+            .add_system(reset_settings.after("ui_update").before("scene_files"))
+            .add_system_to_stage(CoreStage::Last, save_settings);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_settings(
+    time: Res<Time>,
+    mut timer: ResMut<SaveTimer>,
+    mut ev_exit: EventReader<AppExit>,
+    windows: Res<Windows>,
+    ui_state: Res<UiState>,
+    parser: Res<FormulaParser>,
+    grid: Option<Res<SphereGrid>>,
+    cameras: Query<(&PanOrbitCamera, &Transform)>,
+    mut saved: Local<Option<Settings>>,
+) {
+    let exiting = ev_exit.iter().next().is_some();
+    if !timer.0.tick(time.delta()).just_finished() && !exiting {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let settings = Settings {
+        window_size: [window.width(), window.height()],
+        options_window_position: ui_state.options_position,
+        scene: current_scene(&ui_state, &parser, grid.as_deref(), cameras.iter().next()),
+    };
+    if saved.as_ref() == Some(&settings) {
+        return;
+    }
+    if let Err(error) = settings.save() {
+        warn!("Couldn't save settings: {}", error);
+    }
+    *saved = Some(settings);
+}
+
+fn reset_settings(
+    mut ev_reset: EventReader<ResetSettings>,
+    mut ev_apply: EventWriter<ApplyScene>,
+    mut windows: ResMut<Windows>,
+    mut ui_state: ResMut<UiState>,
+) {
+    if ev_reset.iter().next().is_none() {
+        return;
+    }
+    let defaults = Settings::default();
+    ui_state.scene_message = match Settings::clear() {
+        Ok(()) => "Settings reset to defaults".to_string(),
+        Err(error) => format!("Couldn't clear the stored settings: {}", error),
+    };
+    ui_state.reset_layout = true;
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_resolution(defaults.window_size[0], defaults.window_size[1]);
+    }
+    ev_apply.send(ApplyScene(defaults.scene));
+}
@@ -0,0 +1,268 @@
+#[cfg(feature = "bevy")]
+use bevy::tasks::TaskPool;
+use glam::Vec3;
+
+use super::formula_bytecode::{PointData, LANES};
+use super::formula_tree::spherical_coordinates;
+use super::parsing_function::GraphFormula;
+
+/// Distance between neighbouring points of the default lattice
+pub const GRID_SPACING: u32 = 12;
+
+/// Coordinate along one axis of point `index` out of `count`, centring the lattice on the origin
+pub fn grid_coordinate(index: u32, count: u32, spacing: u32) -> f32 {
+    let spacing = spacing as i64;
+    (index as i64 * spacing - (spacing * count as i64) / 2) as f32
+}
+
+/// Reads grid dimensions written as `X,Y,Z`, like `25,1,25`
+pub fn parse_grid_counts(text: &str) -> Result<[u32; 3], String> {
+    let counts: Vec<u32> = text.split(',')
+        .map(|count| count.trim().parse::<u32>().map_err(|error| format!("`{}` is not a point count: {}", count, error)))
+        .collect::<Result<_, _>>()?;
+    match counts[..] {
+        [x, y, z] => Ok([x, y, z]),
+        _ => Err(format!("expected three counts separated by commas, but found {}", counts.len())),
+    }
+}
+
+/// Every point of a `x_count` by `y_count` by `z_count` lattice, in x, then y, then z order
+pub fn grid_points(x_count: u32, y_count: u32, z_count: u32, spacing: u32) -> Vec<Vec3> {
+    let mut points = Vec::with_capacity(x_count as usize * y_count as usize * z_count as usize);
+    for x in 0..x_count {
+        for y in 0..y_count {
+            for z in 0..z_count {
+                points.push(Vec3::new(
+                    grid_coordinate(x, x_count, spacing),
+                    grid_coordinate(y, y_count, spacing),
+                    grid_coordinate(z, z_count, spacing),
+                ));
+            }
+        }
+    }
+    points
+}
+
+/// How the formulas move the points from one frame to the next
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum SimulationMode {
+    /// Every frame applies the formulas to where the previous frame left the points, so they keep moving
+    #[default]
+    Iterate,
+    /// Every frame applies the formulas to the points' starting positions, plotting the formulas over time
+    Plot,
+}
+
+/// The formula moving each axis of a point, applied in x, y, z order
+#[derive(Clone)]
+pub struct AxisFormulas {
+    pub x_func: GraphFormula,
+    pub y_func: GraphFormula,
+    pub z_func: GraphFormula,
+}
+
+/// Structure-of-arrays copy of a set of point positions, which can be kept between frames to reuse the allocations.
+#[derive(Default)]
+pub struct PointColumns {
+    pub xs: Vec<f32>,
+    pub ys: Vec<f32>,
+    pub zs: Vec<f32>,
+    /// The `r`, `theta` and `phi` each point was placed with
+    pub layout: Vec<[f32; 3]>,
+    /// Values loaded with the points that formulas read as `Expr::Column`, `data_width` per point one point after
+    /// another
+    pub data: Vec<f32>,
+    pub data_width: usize,
+    pub output: Vec<f32>,
+}
+
+impl PointColumns {
+    /// Removes every point, keeping the number of data columns
+    pub fn clear(&mut self) {
+        self.xs.clear();
+        self.ys.clear();
+        self.zs.clear();
+        self.layout.clear();
+        self.data.clear();
+    }
+
+    /// Removes every point and sets how many data columns the points carry
+    pub fn clear_with_data_columns(&mut self, count: usize) {
+        self.clear();
+        self.data_width = count;
+    }
+
+    /// Adds a point placed where it is, whose data columns are all NaN
+    pub fn push(&mut self, point: Vec3) {
+        self.push_with_data(point, &[]);
+    }
+
+    /// Adds a point placed where it is, so its layout parameters are its spherical coordinates, with its data
+    /// column values
+    pub fn push_with_data(&mut self, point: Vec3, data: &[f32]) {
+        self.push_with_layout(point, spherical_coordinates(point), data);
+    }
+
+    /// Adds a point with the `r`, `theta` and `phi` it was placed with and its data column values, reading missing
+    /// values as NaN and ignoring extra ones
+    pub fn push_with_layout(&mut self, point: Vec3, layout: [f32; 3], data: &[f32]) {
+        self.xs.push(point.x);
+        self.ys.push(point.y);
+        self.zs.push(point.z);
+        self.layout.push(layout);
+        self.data.extend((0..self.data_width).map(|index| data.get(index).copied().unwrap_or(f32::NAN)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    pub fn point(&self, index: usize) -> Vec3 {
+        Vec3::new(self.xs[index], self.ys[index], self.zs[index])
+    }
+
+    /// Moves every point in place with the formulas on the current thread, returning the distinct errors encountered
+    pub fn evaluate_serial(&mut self, formulas: &AxisFormulas, time_elapsed: f32) -> Vec<String> {
+        let PointColumns { xs, ys, zs, layout, data, data_width, output } = self;
+        output.resize(xs.len(), 0.);
+        evaluate_points(formulas, time_elapsed, xs, ys, zs, PointData::new(layout, data, *data_width), output)
+    }
+
+    /// Moves every point in place with the formulas, splitting the work between `thread_count` scoped threads.
+    /// Returns the distinct errors encountered.
+    pub fn evaluate_threads(&mut self, formulas: &AxisFormulas, time_elapsed: f32, thread_count: usize) -> Vec<String> {
+        let chunk_errors: Vec<Vec<String>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = self.chunks(thread_count)
+                .map(|chunk| scope.spawn(move || chunk.evaluate(formulas, time_elapsed)))
+                .collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+        merge_errors(chunk_errors)
+    }
+
+    /// Moves every point in place with the formulas, splitting the work between the pool's threads.
+    /// Returns the distinct errors encountered.
+    #[cfg(feature = "bevy")]
+    pub fn evaluate(&mut self, formulas: &AxisFormulas, time_elapsed: f32, pool: &TaskPool) -> Vec<String> {
+        let chunk_errors: Vec<Vec<String>> = pool.scope(|scope| {
+            for chunk in self.chunks(pool.thread_num()) {
+                scope.spawn(async move {
+                    chunk.evaluate(formulas, time_elapsed)
+                });
+            }
+        });
+        merge_errors(chunk_errors)
+    }
+
+    /// Splits the points evenly into one chunk per thread, keeping every chunk a whole number of batch lanes
+    fn chunks(&mut self, thread_count: usize) -> impl Iterator<Item = PointChunk<'_>> {
+        let PointColumns { xs, ys, zs, layout, data, data_width, output } = self;
+        output.resize(xs.len(), 0.);
+        let data_width = *data_width;
+        let chunk_size = usize::max(xs.len().div_ceil(thread_count.max(1)).div_ceil(LANES) * LANES, LANES);
+        // Whole points are contiguous in `data`, so each chunk's values are a subslice of it
+        let data_chunks = data.chunks(chunk_size * data_width.max(1));
+        xs.chunks_mut(chunk_size)
+            .zip(ys.chunks_mut(chunk_size))
+            .zip(zs.chunks_mut(chunk_size))
+            .zip(output.chunks_mut(chunk_size))
+            .zip(layout.chunks(chunk_size).map(Some).chain(std::iter::repeat(None)))
+            .zip(data_chunks.map(Some).chain(std::iter::repeat(None)))
+            .map(move |(((((xs, ys), zs), output), layout), data)| PointChunk {
+                xs,
+                ys,
+                zs,
+                data: PointData::new(layout.unwrap_or(&[]), data.unwrap_or(&[]), data_width),
+                output,
+            })
+    }
+}
+
+/// The points one thread moves
+struct PointChunk<'a> {
+    xs: &'a mut [f32],
+    ys: &'a mut [f32],
+    zs: &'a mut [f32],
+    data: PointData<'a>,
+    output: &'a mut [f32],
+}
+
+impl PointChunk<'_> {
+    fn evaluate(self, formulas: &AxisFormulas, time_elapsed: f32) -> Vec<String> {
+        evaluate_points(formulas, time_elapsed, self.xs, self.ys, self.zs, self.data, self.output)
+    }
+}
+
+/// Every chunk reports the errors it ran into, so merge them without repeating the same message
+fn merge_errors(chunk_errors: Vec<Vec<String>>) -> Vec<String> {
+    let mut errors: Vec<String> = Vec::new();
+    for error in chunk_errors.into_iter().flatten() {
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    }
+    errors
+}
+
+/// Moves the given points in place with the formulas, returning the distinct errors encountered.
+/// Each axis sees the values already updated by the previous axes. `data` holds the layout parameters and data
+/// column values of the same points.
+pub fn evaluate_points(formulas: &AxisFormulas, time_elapsed: f32, xs: &mut [f32], ys: &mut [f32], zs: &mut [f32], data: PointData, output: &mut [f32]) -> Vec<String> {
+    // Every formula compiled, so the chunk can go through the batch evaluator in one dispatch per axis
+    if let (Some(x_program), Some(y_program), Some(z_program)) = (&formulas.x_func.program, &formulas.y_func.program, &formulas.z_func.program) {
+        x_program.run_batch_with_columns(time_elapsed, xs, ys, zs, data, output);
+        xs.copy_from_slice(output);
+        y_program.run_batch_with_columns(time_elapsed, xs, ys, zs, data, output);
+        ys.copy_from_slice(output);
+        z_program.run_batch_with_columns(time_elapsed, xs, ys, zs, data, output);
+        zs.copy_from_slice(output);
+        return Vec::new();
+    }
+
+    // The formulas that did compile still run as programs, since only those can read the layout parameters and data columns
+    let run = |formula: &GraphFormula, point: Vec3, layout: Option<[f32; 3]>, row: &[f32]| match (&formula.program, layout) {
+        (Some(program), Some(layout)) => Ok(program.run_with_layout(time_elapsed, point, layout, row)),
+        (Some(program), None) => Ok(program.run_with_columns(time_elapsed, point, row)),
+        (None, _) => (formula.func)(time_elapsed, point),
+    };
+    let mut errors: Vec<String> = Vec::new();
+    for index in 0..xs.len() {
+        let (layout, row) = (data.layout(index), data.row(index));
+        match run(&formulas.x_func, Vec3::new(xs[index], ys[index], zs[index]), layout, row) {
+            Ok(output) => {
+                xs[index] = output;
+            },
+            Err(e) => {
+                if !errors.contains(&e) {
+                    errors.push(e);
+                }
+            },
+        }
+        match run(&formulas.y_func, Vec3::new(xs[index], ys[index], zs[index]), layout, row) {
+            Ok(output) => {
+                ys[index] = output;
+            },
+            Err(e) => {
+                if !errors.contains(&e) {
+                    errors.push(e);
+                }
+            },
+        }
+        match run(&formulas.z_func, Vec3::new(xs[index], ys[index], zs[index]), layout, row) {
+            Ok(output) => {
+                zs[index] = output;
+            },
+            Err(e) => {
+                if !errors.contains(&e) {
+                    errors.push(e);
+                }
+            },
+        }
+    }
+    errors
+}
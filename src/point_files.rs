@@ -0,0 +1,403 @@
+//! Measured points to animate instead of a generated layout: CSV and TSV tables, PLY point clouds and grayscale
+//! PNG heightmaps. Values loaded alongside the positions become columns formulas can read by name.
+
+use std::{fmt, fs, io, path::Path};
+
+use glam::Vec3;
+
+use super::parsing_function::is_reserved_name;
+use super::point_evaluation::grid_coordinate;
+
+/// How many spacings above the darkest pixel of a heightmap the brightest one stands
+pub const HEIGHTMAP_HEIGHT: f32 = 10.;
+
+/// Points read from a file, with the values of any extra columns
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointSet {
+    pub points: Vec<Vec3>,
+    /// Names formulas read the extra columns by
+    pub column_names: Vec<String>,
+    /// Each point's values for the extra columns, in `column_names` order
+    pub data: Vec<Vec<f32>>,
+}
+
+#[derive(Debug)]
+pub enum PointFileError {
+    Io(io::Error),
+    /// The file is not a point set this reads
+    Format(String),
+}
+
+impl fmt::Display for PointFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PointFileError::Io(error) => write!(f, "{}", error),
+            PointFileError::Format(message) => write!(f, "Invalid point file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PointFileError {}
+
+impl From<io::Error> for PointFileError {
+    fn from(error: io::Error) -> Self {
+        PointFileError::Io(error)
+    }
+}
+
+fn format_error(message: impl Into<String>) -> PointFileError {
+    PointFileError::Format(message.into())
+}
+
+/// Reads the points from a `.csv`, `.tsv`, `.ply` or `.png` file, picked by its extension.
+/// Heightmap pixels are laid out `spacing` apart.
+pub fn load_points(path: &Path, spacing: u32) -> Result<PointSet, PointFileError> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "csv" => parse_delimited(&fs::read_to_string(path)?, ','),
+        "tsv" | "tab" => parse_delimited(&fs::read_to_string(path)?, '\t'),
+        "ply" => parse_ply(&fs::read(path)?),
+        "png" => parse_heightmap(&fs::read(path)?, spacing),
+        _ => Err(format_error(format!("`{}` isn't a .csv, .tsv, .ply or .png file", path.display()))),
+    }
+}
+
+/// Turns a column heading into a name formulas can use: lowercase letters, digits and underscores, not starting
+/// with a digit. Headings that come out as a built-in name, like `Time` or `R`, are caught by the check every
+/// format runs on its column names.
+pub fn column_identifier(heading: &str) -> String {
+    let mut identifier: String = heading.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    if !identifier.starts_with(|c: char| c.is_alphabetic()) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+/// Makes sure formulas can read every extra column by its own name: no two columns may have the same name, and
+/// the extra columns, all but `axes`, may not be called after a built-in variable or function, which would hide them
+fn check_column_names(names: &[String], axes: [usize; 3]) -> Result<(), PointFileError> {
+    for (index, name) in names.iter().enumerate() {
+        if let Some(earlier) = names[..index].iter().position(|other| other.eq_ignore_ascii_case(name)) {
+            return Err(format_error(format!("columns {} and {} are both called `{}`", earlier + 1, index + 1, name)));
+        }
+        if !axes.contains(&index) && is_reserved_name(name) {
+            return Err(format_error(format!(
+                "column {} is called `{}`, which formulas already read as a built-in variable or function",
+                index + 1, name,
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Splits one line of a table at the delimiter, dropping the whitespace around each value. A value wrapped in
+/// double quotes can hold the delimiter, with `""` standing for a quote inside it. Quoted values can't span lines.
+fn split_fields(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    // Whether the current value was quoted and its closing quote has been read
+    let mut closed = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                },
+                '"' => {
+                    quoted = false;
+                    closed = true;
+                },
+                _ => field.push(c),
+            }
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field).trim().to_string());
+            closed = false;
+        } else if closed {
+            if !c.is_whitespace() {
+                return Err(format!("`{}` follows a closing quote, values are quoted whole", c));
+            }
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            quoted = true;
+        } else {
+            field.push(c);
+        }
+    }
+    if quoted {
+        return Err("a quoted value is never closed".to_string());
+    }
+    fields.push(field.trim().to_string());
+    Ok(fields)
+}
+
+/// Reads a table with one point per line. When the first line has anything that isn't a number it names the
+/// columns, and the ones called `x`, `y` and `z`, which it must have, hold the position. Otherwise the first three
+/// columns do, and the rest are named after their position, `column4` onwards. Blank lines and lines starting with `#` are skipped.
+/// Values can be quoted like in spreadsheet exports, see `split_fields`.
+pub fn parse_delimited(text: &str, delimiter: char) -> Result<PointSet, PointFileError> {
+    let mut lines = text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            let fields = split_fields(line, delimiter).map_err(|message| format_error(format!("line {}: {}", index + 1, message)))?;
+            Ok((index + 1, fields))
+        })
+        .collect::<Result<Vec<(usize, Vec<String>)>, PointFileError>>()?
+        .into_iter()
+        .peekable();
+
+    let (headings, has_header): (Vec<String>, bool) = match lines.peek() {
+        Some((_, fields)) if fields.iter().any(|field| field.parse::<f32>().is_err()) => {
+            let headings = fields.iter().map(|field| column_identifier(field)).collect();
+            lines.next();
+            (headings, true)
+        },
+        Some((_, fields)) => ((1..=fields.len()).map(|number| format!("column{}", number)).collect(), false),
+        None => return Err(format_error("the table is empty")),
+    };
+    if headings.len() < 3 {
+        return Err(format_error(format!("expected at least three columns for x, y and z, but found {}", headings.len())));
+    }
+    let axes = if has_header {
+        let position = |name: &str| {
+            headings.iter()
+                .position(|heading| heading == name)
+                .ok_or_else(|| format_error(format!("the header has no `{}` column, which the position needs", name)))
+        };
+        [position("x")?, position("y")?, position("z")?]
+    } else {
+        [0, 1, 2]
+    };
+    check_column_names(&headings, axes)?;
+
+    let mut set = PointSet {
+        column_names: (0..headings.len()).filter(|index| !axes.contains(index)).map(|index| headings[index].clone()).collect(),
+        ..PointSet::default()
+    };
+    for (line_number, fields) in lines {
+        if fields.len() != headings.len() {
+            return Err(format_error(format!("line {} has {} values but the table has {} columns", line_number, fields.len(), headings.len())));
+        }
+        let values = fields.iter()
+            .map(|field| field.parse::<f32>().map_err(|_| format_error(format!("line {}: `{}` is not a number", line_number, field))))
+            .collect::<Result<Vec<f32>, _>>()?;
+        set.points.push(Vec3::new(values[axes[0]], values[axes[1]], values[axes[2]]));
+        set.data.push(values.iter().enumerate().filter(|(index, _)| !axes.contains(index)).map(|(_, value)| *value).collect());
+    }
+    Ok(set)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyType {
+    fn from_name(name: &str) -> Option<PlyType> {
+        match name {
+            "char" | "int8" => Some(PlyType::I8),
+            "uchar" | "uint8" => Some(PlyType::U8),
+            "short" | "int16" => Some(PlyType::I16),
+            "ushort" | "uint16" => Some(PlyType::U16),
+            "int" | "int32" => Some(PlyType::I32),
+            "uint" | "uint32" => Some(PlyType::U32),
+            "float" | "float32" => Some(PlyType::F32),
+            "double" | "float64" => Some(PlyType::F64),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            PlyType::I8 | PlyType::U8 => 1,
+            PlyType::I16 | PlyType::U16 => 2,
+            PlyType::I32 | PlyType::U32 | PlyType::F32 => 4,
+            PlyType::F64 => 8,
+        }
+    }
+
+    /// Reads a value from the start of `bytes`, which holds at least `size()` bytes
+    fn read(&self, bytes: &[u8], little_endian: bool) -> f32 {
+        macro_rules! read {
+            ($kind:ty) => {{
+                let bytes = bytes[..std::mem::size_of::<$kind>()].try_into().unwrap();
+                (if little_endian { <$kind>::from_le_bytes(bytes) } else { <$kind>::from_be_bytes(bytes) }) as f32
+            }};
+        }
+        match self {
+            PlyType::I8 => read!(i8),
+            PlyType::U8 => read!(u8),
+            PlyType::I16 => read!(i16),
+            PlyType::U16 => read!(u16),
+            PlyType::I32 => read!(i32),
+            PlyType::U32 => read!(u32),
+            PlyType::F32 => read!(f32),
+            PlyType::F64 => read!(f64),
+        }
+    }
+}
+
+/// Reads the `vertex` element of an ASCII or binary PLY file. Its `x`, `y` and `z` properties are the position
+/// and any others, like normals or colours, become columns. Faces and other elements after the vertices are
+/// ignored.
+pub fn parse_ply(bytes: &[u8]) -> Result<PointSet, PointFileError> {
+    const END_HEADER: &[u8] = b"end_header";
+    let header_end = bytes.windows(END_HEADER.len())
+        .position(|window| window == END_HEADER)
+        .ok_or_else(|| format_error("the PLY header has no `end_header`"))?;
+    let body_start = bytes[header_end..].iter().position(|byte| *byte == b'\n').map_or(bytes.len(), |newline| header_end + newline + 1);
+    let header = std::str::from_utf8(&bytes[..header_end]).map_err(|_| format_error("the PLY header isn't text"))?;
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(format_error("not a PLY file, it doesn't start with `ply`"));
+    }
+    let mut format = None;
+    let mut vertex_count = None;
+    let mut properties: Vec<(String, PlyType)> = Vec::new();
+    let mut in_vertex = false;
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["format", name, _] => {
+                format = Some(match name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(format_error(format!("unknown PLY format `{}`", name))),
+                });
+            },
+            ["element", name, count] => {
+                let count: usize = count.parse().map_err(|_| format_error(format!("`{}` is not an element count", count)))?;
+                if vertex_count.is_some() {
+                    // Everything after the vertices is left unread
+                    in_vertex = false;
+                } else if name == "vertex" {
+                    vertex_count = Some(count);
+                    in_vertex = true;
+                } else if count > 0 {
+                    return Err(format_error(format!("the `{}` element comes before `vertex`, which isn't supported", name)));
+                }
+            },
+            ["property", "list", .., name] if in_vertex => {
+                return Err(format_error(format!("vertex property `{}` is a list, which isn't supported", name)));
+            },
+            ["property", kind, name] if in_vertex => {
+                let kind = PlyType::from_name(kind).ok_or_else(|| format_error(format!("unknown PLY type `{}`", kind)))?;
+                properties.push((name.to_string(), kind));
+            },
+            _ => {},
+        }
+    }
+    let format = format.ok_or_else(|| format_error("the PLY header has no `format` line"))?;
+    let vertex_count = vertex_count.ok_or_else(|| format_error("the PLY file has no `vertex` element"))?;
+    let position = |axis: &str| {
+        properties.iter()
+            .position(|(name, _)| name == axis)
+            .ok_or_else(|| format_error(format!("the vertices have no `{}` property", axis)))
+    };
+    let axes = [position("x")?, position("y")?, position("z")?];
+    let names: Vec<String> = properties.iter().map(|(name, _)| column_identifier(name)).collect();
+    check_column_names(&names, axes)?;
+
+    let mut set = PointSet {
+        column_names: names.into_iter().enumerate()
+            .filter(|(index, _)| !axes.contains(index))
+            .map(|(_, name)| name)
+            .collect(),
+        ..PointSet::default()
+    };
+    let mut push_vertex = |values: &[f32]| {
+        set.points.push(Vec3::new(values[axes[0]], values[axes[1]], values[axes[2]]));
+        set.data.push(values.iter().enumerate().filter(|(index, _)| !axes.contains(index)).map(|(_, value)| *value).collect());
+    };
+    let body = &bytes[body_start..];
+    match format {
+        PlyFormat::Ascii => {
+            let text = std::str::from_utf8(body).map_err(|_| format_error("the ASCII PLY data isn't text"))?;
+            let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+            for vertex in 0..vertex_count {
+                let line = lines.next().ok_or_else(|| format_error(format!("the file ends after {} of {} vertices", vertex, vertex_count)))?;
+                let values = line.split_whitespace()
+                    .map(|value| value.parse::<f32>().map_err(|_| format_error(format!("vertex {}: `{}` is not a number", vertex, value))))
+                    .collect::<Result<Vec<f32>, _>>()?;
+                if values.len() != properties.len() {
+                    return Err(format_error(format!("vertex {} has {} values but {} properties", vertex, values.len(), properties.len())));
+                }
+                push_vertex(&values);
+            }
+        },
+        PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+            let little_endian = format == PlyFormat::BinaryLittleEndian;
+            let stride: usize = properties.iter().map(|(_, kind)| kind.size()).sum();
+            if body.len() < stride * vertex_count {
+                return Err(format_error(format!("the file ends after {} of {} vertices", body.len() / stride.max(1), vertex_count)));
+            }
+            let mut values = Vec::with_capacity(properties.len());
+            for vertex in body.chunks_exact(stride.max(1)).take(vertex_count) {
+                values.clear();
+                let mut offset = 0;
+                for (_, kind) in &properties {
+                    values.push(kind.read(&vertex[offset..], little_endian));
+                    offset += kind.size();
+                }
+                push_vertex(&values);
+            }
+        },
+    }
+    Ok(set)
+}
+
+/// Lays a PNG out as a grid of points `spacing` apart in the ground plane, one per pixel, raised by the pixel's
+/// brightness up to `HEIGHTMAP_HEIGHT` spacings. Colour images use their luminance. The brightness, from 0 to 1,
+/// is also the `brightness` column.
+pub fn parse_heightmap(bytes: &[u8], spacing: u32) -> Result<PointSet, PointFileError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|error| format_error(error.to_string()))?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(|error| format_error(error.to_string()))?;
+    let channels = info.color_type.samples();
+
+    let mut set = PointSet {
+        column_names: vec!["brightness".to_string()],
+        ..PointSet::default()
+    };
+    for row in 0..info.height {
+        for column in 0..info.width {
+            let start = row as usize * info.line_size + column as usize * channels;
+            let pixel = &pixels[start..start + channels];
+            let brightness = match info.color_type {
+                png::ColorType::Rgb | png::ColorType::Rgba => 0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32,
+                _ => pixel[0] as f32,
+            } / 255.;
+            set.points.push(Vec3::new(
+                grid_coordinate(column, info.width, spacing),
+                brightness * HEIGHTMAP_HEIGHT * spacing as f32,
+                grid_coordinate(row, info.height, spacing),
+            ));
+            set.data.push(vec![brightness]);
+        }
+    }
+    Ok(set)
+}
@@ -0,0 +1,243 @@
+//! Ways of arranging the points the formulas start from. Each layout is sized by three counts and a spacing, like
+//! the lattice, and gives every point its own `r`, `theta` and `phi` for formulas to read alongside `x`, `y` and `z`.
+
+use std::f32::consts::{PI, TAU};
+
+use glam::Vec3;
+
+use super::formula_tree::spherical_coordinates;
+use super::noise::hash_integers;
+use super::point_evaluation::grid_points;
+
+/// How many candidates Poisson-disk sampling tries around a point before giving up on growing from it
+const POISSON_ATTEMPTS: u32 = 30;
+
+/// Closest two Poisson-disk points get, as a fraction of the spacing. Random points can't pack as tightly as the
+/// lattice, so keeping them a full spacing apart would leave no room for most of them.
+pub const POISSON_DISTANCE: f32 = 0.7;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum PointLayout {
+    /// An x by y by z lattice centred on the origin
+    #[default]
+    Grid,
+    /// x rings around the y axis, `spacing` apart, each of z points, stacked in y layers.
+    /// With a single layer these are polar rings in the ground plane. `r` and `theta` are the radius of the point's
+    /// ring and its angle around it, whichever layer it is in.
+    Cylindrical,
+    /// x shells around the origin, each of y × z points spread evenly over it along a Fibonacci spiral.
+    /// `r` is the radius of the point's shell.
+    Spherical,
+    /// x by z points in the ground plane with every other row shifted half a spacing, so each point has six
+    /// neighbours `spacing` away, stacked in y layers
+    Hexagonal,
+    /// x × y × z points scattered uniformly through the box the lattice fills
+    Random,
+    /// x × y × z points scattered through the box the lattice fills, no two closer than `POISSON_DISTANCE`
+    /// spacings. Stops short when there is no room left, which only happens to very thin boxes.
+    PoissonDisk,
+}
+
+impl PointLayout {
+    pub const ALL: [PointLayout; 6] = [
+        PointLayout::Grid,
+        PointLayout::Cylindrical,
+        PointLayout::Spherical,
+        PointLayout::Hexagonal,
+        PointLayout::Random,
+        PointLayout::PoissonDisk,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PointLayout::Grid => "Grid",
+            PointLayout::Cylindrical => "Cylindrical",
+            PointLayout::Spherical => "Spherical",
+            PointLayout::Hexagonal => "Hexagonal",
+            PointLayout::Random => "Random",
+            PointLayout::PoissonDisk => "Poisson disk",
+        }
+    }
+
+    /// Whether the points depend on the seed
+    pub fn is_random(&self) -> bool {
+        matches!(self, PointLayout::Random | PointLayout::PoissonDisk)
+    }
+
+    /// Every point of the layout, nested in x, then y, then z order where the counts index the points
+    pub fn points(&self, counts: [u32; 3], spacing: u32, seed: u32) -> Vec<Vec3> {
+        self.points_with_parameters(counts, spacing, seed).into_iter().map(|(point, _)| point).collect()
+    }
+
+    /// Every point of the layout with its `r`, `theta` and `phi`. The cylindrical and spherical layouts give the
+    /// coordinates they placed the point by, the others the spherical coordinates of the point.
+    pub fn points_with_parameters(&self, counts: [u32; 3], spacing: u32, seed: u32) -> Vec<(Vec3, [f32; 3])> {
+        let [x_count, y_count, z_count] = counts;
+        let points = match self {
+            PointLayout::Cylindrical => return cylindrical_points(counts, spacing as f32),
+            PointLayout::Spherical => return spherical_points(x_count, y_count * z_count, spacing as f32),
+            PointLayout::Grid => grid_points(x_count, y_count, z_count, spacing),
+            PointLayout::Hexagonal => hexagonal_points(counts, spacing as f32),
+            PointLayout::Random => random_points(counts, spacing, seed),
+            PointLayout::PoissonDisk => poisson_disk_points(counts, spacing, seed),
+        };
+        points.into_iter().map(|point| (point, spherical_coordinates(point))).collect()
+    }
+}
+
+/// Coordinate along one axis of point `index` out of `count`, with the middle point or gap exactly on the origin.
+/// Unlike the lattice this doesn't round to whole numbers, so `r`, `theta` and `phi` come out exact.
+fn centred_coordinate(index: u32, count: u32, spacing: f32) -> f32 {
+    (index as f32 - (count.max(1) - 1) as f32 / 2.) * spacing
+}
+
+/// An angle brought into the range `atan2` gives, from -pi to pi, so `theta` reads the same for every layout
+fn wrap_angle(angle: f32) -> f32 {
+    angle.sin().atan2(angle.cos())
+}
+
+/// `phi` is the point's angle down from the y axis, as in the other layouts
+fn cylindrical_points([ring_count, layer_count, point_count]: [u32; 3], spacing: f32) -> Vec<(Vec3, [f32; 3])> {
+    let mut points = Vec::with_capacity(ring_count as usize * layer_count as usize * point_count as usize);
+    for ring in 0..ring_count {
+        let radius = (ring + 1) as f32 * spacing;
+        for layer in 0..layer_count {
+            let height = centred_coordinate(layer, layer_count, spacing);
+            for point in 0..point_count {
+                let angle = point as f32 * TAU / point_count as f32;
+                let position = Vec3::new(radius * angle.cos(), height, radius * angle.sin());
+                points.push((position, [radius, wrap_angle(angle), radius.atan2(height)]));
+            }
+        }
+    }
+    points
+}
+
+/// The innermost shell is sized so its points sit about `spacing` apart, and the others are multiples of it
+fn spherical_points(shell_count: u32, point_count: u32, spacing: f32) -> Vec<(Vec3, [f32; 3])> {
+    let golden_angle = PI * (3. - 5f32.sqrt());
+    let inner_radius = spacing * (point_count as f32 / (4. * PI)).sqrt().max(1.);
+    let mut points = Vec::with_capacity(shell_count as usize * point_count as usize);
+    for shell in 0..shell_count {
+        let radius = (shell + 1) as f32 * inner_radius;
+        for point in 0..point_count {
+            let height = 1. - 2. * (point as f32 + 0.5) / point_count as f32;
+            let ring_radius = (1. - height * height).sqrt();
+            let angle = point as f32 * golden_angle;
+            let position = radius * Vec3::new(ring_radius * angle.cos(), height, ring_radius * angle.sin());
+            points.push((position, [radius, wrap_angle(angle), height.acos()]));
+        }
+    }
+    points
+}
+
+fn hexagonal_points([column_count, layer_count, row_count]: [u32; 3], spacing: f32) -> Vec<Vec3> {
+    let row_spacing = spacing * 3f32.sqrt() / 2.;
+    // Centres the block of rows, including the half spacing the odd rows stick out by
+    let offset = Vec3::new(
+        -(column_count as f32 - if row_count > 1 { 0.5 } else { 1. }) * spacing / 2.,
+        0.,
+        (row_count.max(1) - 1) as f32 * row_spacing / -2.,
+    );
+    let mut points = Vec::with_capacity(column_count as usize * layer_count as usize * row_count as usize);
+    for column in 0..column_count {
+        for layer in 0..layer_count {
+            let height = centred_coordinate(layer, layer_count, spacing);
+            for row in 0..row_count {
+                let shift = if row % 2 == 1 { spacing / 2. } else { 0. };
+                points.push(offset + Vec3::new(column as f32 * spacing + shift, height, row as f32 * row_spacing));
+            }
+        }
+    }
+    points
+}
+
+/// Corners of the box the lattice fills, centred on the origin and flat along axes with a single point
+fn lattice_box(counts: [u32; 3], spacing: u32) -> (Vec3, Vec3) {
+    let max = Vec3::from(counts.map(|count| centred_coordinate(count.max(1) - 1, count, spacing as f32)));
+    (-max, max)
+}
+
+/// A point in the box picked by the three pseudo-random values of `draw`
+fn random_in_box(min: Vec3, max: Vec3, draw: u32, seed: u32) -> Vec3 {
+    let unit = Vec3::new(hash_integers(draw, 0, seed), hash_integers(draw, 1, seed), hash_integers(draw, 2, seed));
+    min + (max - min) * unit
+}
+
+fn random_points(counts: [u32; 3], spacing: u32, seed: u32) -> Vec<Vec3> {
+    let (min, max) = lattice_box(counts, spacing);
+    let count = counts.iter().product::<u32>();
+    (0..count).map(|index| random_in_box(min, max, index, seed)).collect()
+}
+
+/// Bridson's algorithm: grows outwards from a random first point by trying candidates between one and two
+/// times the distance from points that still have room around them, keeping those far enough from every other point.
+/// Stops early when the box is full.
+fn poisson_disk_points(counts: [u32; 3], spacing: u32, seed: u32) -> Vec<Vec3> {
+    let target = counts.iter().product::<u32>() as usize;
+    let (min, max) = lattice_box(counts, spacing);
+    let distance = spacing.max(1) as f32 * POISSON_DISTANCE;
+    if target == 0 {
+        return Vec::new();
+    }
+
+    // Cells small enough to hold at most one point, so only nearby cells need checking against a candidate
+    let cell_size = distance / 3f32.sqrt();
+    let cells = ((max - min) / cell_size).floor().as_uvec3() + 1;
+    let cell_of = |point: Vec3| ((point - min) / cell_size).floor().as_uvec3().min(cells - 1);
+    let cell_index = |cell: glam::UVec3| ((cell.x * cells.y + cell.y) * cells.z + cell.z) as usize;
+    let mut occupied: Vec<Option<usize>> = vec![None; (cells.x * cells.y * cells.z) as usize];
+    // Candidates only spread along the axes the box has room in
+    let free_axes = Vec3::select((max - min).cmpgt(Vec3::ZERO), Vec3::ONE, Vec3::ZERO);
+
+    let mut draw = 0;
+    let mut next_random = |axis: u32| {
+        draw += 1;
+        hash_integers(draw, axis, seed)
+    };
+
+    let first = random_in_box(min, max, 0, seed);
+    let mut points = vec![first];
+    occupied[cell_index(cell_of(first))] = Some(0);
+    let mut active = vec![0];
+    while !active.is_empty() && points.len() < target {
+        let slot = ((next_random(3) * active.len() as f32) as usize).min(active.len() - 1);
+        let around = points[active[slot]];
+        let mut found = None;
+        for _ in 0..POISSON_ATTEMPTS {
+            let direction = (Vec3::new(next_random(0), next_random(1), next_random(2)) * 2. - 1.) * free_axes;
+            if direction.length_squared() < 1e-6 {
+                continue;
+            }
+            let candidate = around + direction.normalize() * distance * (1. + next_random(4));
+            if candidate.cmplt(min).any() || candidate.cmpgt(max).any() {
+                continue;
+            }
+            let cell = cell_of(candidate).as_ivec3();
+            let too_close = (-2..=2).any(|dx| (-2..=2).any(|dy| (-2..=2).any(|dz| {
+                let neighbour = cell + glam::IVec3::new(dx, dy, dz);
+                if neighbour.cmplt(glam::IVec3::ZERO).any() || neighbour.cmpge(cells.as_ivec3()).any() {
+                    return false;
+                }
+                occupied[cell_index(neighbour.as_uvec3())]
+                    .is_some_and(|other| points[other].distance(candidate) < distance)
+            })));
+            if !too_close {
+                found = Some(candidate);
+                break;
+            }
+        }
+        match found {
+            Some(candidate) => {
+                occupied[cell_index(cell_of(candidate))] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+            },
+            None => {
+                active.swap_remove(slot);
+            },
+        }
+    }
+    points
+}
@@ -0,0 +1,241 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use super::point_evaluation::SimulationMode;
+use super::point_layout::PointLayout;
+use super::scene::{CameraSettings, FormulaSettings, GridSettings, Parameter, Scene};
+use super::storage::{self, StorageError};
+
+/// Category user presets are listed under when they don't name one
+pub const USER_CATEGORY: &str = "User";
+
+/// A named scene to start from, listed under its category in the "Simulation Options" window
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default = "user_category")]
+    pub category: String,
+    /// What the preset shows, for its preview
+    #[serde(default)]
+    pub description: String,
+    pub scene: Scene,
+}
+
+fn user_category() -> String {
+    USER_CATEGORY.to_string()
+}
+
+impl Preset {
+    /// Description followed by the formulas and parameters, shown when hovering over the preset
+    pub fn preview(&self) -> String {
+        let formulas = &self.scene.formulas;
+        let mut preview = String::new();
+        if !self.description.is_empty() {
+            preview.push_str(&self.description);
+            preview.push('\n');
+        }
+        preview.push_str(&format!("X: {}\nY: {}\nZ: {}", formulas.x, formulas.y, formulas.z));
+        for parameter in &formulas.parameters {
+            preview.push_str(&format!("\n{} = {} ({} to {})", parameter.name, parameter.value, parameter.min, parameter.max));
+        }
+        preview
+    }
+}
+
+/// The categories of `presets`, in the order they first appear
+pub fn categories<'a>(presets: impl IntoIterator<Item = &'a Preset>) -> Vec<&'a str> {
+    let mut categories: Vec<&str> = Vec::new();
+    for preset in presets {
+        if !categories.contains(&preset.category.as_str()) {
+            categories.push(&preset.category);
+        }
+    }
+    categories
+}
+
+fn grid(counts: [u32; 3], spacing: u32, sphere_radius: f32) -> GridSettings {
+    GridSettings {
+        counts,
+        spacing,
+        sphere_radius,
+        ..GridSettings::default()
+    }
+}
+
+fn formulas(x: &str, y: &str, z: &str) -> FormulaSettings {
+    FormulaSettings {
+        x: x.to_string(),
+        y: y.to_string(),
+        z: z.to_string(),
+        ..FormulaSettings::default()
+    }
+}
+
+fn formulas_with_parameters(x: &str, y: &str, z: &str, parameters: Vec<Parameter>) -> FormulaSettings {
+    FormulaSettings {
+        parameters,
+        ..formulas(x, y, z)
+    }
+}
+
+/// The presets that come with the app
+pub fn builtin_presets() -> Vec<Preset> {
+    vec![
+        Preset {
+            name: "Ripples".to_string(),
+            category: "Surfaces".to_string(),
+            description: "Rings spreading out from the centre like a stone dropped in water".to_string(),
+            scene: Scene {
+                grid: grid([41, 1, 41], 6, 2.5),
+                formulas: formulas_with_parameters(
+                    "x",
+                    "amplitude * sin(sqrt(x^2 + z^2) / wavelength - time * speed)",
+                    "z",
+                    vec![
+                        Parameter::new("amplitude", 10., 0., 40.),
+                        Parameter::new("wavelength", 8., 2., 30.),
+                        Parameter::new("speed", 3., 0., 10.),
+                    ],
+                ),
+                mode: SimulationMode::Plot,
+                sphere_color: [0.2, 0.6, 1., 1.],
+                camera: CameraSettings::looking_at(Vec3::new(220., 180., 220.), Vec3::ZERO),
+                ..Scene::default()
+            },
+        },
+        Preset {
+            name: "Saddle".to_string(),
+            category: "Surfaces".to_string(),
+            description: "A hyperbolic paraboloid rocking between its two orientations".to_string(),
+            scene: Scene {
+                formulas: formulas("x", "(x^2 - z^2) / 150 * cos(time)", "z"),
+                mode: SimulationMode::Plot,
+                sphere_color: [1., 0.5, 0.1, 1.],
+                ..Scene::default()
+            },
+        },
+        Preset {
+            name: "Standing Waves".to_string(),
+            category: "Surfaces".to_string(),
+            description: "A drum skin vibrating with fixed nodal lines".to_string(),
+            scene: Scene {
+                grid: grid([41, 1, 41], 6, 2.5),
+                formulas: formulas("x", "20 * sin(x / 20) * sin(z / 20) * cos(time * 2)", "z"),
+                mode: SimulationMode::Plot,
+                sphere_color: [0.4, 1., 0.6, 1.],
+                camera: CameraSettings::looking_at(Vec3::new(220., 180., 220.), Vec3::ZERO),
+                ..Scene::default()
+            },
+        },
+        Preset {
+            name: "Stretching Waves".to_string(),
+            category: "Surfaces".to_string(),
+            description: "Waves that start packed tightly together and spread further apart as time passes".to_string(),
+            scene: Scene {
+                formulas: formulas("x", "sin(x / time * 25) * 25", "z"),
+                mode: SimulationMode::Plot,
+                sphere_color: [0.7, 0.4, 1., 1.],
+                ..Scene::default()
+            },
+        },
+        Preset {
+            name: "Torus".to_string(),
+            category: "Parametric".to_string(),
+            description: "A spinning ring, with the angle around the ring on y and around the tube on z".to_string(),
+            scene: Scene {
+                grid: grid([1, 48, 24], 1, 4.),
+                // Every axis is read before its own formula moves it, so the angles are still the lattice's
+                formulas: formulas(
+                    "(100 + 40 * cos(z * tau / 24)) * cos(y * tau / 48 + time / 2)",
+                    "(100 + 40 * cos(z * tau / 24)) * sin(y * tau / 48 + time / 2)",
+                    "40 * sin(z * tau / 24)",
+                ),
+                mode: SimulationMode::Plot,
+                sphere_color: [1., 0.3, 0.6, 1.],
+                camera: CameraSettings::looking_at(Vec3::new(250., 150., 400.), Vec3::ZERO),
+                ..Scene::default()
+            },
+        },
+        Preset {
+            name: "Helix".to_string(),
+            category: "Parametric".to_string(),
+            description: "A coil turning about the z axis, traced by one row of points".to_string(),
+            scene: Scene {
+                grid: grid([1, 1, 240], 1, 3.),
+                formulas: formulas("60 * cos(z / 8 + time)", "60 * sin(z / 8 + time)", "z * 1.5"),
+                mode: SimulationMode::Plot,
+                sphere_color: [0.8, 0.8, 1., 1.],
+                camera: CameraSettings::looking_at(Vec3::new(350., 200., 150.), Vec3::ZERO),
+                ..Scene::default()
+            },
+        },
+        Preset {
+            name: "Möbius Strip".to_string(),
+            category: "Parametric".to_string(),
+            description: "A one-sided band, with the layers of a cylinder across it and the angle around the cylinder along it".to_string(),
+            scene: Scene {
+                // The layout's `theta` is the angle along the band, and `cos(phi) / sin(phi)` is a point's layer
+                // height over its ring radius, which counts the layers out from the middle of the band
+                grid: GridSettings {
+                    layout: PointLayout::Cylindrical,
+                    ..grid([1, 7, 72], 10, 3.)
+                },
+                formulas: formulas(
+                    "(100 + 8 * cos(phi) / sin(phi) * cos(theta / 2)) * cos(theta + time / 2)",
+                    "(100 + 8 * cos(phi) / sin(phi) * cos(theta / 2)) * sin(theta + time / 2)",
+                    "8 * cos(phi) / sin(phi) * sin(theta / 2)",
+                ),
+                mode: SimulationMode::Plot,
+                sphere_color: [1., 0.9, 0.3, 1.],
+                camera: CameraSettings::looking_at(Vec3::new(250., 200., 300.), Vec3::ZERO),
+                ..Scene::default()
+            },
+        },
+        Preset {
+            name: "Lorenz Attractor".to_string(),
+            category: "Dynamics".to_string(),
+            description: "A cloud of points following the Lorenz system, scaled up five times, settling onto its butterfly".to_string(),
+            scene: Scene {
+                grid: grid([4, 4, 4], 3, 2.),
+                formulas: formulas_with_parameters(
+                    "x + sigma * (y - x) * 0.01",
+                    "y + (x * (rho - z / 5) - y) * 0.01",
+                    "z + (x * y / 5 - beta * z) * 0.01",
+                    vec![
+                        Parameter::new("sigma", 10., 0., 20.),
+                        Parameter::new("rho", 28., 0., 50.),
+                        Parameter::new("beta", 8. / 3., 0., 5.),
+                    ],
+                ),
+                mode: SimulationMode::Iterate,
+                sphere_color: [1., 0.2, 0.2, 1.],
+                camera: CameraSettings::looking_at(Vec3::new(350., 300., 125.), Vec3::new(0., 0., 125.)),
+                ..Scene::default()
+            },
+        },
+    ]
+}
+
+/// Name the user presets are stored under
+const USER_PRESETS: &str = "presets";
+
+pub fn presets_from_ron(text: &str) -> Result<Vec<Preset>, StorageError> {
+    ron::from_str(text).map_err(|error| StorageError(format!("Invalid presets: {}", error)))
+}
+
+pub fn presets_to_ron(presets: &[Preset]) -> Result<String, StorageError> {
+    ron::ser::to_string_pretty(presets, ron::ser::PrettyConfig::new())
+        .map_err(|error| StorageError(format!("Couldn't write presets: {}", error)))
+}
+
+/// Presets the user saved, stored next to the settings as a list that can also be edited by hand
+pub fn load_user_presets() -> Result<Vec<Preset>, StorageError> {
+    Ok(match storage::read(USER_PRESETS)? {
+        Some(text) => presets_from_ron(&text)?,
+        None => Vec::new(),
+    })
+}
+
+pub fn save_user_presets(presets: &[Preset]) -> Result<(), StorageError> {
+    storage::write(USER_PRESETS, &presets_to_ron(presets)?)
+}
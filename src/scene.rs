@@ -0,0 +1,284 @@
+use std::{fmt, fs, io, path::{Path, PathBuf}};
+
+use glam::{Mat3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use super::parsing_function::{is_identifier, is_reserved_name, FormulaParser};
+use super::point_evaluation::{SimulationMode, GRID_SPACING};
+use super::point_layout::PointLayout;
+
+/// Version written into new scene files. Bump it whenever a change to `Scene` would stop older files from reading
+/// correctly, and teach `Scene::from_ron` to upgrade the old layout.
+pub const SCENE_VERSION: u32 = 1;
+
+/// Everything needed to bring a simulation back the way it was saved, stored as RON.
+/// Sections left out of a file keep their default values, so hand-written scenes only need what they change.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub version: u32,
+    pub grid: GridSettings,
+    pub formulas: FormulaSettings,
+    pub mode: SimulationMode,
+    /// Colour of the spheres as sRGB red, green, blue and alpha
+    pub sphere_color: [f32; 4],
+    pub camera: CameraSettings,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            version: SCENE_VERSION,
+            grid: GridSettings::default(),
+            formulas: FormulaSettings::default(),
+            mode: SimulationMode::default(),
+            sphere_color: [1., 1., 0., 1.],
+            camera: CameraSettings::default(),
+        }
+    }
+}
+
+/// Where the points start
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridSettings {
+    pub layout: PointLayout,
+    /// Number of points along the x, y and z axes, or however the layout uses them
+    pub counts: [u32; 3],
+    /// Distance between neighbouring points
+    pub spacing: u32,
+    pub sphere_radius: f32,
+    /// How finely each sphere's mesh is divided. Every step quadruples its triangles.
+    pub sphere_subdivisions: u32,
+    /// Seed scattering the points of the random layouts
+    pub layout_seed: u32,
+    /// CSV, TSV, PLY or PNG file to load the points from instead of the layout
+    pub point_file: Option<PathBuf>,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        GridSettings {
+            layout: PointLayout::Grid,
+            counts: [25, 1, 25],
+            spacing: GRID_SPACING,
+            sphere_radius: 5.,
+            sphere_subdivisions: 6,
+            layout_seed: 0,
+            point_file: None,
+        }
+    }
+}
+
+/// The text of the formula moving each axis, and how it is read
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormulaSettings {
+    pub x: String,
+    pub y: String,
+    pub z: String,
+    pub implicit_multiplication: bool,
+    /// Value of the `seed` constant
+    pub seed: f32,
+    /// Named values the formulas can read, each set with a slider
+    pub parameters: Vec<Parameter>,
+}
+
+impl FormulaSettings {
+    /// A parser reading formulas the way these settings say
+    pub fn parser(&self) -> FormulaParser {
+        let mut parser = FormulaParser::default();
+        self.apply_to(&mut parser);
+        parser
+    }
+
+    /// Makes `parser` read formulas the way these settings say, keeping its columns and limits
+    pub fn apply_to(&self, parser: &mut FormulaParser) {
+        parser.implicit_multiplication = self.implicit_multiplication;
+        parser.seed = self.seed;
+        parser.parameters = parameter_values(&self.parameters);
+    }
+}
+
+impl Default for FormulaSettings {
+    fn default() -> Self {
+        FormulaSettings {
+            x: String::from("x"),
+            y: String::from("sin(x - time) * 10"),
+            z: String::from("z"),
+            implicit_multiplication: false,
+            seed: 0.,
+            parameters: Vec::new(),
+        }
+    }
+}
+
+/// A named value formulas read like a variable, set with a slider running from `min` to `max`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Parameter {
+    pub name: String,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Parameter {
+    pub fn new(name: &str, value: f32, min: f32, max: f32) -> Self {
+        Parameter { name: name.to_string(), value, min, max }
+    }
+}
+
+impl Default for Parameter {
+    fn default() -> Self {
+        Parameter::new("", 0., 0., 1.)
+    }
+}
+
+/// The names and values of `parameters`, as `FormulaParser::parameters` takes them
+pub fn parameter_values(parameters: &[Parameter]) -> Vec<(String, f32)> {
+    parameters.iter().map(|parameter| (parameter.name.clone(), parameter.value)).collect()
+}
+
+/// Checks that formulas could read a new parameter called `name` alongside `parameters`: it has to be a single
+/// name the formula lexer reads whole, not a built-in variable or function, and not already taken, ignoring case
+/// like formulas do.
+pub fn check_parameter_name(name: &str, parameters: &[Parameter]) -> Result<(), String> {
+    if !is_identifier(name) {
+        Err(format!("`{}` isn't a name formulas can use, it must be letters, digits and `_` not starting with a digit", name))
+    } else if is_reserved_name(name) {
+        Err(format!("`{}` is already a built-in variable or function", name))
+    } else if parameters.iter().any(|parameter| parameter.name.eq_ignore_ascii_case(name)) {
+        Err(format!("There is already a parameter called `{}`", name))
+    } else {
+        Ok(())
+    }
+}
+
+/// Where the orbit camera looks from. Its position is `focus` plus `radius` along the rotated z axis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub focus: [f32; 3],
+    pub radius: f32,
+    /// Rotation quaternion as x, y, z and w
+    pub rotation: [f32; 4],
+}
+
+impl CameraSettings {
+    /// Looking at `focus` from `position`, upright like Bevy's `Transform::looking_at` with `Vec3::Y` as up
+    pub fn looking_at(position: Vec3, focus: Vec3) -> Self {
+        let back = (position - focus).normalize();
+        let right = Vec3::Y.cross(back).normalize();
+        let up = back.cross(right);
+        CameraSettings {
+            focus: focus.to_array(),
+            radius: (position - focus).length(),
+            rotation: Quat::from_mat3(&Mat3::from_cols(right, up, back)).to_array(),
+        }
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings::looking_at(Vec3::new(350., 250., 350.), Vec3::ZERO)
+    }
+}
+
+/// Why a scene could not be read or written
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    /// The text is not a valid scene
+    Format(String),
+    /// Saved by a newer build, which may have added things this one would silently drop
+    NewerVersion(u32),
+    /// Older than any layout this build knows how to upgrade
+    UnsupportedVersion(u32),
+    /// The scene holds something RON can't write, like a point file path that isn't valid UTF-8
+    Serialize(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{}", error),
+            SceneError::Format(message) => write!(f, "Invalid scene: {}", message),
+            SceneError::NewerVersion(version) => write!(
+                f,
+                "Scene version {} was saved by a newer version of the app, which reads up to version {}",
+                version, SCENE_VERSION,
+            ),
+            SceneError::UnsupportedVersion(version) => write!(
+                f,
+                "Scene version {} is no longer supported, only versions 1 to {} can be loaded",
+                version, SCENE_VERSION,
+            ),
+            SceneError::Serialize(message) => write!(f, "Couldn't write the scene: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+/// Just enough of a scene to find out which layout the rest of it uses. Scenes without a version are read as the
+/// current one, like any other field they leave out.
+#[derive(Deserialize)]
+#[serde(rename = "Scene")]
+struct SceneVersion {
+    #[serde(default = "current_version")]
+    version: u32,
+}
+
+fn current_version() -> u32 {
+    SCENE_VERSION
+}
+
+impl Scene {
+    pub fn from_ron(text: &str) -> Result<Scene, SceneError> {
+        let SceneVersion { version } = ron::from_str(text).map_err(|error| SceneError::Format(error.to_string()))?;
+        match version {
+            SCENE_VERSION => {
+                let scene: Scene = ron::from_str(text).map_err(|error| SceneError::Format(error.to_string()))?;
+                scene.check_parameters()?;
+                Ok(scene)
+            },
+            version if version > SCENE_VERSION => Err(SceneError::NewerVersion(version)),
+            // Layouts replaced by a later version get upgraded here, before falling back to refusing them
+            version => Err(SceneError::UnsupportedVersion(version)),
+        }
+    }
+
+    fn check_parameters(&self) -> Result<(), SceneError> {
+        let parameters = &self.formulas.parameters;
+        for (index, parameter) in parameters.iter().enumerate() {
+            check_parameter_name(&parameter.name, &parameters[..index]).map_err(SceneError::Format)?;
+            if !(parameter.min.is_finite() && parameter.max.is_finite() && parameter.min <= parameter.max) {
+                return Err(SceneError::Format(format!(
+                    "parameter `{}` runs from {} to {}, which must be finite with the minimum no higher than the maximum",
+                    parameter.name, parameter.min, parameter.max,
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_ron(&self) -> Result<String, SceneError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new()).map_err(|error| SceneError::Serialize(error.to_string()))
+    }
+
+    pub fn load(path: &Path) -> Result<Scene, SceneError> {
+        Scene::from_ron(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SceneError> {
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+}
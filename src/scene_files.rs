@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use super::move_spheres::UiState;
+use super::pan_camera::{orbit_transform, PanOrbitCamera};
+use super::parsing_function::FormulaParser;
+use super::presets::{save_user_presets, Preset, USER_CATEGORY};
+use super::scene::{CameraSettings, GridSettings, Scene};
+use super::spawn_spheres::SphereGrid;
+
+/// Saves and loads scene files for the "Simulation Options" window. Added by `MoveSpheres`, and uses the grid and
+/// camera from `SpawnSpheres` and `AddOrbitCamera` when they are there.
+pub(crate) struct SceneFiles;
+
+/// Asks for the running simulation to be written to a scene file, or replaced by one
+pub enum SceneFileEvent {
+    Save(PathBuf),
+    Load(PathBuf),
+    /// Adds the running simulation to the user presets under this name, replacing one with the same name
+    SavePreset(String),
+}
+
+/// Replaces the formulas, grid and camera with the scene's, rebuilding the spheres
+pub struct ApplyScene(pub Scene);
+
+impl Plugin for SceneFiles {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SceneFileEvent>()
+            .add_event::<ApplyScene>()
+            .add_system(scene_files.label("scene_files").after("ui_update"))
+            .add_system(apply_scene.after("scene_files"));
+    }
+}
+
+fn scene_files(
+    mut ev_files: EventReader<SceneFileEvent>,
+    mut ev_apply: EventWriter<ApplyScene>,
+    mut ui_state: ResMut<UiState>,
+    parser: Res<FormulaParser>,
+    grid: Option<Res<SphereGrid>>,
+    cameras: Query<(&PanOrbitCamera, &Transform)>,
+) {
+    for event in ev_files.iter() {
+        match event {
+            SceneFileEvent::Save(path) => {
+                let scene = current_scene(&ui_state, &parser, grid.as_deref(), cameras.iter().next());
+                ui_state.scene_message = match scene.save(path) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(error) => format!("Couldn't save {}: {}", path.display(), error),
+                };
+            },
+            SceneFileEvent::Load(path) => match Scene::load(path) {
+                Ok(scene) => {
+                    ui_state.scene_message = format!("Loaded {}", path.display());
+                    ev_apply.send(ApplyScene(scene));
+                },
+                Err(error) => {
+                    ui_state.scene_message = format!("Couldn't load {}: {}", path.display(), error);
+                },
+            },
+            SceneFileEvent::SavePreset(name) => {
+                let name = name.trim();
+                if name.is_empty() {
+                    ui_state.scene_message = "Name the preset to save it".to_string();
+                    continue;
+                }
+                let preset = Preset {
+                    name: name.to_string(),
+                    category: USER_CATEGORY.to_string(),
+                    description: String::new(),
+                    scene: current_scene(&ui_state, &parser, grid.as_deref(), cameras.iter().next()),
+                };
+                match ui_state.user_presets.iter_mut().find(|existing| existing.name == preset.name) {
+                    Some(existing) => *existing = preset,
+                    None => ui_state.user_presets.push(preset),
+                }
+                ui_state.scene_message = match save_user_presets(&ui_state.user_presets) {
+                    Ok(()) => format!("Saved the {} preset", name),
+                    Err(error) => format!("Couldn't save the {} preset: {}", name, error),
+                };
+            },
+        }
+    }
+}
+
+pub(crate) fn current_scene(ui_state: &UiState, parser: &FormulaParser, grid: Option<&SphereGrid>, camera: Option<(&PanOrbitCamera, &Transform)>) -> Scene {
+    let mut scene = Scene {
+        formulas: ui_state.formula_settings(parser),
+        mode: ui_state.mode,
+        ..Scene::default()
+    };
+    if let Some(grid) = grid {
+        scene.grid = grid_settings(grid);
+        scene.sphere_color = grid.color.as_rgba_f32();
+    }
+    if let Some((camera, transform)) = camera {
+        scene.camera = CameraSettings {
+            focus: camera.focus.to_array(),
+            radius: camera.radius,
+            rotation: transform.rotation.to_array(),
+        };
+    }
+    scene
+}
+
+fn apply_scene(
+    mut ev_apply: EventReader<ApplyScene>,
+    mut ui_state: ResMut<UiState>,
+    mut parser: ResMut<FormulaParser>,
+    mut grid: Option<ResMut<SphereGrid>>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform)>,
+) {
+    for ApplyScene(scene) in ev_apply.iter() {
+        ui_state.set_formulas(&scene.formulas, &mut parser);
+        ui_state.mode = scene.mode;
+        ui_state.error = String::new();
+
+        if let Some(grid) = grid.as_mut() {
+            **grid = sphere_grid(scene);
+        }
+        for (mut camera, mut transform) in cameras.iter_mut() {
+            place_camera(&scene.camera, &mut camera, &mut transform);
+        }
+    }
+}
+
+pub(crate) fn sphere_grid(scene: &Scene) -> SphereGrid {
+    let [red, green, blue, alpha] = scene.sphere_color;
+    grid_with_settings(&scene.grid, Color::rgba(red, green, blue, alpha))
+}
+
+/// The lattice `settings` describe, built from spheres of `color`
+pub(crate) fn grid_with_settings(settings: &GridSettings, color: Color) -> SphereGrid {
+    let [x_count, y_count, z_count] = settings.counts;
+    SphereGrid {
+        layout: settings.layout,
+        x_count,
+        y_count,
+        z_count,
+        spacing: settings.spacing,
+        radius: settings.sphere_radius,
+        subdivisions: settings.sphere_subdivisions,
+        color,
+        seed: settings.layout_seed,
+        point_file: settings.point_file.clone(),
+    }
+}
+
+pub(crate) fn grid_settings(grid: &SphereGrid) -> GridSettings {
+    GridSettings {
+        layout: grid.layout,
+        counts: [grid.x_count, grid.y_count, grid.z_count],
+        spacing: grid.spacing,
+        sphere_radius: grid.radius,
+        sphere_subdivisions: grid.subdivisions,
+        layout_seed: grid.seed,
+        point_file: grid.point_file.clone(),
+    }
+}
+
+pub(crate) fn place_camera(settings: &CameraSettings, camera: &mut PanOrbitCamera, transform: &mut Transform) {
+    camera.focus = Vec3::from(settings.focus);
+    camera.radius = settings.radius;
+    *transform = orbit_transform(camera.focus, camera.radius, Quat::from_array(settings.rotation).normalize());
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use super::scene::Scene;
+use super::storage::{self, StorageError};
+
+/// What the app remembers between sessions: the last simulation and how the windows were laid out.
+/// Stored as RON in the platform's config directory, or in `localStorage` when running in a browser.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Size of the main window in logical pixels
+    pub window_size: [f32; 2],
+    /// Top left corner of the "Simulation Options" window, or `None` to let egui place it
+    pub options_window_position: Option<[f32; 2]>,
+    /// Formulas, grid, camera and the rest of the simulation as last used
+    pub scene: Scene,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window_size: [1200., 800.],
+            options_window_position: None,
+            scene: Scene::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_ron(text: &str) -> Result<Settings, StorageError> {
+        ron::from_str(text).map_err(|error| StorageError(format!("Invalid settings: {}", error)))
+    }
+
+    pub fn to_ron(&self) -> Result<String, StorageError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+            .map_err(|error| StorageError(format!("Couldn't write settings: {}", error)))
+    }
+
+    /// The stored settings, `Ok(None)` when nothing has been stored yet
+    pub fn load() -> Result<Option<Settings>, StorageError> {
+        storage::read("settings")?.map(|text| Settings::from_ron(&text)).transpose()
+    }
+
+    pub fn save(&self) -> Result<(), StorageError> {
+        storage::write("settings", &self.to_ron()?)
+    }
+
+    /// Forgets the stored settings, so the next launch starts from the defaults
+    pub fn clear() -> Result<(), StorageError> {
+        storage::remove("settings")
+    }
+}
@@ -1,24 +1,107 @@
+use std::path::PathBuf;
+
 use bevy::prelude::*;
+use super::formula_tree::spherical_coordinates;
+use super::point_evaluation::GRID_SPACING;
+use super::point_files::load_points;
+use super::point_layout::PointLayout;
+
+/// Most subdivisions a sphere mesh is built with, past which the meshes get too heavy to draw thousands of
+pub const MAX_SUBDIVISIONS: u32 = 10;
 
 pub struct SpawnSpheres {
+    pub layout: PointLayout,
     pub sphere_x_count: u32,
     pub sphere_y_count: u32,
     pub sphere_z_count: u32,
+    /// Distance between neighbouring spheres
+    pub spacing: u32,
+    pub radius: f32,
+    /// How finely each sphere's mesh is divided, up to `MAX_SUBDIVISIONS`
+    pub subdivisions: u32,
+    pub color: Color,
+    /// Seed scattering the points of the random layouts
+    pub seed: u32,
+    /// CSV, TSV, PLY or PNG file to load the points from instead of the layout
+    pub point_file: Option<PathBuf>,
+}
+
+impl Default for SpawnSpheres {
+    fn default() -> Self {
+        SpawnSpheres {
+            layout: PointLayout::Grid,
+            sphere_x_count: 25,
+            sphere_y_count: 1,
+            sphere_z_count: 25,
+            spacing: GRID_SPACING,
+            radius: 5.,
+            subdivisions: 6,
+            color: Color::YELLOW,
+            seed: 0,
+            point_file: None,
+        }
+    }
 }
 
+/// Which point of the layout the sphere started on, as indices along the three counts
 #[derive(Component)]
 pub struct Sphere(pub u32, pub u32, pub u32);
 
 #[derive(Component)]
 pub struct OriginalPosition(pub Transform);
 
-struct SphereCount(pub u32, pub u32, pub u32);
+/// The `r`, `theta` and `phi` the layout placed this sphere with, which formulas read however far it has moved.
+/// Spheres loaded from a point file get the spherical coordinates of their point.
+#[derive(Component)]
+pub struct LayoutParameters(pub [f32; 3]);
+
+/// Values of the extra columns a point file gave this sphere, in `LoadedPoints::column_names` order
+#[derive(Component)]
+pub struct ColumnValues(pub Vec<f32>);
+
+/// What the last load of `SphereGrid::point_file` found. Changes whenever the spheres are rebuilt.
+#[derive(Default)]
+pub struct LoadedPoints {
+    /// Names formulas read the extra columns by
+    pub column_names: Vec<String>,
+    /// Number of points loaded
+    pub count: usize,
+    /// Why the file couldn't be loaded, in which case the spheres fall back to the layout
+    pub error: Option<String>,
+}
+
+/// How the spheres are laid out. Changing it, or just setting it again, despawns every sphere and builds the new
+/// layout.
+pub struct SphereGrid {
+    pub layout: PointLayout,
+    pub x_count: u32,
+    pub y_count: u32,
+    pub z_count: u32,
+    pub spacing: u32,
+    pub radius: f32,
+    pub subdivisions: u32,
+    pub color: Color,
+    pub seed: u32,
+    pub point_file: Option<PathBuf>,
+}
 
 impl Plugin for SpawnSpheres {
     fn build(&self, app: &mut App) {
         app
-            .insert_resource(SphereCount(self.sphere_x_count, self.sphere_y_count, self.sphere_z_count))
-            .add_startup_system(add_spheres);
+            .insert_resource(SphereGrid {
+                layout: self.layout,
+                x_count: self.sphere_x_count,
+                y_count: self.sphere_y_count,
+                z_count: self.sphere_z_count,
+                spacing: self.spacing,
+                radius: self.radius,
+                subdivisions: self.subdivisions,
+                color: self.color,
+                seed: self.seed,
+                point_file: self.point_file.clone(),
+            })
+            .init_resource::<LoadedPoints>()
+            .add_system(add_spheres);
     }
 }
 
@@ -26,34 +109,59 @@ fn add_spheres(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    sphere_count: Res<SphereCount>,
+    grid: Res<SphereGrid>,
+    mut loaded: ResMut<LoadedPoints>,
+    spheres: Query<Entity, With<Sphere>>,
 ) {
+    // Also true the first time this runs, which builds the starting lattice
+    if !grid.is_changed() {
+        return;
+    }
+    for entity in spheres.iter() {
+        commands.entity(entity).despawn();
+    }
+
     let sphere_mesh = meshes.add(Mesh::from(shape::Icosphere {
-        subdivisions: 6,
-        radius: 5.,
-        ..Default::default()
+        subdivisions: grid.subdivisions.min(MAX_SUBDIVISIONS) as usize,
+        radius: grid.radius,
     }));
     let sphere_material = materials.add(StandardMaterial {
-        base_color: Color::YELLOW,
+        base_color: grid.color,
         ..Default::default()
     });
-    for x in 0..sphere_count.0 {
-        for y in 0..sphere_count.1 {
-            for z in 0..sphere_count.2 {
-                let pos = Transform::from_xyz(
-                    ((x * 12) as i64 - ((12 * sphere_count.0 as i64) / 2)) as f32,
-                    ((y * 12) as i64 - ((12 * sphere_count.1 as i64) / 2)) as f32,
-                    ((z * 12) as i64 - ((12 * sphere_count.2 as i64) / 2)) as f32);
-                commands
-                    .spawn_bundle(PbrBundle {
-                        mesh: sphere_mesh.clone(),
-                        material: sphere_material.clone(),
-                        transform: pos,
-                        ..Default::default()
-                    })
-                    .insert(Sphere(x, 0, z))
-                    .insert(OriginalPosition(pos));
-            }
+    let spawn_sphere = |commands: &mut Commands, point: Vec3, layout: [f32; 3]| {
+        let pos = Transform::from_translation(point);
+        let mut sphere = commands.spawn_bundle(PbrBundle {
+            mesh: sphere_mesh.clone(),
+            material: sphere_material.clone(),
+            transform: pos,
+            ..Default::default()
+        });
+        sphere.insert(OriginalPosition(pos)).insert(LayoutParameters(layout));
+        sphere.id()
+    };
+
+    *loaded = LoadedPoints::default();
+    if let Some(path) = &grid.point_file {
+        match load_points(path, grid.spacing) {
+            Ok(set) => {
+                loaded.column_names = set.column_names;
+                loaded.count = set.points.len();
+                for ((index, point), values) in (0u32..).zip(set.points).zip(set.data) {
+                    let entity = spawn_sphere(&mut commands, point, spherical_coordinates(point));
+                    commands.entity(entity).insert(Sphere(index, 0, 0)).insert(ColumnValues(values));
+                }
+                return;
+            },
+            Err(error) => loaded.error = Some(format!("{}: {}", path.display(), error)),
         }
     }
+
+    let counts = [grid.x_count, grid.y_count, grid.z_count];
+    let points = grid.layout.points_with_parameters(counts, grid.spacing, grid.seed);
+    let (y_count, z_count) = (grid.y_count.max(1), grid.z_count.max(1));
+    for (index, (point, layout)) in (0u32..).zip(points) {
+        let entity = spawn_sphere(&mut commands, point, layout);
+        commands.entity(entity).insert(Sphere(index / (y_count * z_count), index / z_count % y_count, index % z_count));
+    }
 }
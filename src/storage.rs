@@ -0,0 +1,94 @@
+//! Small named text documents kept between sessions: RON files in the platform's config directory, or
+//! `localStorage` entries when running in a browser.
+
+use std::fmt;
+
+/// Why a stored document could not be read or written
+#[derive(Debug)]
+pub struct StorageError(pub String);
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::{
+        fs, io,
+        path::{Path, PathBuf},
+    };
+
+    use super::StorageError;
+
+    /// Where the document called `name` is kept, like `~/.config/bevy_graph_sim/settings.ron` on Linux
+    pub fn path(name: &str) -> Option<PathBuf> {
+        dirs::config_dir().map(|directory| directory.join("bevy_graph_sim").join(format!("{}.ron", name)))
+    }
+
+    fn required_path(name: &str) -> Result<PathBuf, StorageError> {
+        path(name).ok_or_else(|| StorageError("No config directory on this platform".to_string()))
+    }
+
+    fn describe(error: io::Error, path: &Path) -> StorageError {
+        StorageError(format!("{}: {}", path.display(), error))
+    }
+
+    pub fn read(name: &str) -> Result<Option<String>, StorageError> {
+        let path = required_path(name)?;
+        match fs::read_to_string(&path) {
+            Ok(text) => Ok(Some(text)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(describe(error, &path)),
+        }
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), StorageError> {
+        let path = required_path(name)?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|error| describe(error, directory))?;
+        }
+        fs::write(&path, text).map_err(|error| describe(error, &path))
+    }
+
+    pub fn remove(name: &str) -> Result<(), StorageError> {
+        let path = required_path(name)?;
+        match fs::remove_file(&path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(describe(error, &path)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::StorageError;
+
+    fn local_storage() -> Result<web_sys::Storage, StorageError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| StorageError("localStorage is not available".to_string()))
+    }
+
+    /// `localStorage` key the document called `name` is kept under
+    fn key(name: &str) -> String {
+        format!("bevy_graph_sim.{}", name)
+    }
+
+    pub fn read(name: &str) -> Result<Option<String>, StorageError> {
+        local_storage()?.get_item(&key(name)).map_err(|_| StorageError("Couldn't read localStorage".to_string()))
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), StorageError> {
+        local_storage()?.set_item(&key(name), text).map_err(|_| StorageError("Couldn't write localStorage, it may be full".to_string()))
+    }
+
+    pub fn remove(name: &str) -> Result<(), StorageError> {
+        local_storage()?.remove_item(&key(name)).map_err(|_| StorageError("Couldn't clear localStorage".to_string()))
+    }
+}
+
+pub use platform::*;
@@ -0,0 +1,51 @@
+use std::{fs, path::PathBuf};
+
+use bevy_graph_sim::file_watch::{read_formula_files, FileWatch};
+
+/// An empty directory of its own for each test
+fn test_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("bevy_graph_sim-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[test]
+fn reports_created_changed_and_deleted_files() {
+    let directory = test_directory("file_watch");
+    let path = directory.join("scene.ron");
+    let mut watch = FileWatch::new([path.clone()]);
+    assert!(!watch.poll());
+
+    fs::write(&path, "(version: 1)").unwrap();
+    assert!(watch.poll());
+    assert!(!watch.poll());
+
+    fs::write(&path, "(version: 1, grid: (spacing: 3))").unwrap();
+    assert!(watch.poll());
+
+    fs::remove_file(&path).unwrap();
+    assert!(watch.poll());
+    assert!(!watch.poll());
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn first_poll_reports_existing_files() {
+    let directory = test_directory("file_watch_existing");
+    let path = directory.join("x.formula");
+    fs::write(&path, "x").unwrap();
+    let mut watch = FileWatch::new([path]);
+    assert!(watch.poll());
+    assert!(!watch.poll());
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn reads_trimmed_formula_files() {
+    let directory = test_directory("formula_files");
+    fs::write(directory.join("x.formula"), "x + 1\n").unwrap();
+    fs::write(directory.join("z.formula"), "  sin(z)  ").unwrap();
+    assert_eq!(read_formula_files(&directory), [Some("x + 1".to_string()), None, Some("sin(z)".to_string())]);
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,111 @@
+use bevy_graph_sim::{
+    formula_interval::{evaluate_interval, Interval},
+    parsing_function::FormulaParser,
+};
+use glam::Vec3;
+
+const STEPS: usize = 24;
+
+fn sample(interval: Interval, step: usize) -> f32 {
+    interval.min + (interval.max - interval.min) * step as f32 / STEPS as f32
+}
+
+/// Checks the computed bounds contain the value at every point of a grid over the input ranges
+fn assert_encloses_samples(formula: &str, time: Interval, x: Interval, y: Interval, z: Interval) -> Interval {
+    let parser = FormulaParser::new();
+    let expr = parser.parse_tree(formula).unwrap();
+    let bounds = evaluate_interval(&expr, time, x, y, z);
+    for t in 0..=STEPS {
+        for i in 0..=STEPS {
+            for j in 0..=STEPS {
+                for k in 0..=STEPS {
+                    let time_elapsed = sample(time, t);
+                    let point = Vec3::new(sample(x, i), sample(y, j), sample(z, k));
+                    let value = expr.evaluate(time_elapsed, point);
+                    if !value.is_nan() {
+                        assert!(bounds.contains(value), "{} = {} at time {}, {:?} is outside {:?}", formula, value, time_elapsed, point, bounds);
+                    }
+                }
+            }
+        }
+    }
+    bounds
+}
+
+#[test]
+fn bounds_enclose_sampled_values() {
+    let time = Interval::new(0.5, 20.);
+    let grid = Interval::new(-150., 150.);
+    for formula in [
+        "x",
+        "sin(x - time) * 10",
+        "sin(x / time * 25) * 25",
+        "abs(x - z) / 3 + cos(z / 4)",
+        "x * z - y * y",
+        "(x / 100) ^ 2 + 2 ^ (time / 10)",
+        "time ^ 3 - 4 ^ (time / 7)",
+    ] {
+        assert_encloses_samples(formula, time, grid, grid, grid);
+    }
+}
+
+#[test]
+fn linear_bounds_are_exact() {
+    let bounds = assert_encloses_samples("x * 2 + 1", Interval::point(0.), Interval::new(-3., 5.), Interval::point(0.), Interval::point(0.));
+    assert_eq!(bounds, Interval::new(-5., 11.));
+}
+
+#[test]
+fn sin_is_bounded_by_its_extremes() {
+    let full_period = Interval::new(0., 7.).sin();
+    assert_eq!(full_period, Interval::new(-1., 1.));
+
+    // Increasing between -pi / 2 and pi / 2, so only the endpoints matter
+    let rising = Interval::new(-1., 1.).sin();
+    assert!(rising.min <= f32::sin(-1.) && rising.min > -0.85);
+    assert!(rising.max >= f32::sin(1.) && rising.max < 0.85);
+
+    // Peaks at pi / 2 inside the interval
+    let peak = Interval::new(1., 2.).sin();
+    assert_eq!(peak.max, 1.);
+    assert!(peak.min <= f32::sin(1.) && peak.min > 0.8);
+}
+
+#[test]
+fn tan_is_unbounded_across_poles() {
+    assert_eq!(Interval::new(1., 2.).tan(), Interval::ENTIRE);
+    assert_eq!(Interval::new(-2., -1.).tan(), Interval::ENTIRE);
+    assert_eq!(Interval::new(0., 4.).tan(), Interval::ENTIRE);
+
+    let between_poles = Interval::new(-1., 1.).tan();
+    assert!(between_poles.min <= f32::tan(-1.) && between_poles.max >= f32::tan(1.));
+    assert!(between_poles.max < 1.6);
+}
+
+#[test]
+fn division_by_intervals_containing_zero() {
+    let positive = Interval::new(1., 2.);
+    assert_eq!(positive / Interval::new(-1., 1.), Interval::ENTIRE);
+    assert_eq!(positive / Interval::new(0., 4.), Interval::new(0.25, f32::INFINITY));
+    assert_eq!(positive / Interval::new(-4., 0.), Interval::new(f32::NEG_INFINITY, -0.25));
+    assert_eq!(Interval::new(-2., -1.) / Interval::new(0., 4.), Interval::new(f32::NEG_INFINITY, -0.25));
+    assert_eq!(Interval::new(-1., 1.) / Interval::new(0., 4.), Interval::ENTIRE);
+    assert_eq!(positive / Interval::point(0.), Interval::ENTIRE);
+    assert_eq!(positive / Interval::new(2., 4.), Interval::new(0.25, 1.));
+}
+
+#[test]
+fn even_powers_are_never_negative() {
+    let squared = Interval::new(-3., 2.).pow(Interval::point(2.));
+    assert!(squared.min <= 0. && squared.min > -0.001);
+    assert!(squared.max >= 9. && squared.max < 9.001);
+    assert_eq!(Interval::new(-3., 2.).pow(Interval::point(-1.)), Interval::ENTIRE);
+    assert_eq!(Interval::new(-3., 2.).pow(Interval::point(0.5)), Interval::ENTIRE);
+}
+
+#[test]
+fn sqrt_is_bounded_on_its_domain() {
+    let bounds = assert_encloses_samples("√(x² + 1)", Interval::point(0.), Interval::new(-3., 2.), Interval::point(0.), Interval::point(0.));
+    assert!(bounds.min <= 1. && bounds.min > 0.99 && bounds.max >= 3.16 && bounds.max < 3.17);
+    assert_eq!(Interval::new(-1., 4.).sqrt(), Interval::ENTIRE);
+}
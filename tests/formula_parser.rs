@@ -0,0 +1,306 @@
+use bevy_graph_sim::{
+    formula_error::Span,
+    formula_lexer::{tokenize, TokenKind},
+    formula_tree::Expr,
+    parsing_function::{is_identifier, is_reserved_name, FormulaParser, FunctionType, Operator},
+};
+use glam::Vec3;
+
+fn evaluate(formula: &str, time_elapsed: f32, point_pos: Vec3) -> f32 {
+    FormulaParser::new().parse_tree(formula).unwrap().evaluate(time_elapsed, point_pos)
+}
+
+#[test]
+fn tokens_carry_kinds_and_spans() {
+    let tokens = tokenize("sin(x1, 2.5)^time").unwrap();
+    let kinds: Vec<TokenKind> = tokens.iter().map(|token| token.kind.clone()).collect();
+    assert_eq!(kinds, vec![
+        TokenKind::Identifier("sin".to_string()),
+        TokenKind::OpenParen,
+        TokenKind::Identifier("x1".to_string()),
+        TokenKind::Comma,
+        TokenKind::Number(2.5),
+        TokenKind::CloseParen,
+        TokenKind::Operator(Operator::Exponentiation),
+        TokenKind::Identifier("time".to_string()),
+    ]);
+    assert_eq!(tokens[4].span, Span { start: 8, end: 11, byte_start: 8, byte_end: 11, line: 1, column: 9 });
+    assert_eq!(tokens[7].span, Span { start: 13, end: 17, byte_start: 13, byte_end: 17, line: 1, column: 14 });
+}
+
+#[test]
+fn tokens_track_lines() {
+    let tokens = tokenize("x +\n  y").unwrap();
+    assert_eq!((tokens[2].span.line, tokens[2].span.column), (2, 3));
+    assert_eq!(tokens[2].span.start, 6);
+    let tokens = tokenize("x +\r\n  y").unwrap();
+    assert_eq!((tokens[2].span.line, tokens[2].span.column), (2, 3));
+}
+
+#[test]
+fn spans_count_chars_and_bytes() {
+    let text = "π × x²";
+    let tokens = tokenize(text).unwrap();
+    let spans: Vec<(usize, usize, &str)> = tokens.iter()
+        .map(|token| (token.span.start, token.span.column as usize, &text[token.span.byte_start..token.span.byte_end]))
+        .collect();
+    assert_eq!(spans, vec![(0, 1, "π"), (2, 3, "×"), (4, 5, "x"), (5, 6, "²"), (5, 6, "²")]);
+}
+
+#[test]
+fn unicode_math_symbols_are_aliases() {
+    let point = Vec3::new(2., 3., 9.);
+    assert_eq!(evaluate("π", 0., point), std::f32::consts::PI);
+    assert_eq!(evaluate("τ / 2 - pi", 0., point), 0.);
+    assert_eq!(evaluate("x × y ÷ 3 − 1", 0., point), 1.);
+    assert_eq!(evaluate("x · y ⋅ 2", 0., point), 12.);
+    assert_eq!(evaluate("√z + √(z + 7)", 0., point), 7.);
+    assert_eq!(evaluate("√x^2 * 2", 0., point), 4.);
+    assert_eq!(evaluate("x² + y³", 0., point), 31.);
+    assert_eq!(evaluate("x¹⁰", 0., point), 1024.);
+    assert_eq!(evaluate("sqrt(z)", 0., point), 3.);
+    assert_eq!(
+        FormulaParser::new().parse_tree("πx").unwrap_err().to_string(),
+        "Expected an operator, but found `x` at line: 1, column: 2",
+    );
+}
+
+#[test]
+fn identifiers_use_maximal_munch() {
+    let parser = FormulaParser::new();
+    assert_eq!(parser.parse_tree("time").unwrap(), Expr::Time);
+    // Neither `t` nor the `x` inside `exp` are read as separate tokens
+    assert!(parser.parse_tree("t").is_err());
+    assert!(parser.parse_tree("exp(1)").unwrap_err().message.contains("`exp`"));
+    assert!(parser.parse_tree("xy").unwrap_err().message.contains("`xy`"));
+    assert_eq!(parser.parse_tree("SIN(X)").unwrap(), Expr::Function(FunctionType::Sin, vec![Expr::PointX]));
+}
+
+#[test]
+fn operators_follow_precedence_and_associativity() {
+    let point = Vec3::new(2., 3., 4.);
+    assert_eq!(evaluate("1 - 2 + 3", 0., point), 2.);
+    assert_eq!(evaluate("8 / 4 / 2", 0., point), 1.);
+    assert_eq!(evaluate("2 ^ 3 ^ 2", 0., point), 512.);
+    assert_eq!(evaluate("1 + 2 * 3 - 4", 0., point), 3.);
+    assert_eq!(evaluate("(1 + 2) * (3 - 4)", 0., point), -3.);
+    assert_eq!(evaluate("x * y ^ 2 - z / time", 2., point), 16.);
+    assert_eq!(evaluate("1.5 + .5", 0., point), 2.);
+}
+
+#[test]
+fn errors_point_at_the_problem() {
+    let parser = FormulaParser::new();
+    let error = parser.parse_tree("x + * y").unwrap_err();
+    assert_eq!(error.message, "Expected a value, but found operator `*`");
+    assert_eq!(error.span.unwrap().column, 5);
+
+    let error = parser.parse_tree("sin(x").unwrap_err();
+    assert_eq!(error.message, "Expected `,` or `)` in the arguments of `sin`, but found end of input");
+    assert_eq!(error.span.unwrap().column, 6);
+
+    let error = parser.parse_tree("x y").unwrap_err();
+    assert_eq!(error.to_string(), "Expected an operator, but found `y` at line: 1, column: 3");
+
+    assert!(parser.parse_tree("x $ y").unwrap_err().message.contains("`$`"));
+    assert!(parser.parse_tree("sin x").unwrap_err().message.contains("`(`"));
+    assert!(parser.parse_tree("(x + 1").is_err());
+    assert!(parser.parse_tree("x + 1)").is_err());
+    assert_eq!(parser.parse_tree("  ").unwrap_err().message, "Input is empty.");
+}
+
+fn diagnostics(formula: &str) -> Vec<String> {
+    FormulaParser::new().parse_tree_diagnostics(formula).unwrap_err().iter().map(|error| error.to_string()).collect()
+}
+
+#[test]
+fn reports_every_problem_in_one_pass() {
+    assert_eq!(diagnostics("x + * y + sin(z"), vec![
+        "Expected a value, but found operator `*` at line: 1, column: 5",
+        "Expected `,` or `)` in the arguments of `sin`, but found end of input at line: 1, column: 16",
+    ]);
+    assert_eq!(diagnostics("foo(x $ 1) + (y, z) + w"), vec![
+        "Unknown function `foo` at line: 1, column: 1",
+        "Unexpected character `$` at line: 1, column: 7",
+        "Expected `)` to close the parentheses, but found `,` at line: 1, column: 16",
+        "Unknown variable `w` at line: 1, column: 23",
+    ]);
+}
+
+#[test]
+fn recovers_at_operators_and_closing_parentheses() {
+    assert_eq!(diagnostics("(x y) + 1) * 2 + z z"), vec![
+        "Expected an operator, but found `y` at line: 1, column: 4",
+        "Expected an operator, but found `)` at line: 1, column: 10",
+        "Expected an operator, but found `z` at line: 1, column: 20",
+    ]);
+}
+
+#[test]
+fn cascading_errors_are_reported_once() {
+    // The missing `(` and the missing operator both describe the `x` after `sin`
+    assert_eq!(diagnostics("sin x"), vec!["Expected `(` after function `sin`, but found `x` at line: 1, column: 5"]);
+    assert_eq!(diagnostics("2 * ("), vec!["Expected a value, but found end of input at line: 1, column: 6"]);
+    assert_eq!(diagnostics("x + )"), vec!["Expected a value, but found `)` at line: 1, column: 5"]);
+}
+
+#[test]
+fn unknown_names_suggest_close_matches() {
+    assert_eq!(diagnostics("sine(x)"), vec!["Unknown function `sine`, did you mean `sin`? at line: 1, column: 1"]);
+    assert_eq!(diagnostics("x * tiem"), vec!["Unknown variable `tiem`, did you mean `time`? at line: 1, column: 5"]);
+    assert_eq!(diagnostics("perln(x, y, z) + abz(1)"), vec![
+        "Unknown function `perln`, did you mean `perlin`? at line: 1, column: 1",
+        "Unknown function `abz`, did you mean `abs`? at line: 1, column: 18",
+    ]);
+    assert_eq!(diagnostics("cosine(x)"), vec!["Unknown function `cosine` at line: 1, column: 1"]);
+    assert_eq!(diagnostics("sin(x, y) + hash(1)"), vec![
+        "sin() takes 1 argument(s), but was given 2 at line: 1, column: 1",
+        "hash() takes 3 argument(s), but was given 1 at line: 1, column: 13",
+    ]);
+}
+
+#[test]
+fn formula_closure_reports_all_errors() {
+    let formula = FormulaParser::new().parse_formula("sine(x) + tiem");
+    assert!(formula.program.is_none());
+    assert_eq!(
+        (formula.func)(0., Vec3::ZERO).unwrap_err(),
+        "Unknown function `sine`, did you mean `sin`? at line: 1, column: 1\nUnknown variable `tiem`, did you mean `time`? at line: 1, column: 11",
+    );
+}
+
+#[test]
+fn seed_is_a_constant_set_on_the_parser() {
+    let parser = FormulaParser { seed: 7., ..FormulaParser::default() };
+    assert_eq!(parser.parse_tree("seed * 2").unwrap().evaluate(0., Vec3::ZERO), 14.);
+    assert_eq!(FormulaParser::new().parse_tree("seed").unwrap(), Expr::Constant(0.));
+    assert!(FormulaParser::new().parse_tree("sed").unwrap_err().message.contains("did you mean `seed`?"));
+}
+
+#[test]
+fn parameters_are_named_values_set_on_the_parser() {
+    let parser = FormulaParser {
+        parameters: vec![("Speed".to_string(), 3.), ("time".to_string(), 100.), ("height".to_string(), 1.)],
+        columns: vec!["speed".to_string()],
+        ..FormulaParser::default()
+    };
+    let expr = parser.parse_tree("speed * time").unwrap();
+    assert_eq!(expr.evaluate_with_columns(2., Vec3::ZERO, &[50.]), 6.);
+    assert_eq!(expr.to_string(), "Speed * time");
+    assert_eq!(expr.simplify(), expr);
+    assert!(parser.parse_tree("heigt").unwrap_err().message.contains("did you mean `height`?"));
+}
+
+#[test]
+fn reserved_names_are_the_builtin_variables_and_functions() {
+    for name in ["time", "X", "theta", "Pi", "seed", "sin", "ATAN2", "worley"] {
+        assert!(is_reserved_name(name), "{}", name);
+    }
+    for name in ["speed", "sine", "x2", "_"] {
+        assert!(!is_reserved_name(name), "{}", name);
+    }
+    assert!(is_identifier("wind_speed2") && is_identifier("_a"));
+    for name in ["", "2x", "wind speed", "a-b", "π", "x²"] {
+        assert!(!is_identifier(name), "{}", name);
+    }
+}
+
+#[test]
+fn spherical_coordinates_of_the_point() {
+    let parser = FormulaParser::new();
+    let evaluate = |formula: &str, point: Vec3| parser.parse_tree(formula).unwrap().evaluate(0., point);
+    assert_eq!(evaluate("r", Vec3::new(2., 3., 6.)), 7.);
+    assert!((evaluate("theta", Vec3::new(0., 5., 4.)) - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    assert!((evaluate("phi", Vec3::new(3., 0., 4.)) - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    assert_eq!(evaluate("phi", Vec3::new(0., -2., 0.)), std::f32::consts::PI);
+    assert_eq!(evaluate("r + theta + phi", Vec3::ZERO), 0.);
+    assert_eq!(evaluate("atan2(1, 0)", Vec3::ZERO), std::f32::consts::FRAC_PI_2);
+}
+
+#[test]
+fn implicit_multiplication_is_opt_in() {
+    let parser = FormulaParser::new();
+    assert_eq!(parser.parse_tree("2x").unwrap_err().to_string(), "Expected an operator, but found `x` at line: 1, column: 2");
+    assert!(parser.parse_tree("(x+1)(x-1)").is_err());
+}
+
+#[test]
+fn implicit_multiplication_rules() {
+    let parser = FormulaParser { implicit_multiplication: true, ..FormulaParser::default() };
+    let evaluate = |formula: &str| parser.parse_tree(formula).unwrap().evaluate(0.5, Vec3::new(3., 2., 1.));
+    assert_eq!(evaluate("2x"), 6.);
+    assert_eq!(evaluate("2pi"), std::f32::consts::TAU);
+    assert_eq!(evaluate("2π"), std::f32::consts::TAU);
+    assert_eq!(evaluate("4sin(time - time)"), 0.);
+    assert_eq!(evaluate("3abs(y)"), 6.);
+    assert_eq!(evaluate("(x+1)(x-1)"), 8.);
+    assert_eq!(evaluate("abs(y)(x)"), 6.);
+    assert_eq!(evaluate("2√(x + 1)"), 4.);
+    // The product binds like `*`
+    assert_eq!(evaluate("1/2x"), 1.5);
+    assert_eq!(evaluate("2x^2"), 18.);
+    assert_eq!(evaluate("2x²"), 18.);
+    assert_eq!(evaluate("x^2y"), 18.);
+    assert_eq!(evaluate("2x + 1"), 7.);
+    assert_eq!(
+        parser.parse_tree("2x").unwrap(),
+        Expr::Binary(Operator::Multiplication, Box::new(Expr::Constant(2.)), Box::new(Expr::PointX)),
+    );
+}
+
+#[test]
+fn implicit_multiplication_needs_touching_tokens() {
+    let parser = FormulaParser { implicit_multiplication: true, ..FormulaParser::default() };
+    assert_eq!(parser.parse_tree("x y").unwrap_err().to_string(), "Expected an operator, but found `y` at line: 1, column: 3");
+    assert_eq!(parser.parse_tree("2 x").unwrap_err().to_string(), "Expected an operator, but found `x` at line: 1, column: 3");
+    assert_eq!(parser.parse_tree("(x) (y)").unwrap_err().to_string(), "Expected an operator, but found `(` at line: 1, column: 5");
+    assert!(parser.parse_tree("xy").unwrap_err().message.contains("`xy`"));
+    assert!(parser.parse_tree("x(y)").is_err());
+    assert!(parser.parse_tree("(x)y").is_err());
+    assert!(parser.parse_tree("2 3").is_err());
+}
+
+#[test]
+fn deep_nesting_is_an_error_not_a_crash() {
+    let parser = FormulaParser { max_tokens: usize::MAX, ..FormulaParser::default() };
+    let nested = format!("{}x{}", "(".repeat(100_000), ")".repeat(100_000));
+    assert_eq!(
+        parser.parse_tree_diagnostics(&nested).unwrap_err().iter().map(|error| error.to_string()).collect::<Vec<String>>(),
+        vec!["Formula is nested more than 64 levels deep at line: 1, column: 65"],
+    );
+    let unclosed = "sin(".repeat(100_000);
+    assert!(parser.parse_tree(&unclosed).unwrap_err().message.contains("nested"));
+    let chained = vec!["x"; 10_000].join("^");
+    assert!(parser.parse_tree(&chained).unwrap_err().message.contains("nested"));
+
+    let shallow = format!("{}x{}", "(".repeat(63), ")".repeat(63));
+    assert_eq!(parser.parse_tree(&shallow), Ok(Expr::PointX));
+    let deeper = FormulaParser { max_depth: 200, ..parser };
+    assert!(deeper.parse_tree(&format!("{}x{}", "(".repeat(150), ")".repeat(150))).is_ok());
+}
+
+#[test]
+fn token_count_is_limited() {
+    let parser = FormulaParser { max_tokens: 5, ..FormulaParser::default() };
+    assert!(parser.parse_tree("x + y + z").is_ok());
+    let error = parser.parse_tree("x + y + z + time").unwrap_err();
+    assert_eq!(error.to_string(), "Formula has 7 tokens, more than the limit of 5 at line: 1, column: 11");
+    assert!(FormulaParser::new().parse_tree(&vec!["x"; 600].join("+")).unwrap_err().message.contains("limit of 1024"));
+}
+
+#[test]
+fn evaluation_cost_is_limited() {
+    let parser = FormulaParser::new();
+    assert_eq!(parser.parse_tree("x + 1").unwrap().cost(), 3);
+    assert_eq!(parser.parse_tree("sin(x) ^ 2").unwrap().cost(), 18);
+    assert_eq!(parser.parse_tree("fbm(x, y, z, 4)").unwrap().cost(), 164);
+    assert_eq!(parser.parse_tree("fbm(x, y, z, time)").unwrap().cost(), 644);
+
+    let expensive = vec!["worley(x, y, z)"; 40].join(" + ");
+    assert_eq!(
+        parser.parse_tree(&expensive).unwrap_err().to_string(),
+        "Formula costs 12159 to evaluate per point, more than the limit of 10000",
+    );
+    let generous = FormulaParser { max_cost: 20_000, ..FormulaParser::default() };
+    assert!(generous.parse_tree(&expensive).is_ok());
+}
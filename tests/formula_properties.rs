@@ -0,0 +1,175 @@
+use bevy_graph_sim::{
+    formula_bytecode::Program,
+    formula_tree::Expr,
+    noise,
+    parsing_function::{FormulaParser, FunctionType, Operator, MAX_ARITY},
+};
+use glam::Vec3;
+use proptest::{collection::vec, num::f32 as float, prelude::*, sample::select};
+
+fn arb_operator() -> impl Strategy<Value = Operator> {
+    select(vec![
+        Operator::Addition,
+        Operator::Subtraction,
+        Operator::Multiplication,
+        Operator::Division,
+        Operator::Exponentiation,
+    ])
+}
+
+/// Constants the parser can read back exactly, from whole numbers to subnormals
+fn arb_constant() -> impl Strategy<Value = f32> {
+    prop_oneof![
+        (0u32..100).prop_map(|x| x as f32),
+        0f32..10.,
+        float::POSITIVE | float::NORMAL | float::SUBNORMAL | float::ZERO,
+    ]
+}
+
+fn arb_expr() -> impl Strategy<Value = Expr> {
+    let leaf = prop_oneof![
+        Just(Expr::Time),
+        Just(Expr::PointX),
+        Just(Expr::PointY),
+        Just(Expr::PointZ),
+        Just(Expr::PointR),
+        Just(Expr::PointTheta),
+        Just(Expr::PointPhi),
+        arb_constant().prop_map(Expr::Constant),
+    ];
+    leaf.prop_recursive(6, 48, MAX_ARITY as u32, |inner| {
+        prop_oneof![
+            (arb_operator(), inner.clone(), inner.clone())
+                .prop_map(|(oper, left, right)| Expr::Binary(oper, Box::new(left), Box::new(right))),
+            (select(FunctionType::ALL.to_vec()), vec(inner, MAX_ARITY))
+                .prop_map(|(func_type, mut args)| {
+                    args.truncate(func_type.arity());
+                    Expr::Function(func_type, args)
+                }),
+        ]
+    })
+}
+
+fn arb_point() -> impl Strategy<Value = (f32, Vec3)> {
+    (-50f32..50., -50f32..50., -50f32..50., -50f32..50.).prop_map(|(time, x, y, z)| (time, Vec3::new(x, y, z)))
+}
+
+/// Evaluates the tree with plain f32 operations, independently of `Expr::evaluate` and the bytecode
+fn reference_evaluate(expr: &Expr, time: f32, point: Vec3) -> f32 {
+    let eval = |expr: &Expr| reference_evaluate(expr, time, point);
+    match expr {
+        Expr::Time => time,
+        Expr::PointX => point.x,
+        Expr::PointY => point.y,
+        Expr::PointZ => point.z,
+        // Points evaluated on their own read the spherical coordinates of where they are
+        Expr::PointR => (point.x * point.x + point.y * point.y + point.z * point.z).sqrt(),
+        Expr::PointTheta => point.z.atan2(point.x),
+        Expr::PointPhi => (point.x * point.x + point.z * point.z).sqrt().atan2(point.y),
+        Expr::Column(_, _) => f32::NAN,
+        Expr::Parameter(_, x) | Expr::Constant(x) => *x,
+        Expr::Function(func_type, args) => {
+            let args: Vec<f32> = args.iter().map(eval).collect();
+            match func_type {
+                FunctionType::Sin => args[0].sin(),
+                FunctionType::Cos => args[0].cos(),
+                FunctionType::Tan => args[0].tan(),
+                FunctionType::Abs => args[0].abs(),
+                FunctionType::Sqrt => args[0].sqrt(),
+                FunctionType::Atan2 => args[0].atan2(args[1]),
+                FunctionType::Rand => noise::rand(args[0]),
+                FunctionType::Hash => noise::hash(args[0], args[1], args[2]),
+                FunctionType::Perlin => noise::perlin(args[0], args[1], args[2]),
+                FunctionType::Simplex => noise::simplex(args[0], args[1], args[2]),
+                FunctionType::Fbm => noise::fbm(args[0], args[1], args[2], args[3]),
+                FunctionType::Worley => noise::worley(args[0], args[1], args[2]),
+            }
+        },
+        Expr::Binary(oper, left, right) => {
+            let (left, right) = (eval(left), eval(right));
+            match oper {
+                Operator::Addition => left + right,
+                Operator::Subtraction => left - right,
+                Operator::Multiplication => left * right,
+                Operator::Division => left / right,
+                Operator::Exponentiation => left.powf(right),
+            }
+        },
+    }
+}
+
+/// Random trees can pile up noise calls past the default cost limit, which isn't what these tests are about
+fn unlimited_parser() -> FormulaParser {
+    FormulaParser { max_cost: u32::MAX, ..FormulaParser::default() }
+}
+
+fn same_value(a: f32, b: f32) -> bool {
+    a == b || (a.is_nan() && b.is_nan())
+}
+
+proptest! {
+    #[test]
+    fn printed_trees_parse_back_identically(expr in arb_expr()) {
+        let text = expr.to_string();
+        let parsed = unlimited_parser().parse_tree(&text);
+        prop_assert_eq!(parsed, Ok(expr), "printed as {}", text);
+    }
+
+    #[test]
+    fn every_evaluator_matches_the_reference(expr in arb_expr(), points in vec(arb_point(), 1..20)) {
+        let text = expr.to_string();
+        let parser = unlimited_parser();
+        let parsed = parser.parse_tree(&text).unwrap();
+        let program = Program::compile(&parsed).unwrap();
+        let formula = parser.parse_formula(&text);
+        for (time, point) in points.iter() {
+            let expected = reference_evaluate(&expr, *time, *point);
+            let from_tree = parsed.evaluate(*time, *point);
+            let from_program = program.run(*time, *point);
+            let from_closure = (formula.func)(*time, *point).unwrap();
+            prop_assert!(same_value(expected, from_tree), "{}: tree gave {} not {}", text, from_tree, expected);
+            prop_assert!(same_value(expected, from_program), "{}: bytecode gave {} not {}", text, from_program, expected);
+            prop_assert!(same_value(expected, from_closure), "{}: closure gave {} not {}", text, from_closure, expected);
+        }
+
+        // The batch path runs on a shared time, so sweep the points at the first point's time
+        let time = points[0].0;
+        let xs: Vec<f32> = points.iter().map(|(_, point)| point.x).collect();
+        let ys: Vec<f32> = points.iter().map(|(_, point)| point.y).collect();
+        let zs: Vec<f32> = points.iter().map(|(_, point)| point.z).collect();
+        let mut output = vec![0.; points.len()];
+        program.run_batch(time, &xs, &ys, &zs, &mut output);
+        for ((_, point), from_batch) in points.iter().zip(output) {
+            let expected = reference_evaluate(&expr, time, *point);
+            prop_assert!(same_value(expected, from_batch), "{}: batch gave {} not {}", text, from_batch, expected);
+        }
+    }
+
+    #[test]
+    fn simplifying_keeps_the_value(expr in arb_expr(), (time, point) in arb_point()) {
+        let simplified = expr.simplify();
+        let (expected, actual) = (reference_evaluate(&expr, time, point), simplified.evaluate(time, point));
+        prop_assert!(same_value(expected, actual), "{} simplified to {} gives {} not {}", expr, simplified, actual, expected);
+    }
+
+    #[test]
+    fn negative_and_special_constants_print_as_equivalent_arithmetic(x in any::<f32>()) {
+        let text = Expr::Constant(x).to_string();
+        let value = FormulaParser::new().parse_tree(&text).unwrap().evaluate(0., Vec3::ZERO);
+        prop_assert!(same_value(value, x) || (x == 0. && value == 0.), "{} printed as {} reads back as {}", x, text, value);
+    }
+
+    #[test]
+    fn parsing_never_panics_on_formula_like_text(text in "[0-9a-z()+*/^,. ×÷−π√²τ\\-]{0,48}") {
+        for implicit_multiplication in [false, true] {
+            let formula = FormulaParser { implicit_multiplication, ..FormulaParser::default() }.parse_formula(&text);
+            let _ = (formula.func)(1., Vec3::ONE);
+        }
+    }
+
+    #[test]
+    fn parsing_never_panics_on_arbitrary_text(text in any::<String>()) {
+        let formula = FormulaParser::new().parse_formula(&text);
+        let _ = (formula.func)(1., Vec3::ONE);
+    }
+}
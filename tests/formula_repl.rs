@@ -0,0 +1,53 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+fn formula_repl(input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_formula-repl"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn prints_parsed_simplified_and_value() {
+    let (stdout, stderr) = formula_repl(":set x 1 + 2\n:set time x * 2\nx * (1 + 1) + time\n:tree\n:latex\n");
+    assert_eq!(stderr, "");
+    assert_eq!(stdout, "\
+x = 3
+time = 6
+parsed:     x * (1 + 1) + time
+simplified: x * 2 + time
+value:      12
++
+├── *
+│   ├── x
+│   └── +
+│       ├── 1
+│       └── 1
+└── time
+x \\cdot \\left(1 + 1\\right) + t
+");
+}
+
+#[test]
+fn underlines_errors_and_keeps_going() {
+    let (stdout, stderr) = formula_repl(":set x sinn(2)\n:set w 1\nx +\n:quit\nx\n");
+    assert_eq!(stdout, "");
+    // A line continuation would swallow the leading spaces, so the first line starts with the quote
+    assert_eq!(stderr, "  :set x sinn(2)
+         ^^^^
+error: Unknown function `sinn`, did you mean `sin`? at line: 1, column: 1
+error: Can't set `w`, only time, x, y and z
+  x +
+     ^
+error: Expected a value, but found end of input at line: 1, column: 4
+");
+}
@@ -0,0 +1,50 @@
+use bevy_graph_sim::{formula_latex::to_latex, parsing_function::FormulaParser};
+
+fn simplified(input: &str) -> String {
+    FormulaParser::new().parse_tree(input).unwrap().simplify().to_string()
+}
+
+fn latex(input: &str) -> String {
+    to_latex(&FormulaParser::new().parse_tree(input).unwrap())
+}
+
+#[test]
+fn simplify_folds_constants() {
+    assert_eq!(simplified("x + 2 * 3"), "x + 6");
+    assert_eq!(simplified("sin(0) + abs(0 - 2) ^ 2"), "4");
+    assert_eq!(simplified("y * (1 / 0)"), "y * (1 / 0)");
+    assert_eq!(simplified("x + 1 + 2"), "x + 1 + 2");
+}
+
+#[test]
+fn simplify_drops_identity_operations() {
+    assert_eq!(simplified("(x + 0) * 1 - 0"), "x");
+    assert_eq!(simplified("0 + y / 1 ^ z"), "y");
+    assert_eq!(simplified("sin(time) ^ 0 + x ^ 1"), "1 + x");
+    // Multiplying by zero is kept, since it gives NaN rather than 0 for infinite values
+    assert_eq!(simplified("x * 0"), "x * 0");
+}
+
+#[test]
+fn tree_diagram_indents_operands() {
+    let tree = FormulaParser::new().parse_tree("x + perlin(y, 2, time) * 3").unwrap();
+    assert_eq!(tree.tree_diagram(), "\
++
+├── x
+└── *
+    ├── perlin
+    │   ├── y
+    │   ├── 2
+    │   └── time
+    └── 3
+");
+}
+
+#[test]
+fn latex_uses_fractions_superscripts_and_minimal_parentheses() {
+    assert_eq!(latex("(x + 1) / 2 - y ^ (z - 1)"), "\\frac{x + 1}{2} - y^{z - 1}");
+    assert_eq!(latex("(x + 1) * 2 - (y - time)"), "\\left(x + 1\\right) \\cdot 2 - \\left(y - t\\right)");
+    assert_eq!(latex("sin(x) ^ 2 + abs(y) ^ 2 + sqrt(z)"), "\\left(\\sin\\left(x\\right)\\right)^{2} + \\left|y\\right|^{2} + \\sqrt{z}");
+    assert_eq!(latex("fbm(x, y, z, 4)"), "\\operatorname{fbm}\\left(x, y, z, 4\\right)");
+    assert_eq!(latex("r ^ 2 * cos(theta) - phi"), "r^{2} \\cdot \\cos\\left(\\theta\\right) - \\phi");
+}
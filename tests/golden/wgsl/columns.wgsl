@@ -0,0 +1,3 @@
+fn formula_columns(point: vec3<f32>, col_sign: f32, col_loop: f32, col_height: f32) -> f32 {
+    return col_height * 2.0 + col_loop - col_sign;
+}
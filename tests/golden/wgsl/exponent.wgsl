@@ -0,0 +1,3 @@
+fn formula_exponent(point: vec3<f32>) -> f32 {
+    return pow(2.0, pow(point.x, 2.0)) * pow(point.y + 1.0, 2.0);
+}
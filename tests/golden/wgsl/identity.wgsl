@@ -0,0 +1,3 @@
+fn formula_identity(point: vec3<f32>) -> f32 {
+    return point.x;
+}
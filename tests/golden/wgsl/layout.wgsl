@@ -0,0 +1,3 @@
+fn formula_layout(point: vec3<f32>, point_layout: vec3<f32>) -> f32 {
+    return point_layout.x * cos(point_layout.y + uniforms.time) - point_layout.z;
+}
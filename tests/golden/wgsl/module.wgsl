@@ -0,0 +1,18 @@
+struct FormulaUniforms {
+    time: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: FormulaUniforms;
+
+fn formula_x(point: vec3<f32>) -> f32 {
+    return point.x;
+}
+
+fn formula_y(point: vec3<f32>) -> f32 {
+    return sin(point.x - uniforms.time) * 10.0;
+}
+
+fn formula_z(point: vec3<f32>) -> f32 {
+    return point.z;
+}
@@ -0,0 +1,3 @@
+fn formula_nested_functions(point: vec3<f32>) -> f32 {
+    return abs(sin(point.x / uniforms.time * 25.0) * 25.0) + cos(tan(point.z));
+}
@@ -0,0 +1,3 @@
+fn formula_noise(point: vec3<f32>) -> f32 {
+    return noise_fbm(point.x / 50.0, uniforms.time, point.z / 50.0, 4.0) * 20.0 + noise_worley(point.x, point.y, point.z) - noise_hash(point.x, 0.0, point.z) * noise_rand(3.0);
+}
@@ -0,0 +1,175 @@
+struct FormulaUniforms {
+    time: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: FormulaUniforms;
+
+// WGSL port of the formula noise functions in noise.rs, kept line for line in step with it.
+
+fn noise_mix_bits(value: u32) -> u32 {
+    var x: u32 = value;
+    x = x ^ (x >> 16u);
+    x = x * 0x7feb352du;
+    x = x ^ (x >> 15u);
+    x = x * 0x846ca68bu;
+    x = x ^ (x >> 16u);
+    return x;
+}
+
+fn noise_hash_lattice(i: i32, j: i32, k: i32) -> u32 {
+    return noise_mix_bits(bitcast<u32>(i) ^ noise_mix_bits(bitcast<u32>(j) ^ noise_mix_bits(bitcast<u32>(k))));
+}
+
+fn noise_to_unit(hash: u32) -> f32 {
+    return f32(hash >> 8u) * (1.0 / 16777216.0);
+}
+
+fn noise_fade(t: f32) -> f32 {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+fn noise_lerp(t: f32, a: f32, b: f32) -> f32 {
+    return a + t * (b - a);
+}
+
+fn noise_gradient(hash: u32, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15u;
+    let u = select(y, x, h < 8u);
+    let v = select(select(z, x, h == 12u || h == 14u), y, h < 4u);
+    return select(-u, u, (h & 1u) == 0u) + select(-v, v, (h & 2u) == 0u);
+}
+
+fn noise_rand(seed: f32) -> f32 {
+    return noise_to_unit(noise_mix_bits(bitcast<u32>(seed + 0.0)));
+}
+
+fn noise_hash(i: f32, j: f32, k: f32) -> f32 {
+    return noise_to_unit(noise_hash_lattice(i32(floor(i)), i32(floor(j)), i32(floor(k))));
+}
+
+fn noise_perlin_corner(i: i32, j: i32, k: i32, x: f32, y: f32, z: f32) -> f32 {
+    return noise_gradient(noise_hash_lattice(i, j, k), x, y, z);
+}
+
+fn noise_perlin(px: f32, py: f32, pz: f32) -> f32 {
+    let i = i32(floor(px));
+    let j = i32(floor(py));
+    let k = i32(floor(pz));
+    let x = px - floor(px);
+    let y = py - floor(py);
+    let z = pz - floor(pz);
+    let u = noise_fade(x);
+    let v = noise_fade(y);
+    let w = noise_fade(z);
+    let value = noise_lerp(
+        w,
+        noise_lerp(
+            v,
+            noise_lerp(u, noise_perlin_corner(i, j, k, x, y, z), noise_perlin_corner(i + 1, j, k, x - 1.0, y, z)),
+            noise_lerp(u, noise_perlin_corner(i, j + 1, k, x, y - 1.0, z), noise_perlin_corner(i + 1, j + 1, k, x - 1.0, y - 1.0, z)),
+        ),
+        noise_lerp(
+            v,
+            noise_lerp(u, noise_perlin_corner(i, j, k + 1, x, y, z - 1.0), noise_perlin_corner(i + 1, j, k + 1, x - 1.0, y, z - 1.0)),
+            noise_lerp(u, noise_perlin_corner(i, j + 1, k + 1, x, y - 1.0, z - 1.0), noise_perlin_corner(i + 1, j + 1, k + 1, x - 1.0, y - 1.0, z - 1.0)),
+        ),
+    );
+    return clamp(value, -1.0, 1.0);
+}
+
+fn noise_simplex_corner(i: i32, j: i32, k: i32, x: f32, y: f32, z: f32) -> f32 {
+    let t = 0.6 - x * x - y * y - z * z;
+    if (t < 0.0) {
+        return 0.0;
+    }
+    let t2 = t * t;
+    return t2 * t2 * noise_gradient(noise_hash_lattice(i, j, k), x, y, z);
+}
+
+fn noise_simplex(x: f32, y: f32, z: f32) -> f32 {
+    let skew_factor = 1.0 / 3.0;
+    let unskew_factor = 1.0 / 6.0;
+
+    let skew = (x + y + z) * skew_factor;
+    let floor_i = floor(x + skew);
+    let floor_j = floor(y + skew);
+    let floor_k = floor(z + skew);
+    let unskew = (floor_i + floor_j + floor_k) * unskew_factor;
+    let x0 = x - (floor_i - unskew);
+    let y0 = y - (floor_j - unskew);
+    let z0 = z - (floor_k - unskew);
+    let i = i32(floor_i);
+    let j = i32(floor_j);
+    let k = i32(floor_k);
+
+    var first = vec3<i32>(0, 1, 0);
+    var second = vec3<i32>(1, 1, 0);
+    if (x0 >= y0) {
+        if (y0 >= z0) {
+            first = vec3<i32>(1, 0, 0);
+            second = vec3<i32>(1, 1, 0);
+        } else if (x0 >= z0) {
+            first = vec3<i32>(1, 0, 0);
+            second = vec3<i32>(1, 0, 1);
+        } else {
+            first = vec3<i32>(0, 0, 1);
+            second = vec3<i32>(1, 0, 1);
+        }
+    } else if (y0 < z0) {
+        first = vec3<i32>(0, 0, 1);
+        second = vec3<i32>(0, 1, 1);
+    } else if (x0 < z0) {
+        first = vec3<i32>(0, 1, 0);
+        second = vec3<i32>(0, 1, 1);
+    }
+
+    let value = noise_simplex_corner(i, j, k, x0, y0, z0)
+        + noise_simplex_corner(i + first.x, j + first.y, k + first.z, x0 - f32(first.x) + unskew_factor, y0 - f32(first.y) + unskew_factor, z0 - f32(first.z) + unskew_factor)
+        + noise_simplex_corner(i + second.x, j + second.y, k + second.z, x0 - f32(second.x) + 2.0 * unskew_factor, y0 - f32(second.y) + 2.0 * unskew_factor, z0 - f32(second.z) + 2.0 * unskew_factor)
+        + noise_simplex_corner(i + 1, j + 1, k + 1, x0 - 1.0 + 3.0 * unskew_factor, y0 - 1.0 + 3.0 * unskew_factor, z0 - 1.0 + 3.0 * unskew_factor);
+    return clamp(32.0 * value, -1.0, 1.0);
+}
+
+fn noise_fbm(x: f32, y: f32, z: f32, octaves: f32) -> f32 {
+    let octave_count = u32(clamp(floor(octaves), 1.0, 16.0));
+    var sum: f32 = 0.0;
+    var total_amplitude: f32 = 0.0;
+    var amplitude: f32 = 1.0;
+    var frequency: f32 = 1.0;
+    for (var octave: u32 = 0u; octave < octave_count; octave = octave + 1u) {
+        sum = sum + amplitude * noise_perlin(x * frequency, y * frequency, z * frequency);
+        total_amplitude = total_amplitude + amplitude;
+        amplitude = amplitude * 0.5;
+        frequency = frequency * 2.0;
+    }
+    return sum / total_amplitude;
+}
+
+fn noise_worley(px: f32, py: f32, pz: f32) -> f32 {
+    let i = i32(floor(px));
+    let j = i32(floor(py));
+    let k = i32(floor(pz));
+    let x = px - floor(px);
+    let y = py - floor(py);
+    let z = pz - floor(pz);
+    var nearest: f32 = 3.0;
+    for (var dk: i32 = -1; dk <= 1; dk = dk + 1) {
+        for (var dj: i32 = -1; dj <= 1; dj = dj + 1) {
+            for (var di: i32 = -1; di <= 1; di = di + 1) {
+                let cell_hash = noise_hash_lattice(i + di, j + dj, k + dk);
+                let offset = vec3<f32>(
+                    f32(di) + noise_to_unit(cell_hash) - x,
+                    f32(dj) + noise_to_unit(noise_mix_bits(cell_hash)) - y,
+                    f32(dk) + noise_to_unit(noise_mix_bits(noise_mix_bits(cell_hash))) - z,
+                );
+                nearest = min(nearest, offset.x * offset.x + offset.y * offset.y + offset.z * offset.z);
+            }
+        }
+    }
+    return min(sqrt(nearest), 1.732051);
+}
+
+fn formula_y(point: vec3<f32>) -> f32 {
+    return noise_perlin(point.x / 30.0, uniforms.time, point.z / 30.0) * 25.0 + noise_simplex(point.x, point.y, point.z);
+}
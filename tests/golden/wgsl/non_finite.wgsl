@@ -0,0 +1,3 @@
+fn formula_non_finite(point: vec3<f32>) -> f32 {
+    return point.x + bitcast<f32>(0x7f800000u) * point.y - bitcast<f32>(0x7fc00000u) + bitcast<f32>(0xff800000u);
+}
@@ -0,0 +1,12 @@
+struct FormulaUniforms {
+    time: f32;
+    param_amplitude: f32;
+    param_speed: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: FormulaUniforms;
+
+fn formula_y(point: vec3<f32>) -> f32 {
+    return uniforms.param_amplitude * sin(point.x - uniforms.time * uniforms.param_speed);
+}
@@ -0,0 +1,3 @@
+fn formula_precedence(point: vec3<f32>) -> f32 {
+    return 1.0 - (2.0 - point.x) / (point.y * 3.0) + point.z;
+}
@@ -0,0 +1,3 @@
+fn formula_ripple(point: vec3<f32>) -> f32 {
+    return sin(point.x - uniforms.time) * 10.0;
+}
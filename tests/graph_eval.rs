@@ -0,0 +1,69 @@
+use std::process::Command;
+
+fn graph_eval(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_graph-eval")).args(args).output().unwrap();
+    (output.status.success(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn writes_csv_for_every_step() {
+    let (success, stdout, _) = graph_eval(&["--grid", "2,1,1", "-y", "y + time", "--start", "0", "--end", "1", "--step", "0.5", "--threads", "2"]);
+    assert!(success);
+    assert_eq!(stdout, "\
+time,index,x,y,z
+0,0,-12,-6,-6
+0,1,0,-6,-6
+0.5,0,-12,-5.5,-6
+0.5,1,0,-5.5,-6
+1,0,-12,-4.5,-6
+1,1,0,-4.5,-6
+");
+}
+
+#[test]
+fn writes_json_lines() {
+    let (success, stdout, _) = graph_eval(&["--grid", "1,1,2", "--spacing", "2", "-x", "x / 0", "-z", "z * 2", "--format", "json"]);
+    assert!(success);
+    assert_eq!(stdout, "\
+{\"time\":0,\"index\":0,\"x\":null,\"y\":-1,\"z\":-4}
+{\"time\":0,\"index\":1,\"x\":null,\"y\":-1,\"z\":0}
+");
+}
+
+#[test]
+fn matches_formula_evaluation_on_a_full_grid() {
+    let (success, stdout, _) = graph_eval(&["-y", "sin(x - time) * 10", "--end", "0.05"]);
+    assert!(success);
+    let rows: Vec<&str> = stdout.lines().skip(1).collect();
+    assert_eq!(rows.len(), 25 * 25 * 4);
+    let time = 1. / 60. * 3.;
+    assert_eq!(rows[25 * 25 * 3 + 1], format!("{},1,-150,{},-138", time, (-150f32 - time).sin() * 10.));
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let (success, _, stderr) = graph_eval(&["--grid", "2,2"]);
+    assert!(!success);
+    assert!(stderr.contains("expected three counts"));
+    let (success, _, _) = graph_eval(&["--step", "0"]);
+    assert!(!success);
+    let (success, stdout, stderr) = graph_eval(&["--start", "2", "--end", "1"]);
+    assert!(!success);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("--start no later than --end"));
+    let (success, _, stderr) = graph_eval(&["--threads", "0"]);
+    assert!(!success);
+    assert!(stderr.contains("--threads"));
+}
+
+#[test]
+fn reports_every_formula_error() {
+    let (success, stdout, stderr) = graph_eval(&["-x", "sine(x)", "-z", "2z", "--implicit-multiplication"]);
+    assert!(!success);
+    assert!(stdout.is_empty());
+    assert_eq!(stderr, "X formula `sine(x)`: Unknown function `sine`, did you mean `sin`? at line: 1, column: 1\n");
+
+    let (success, _, stderr) = graph_eval(&["-y", "y +", "-z", "2z"]);
+    assert!(!success);
+    assert_eq!(stderr.lines().count(), 2);
+}
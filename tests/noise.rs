@@ -0,0 +1,62 @@
+use bevy_graph_sim::{noise, parsing_function::FormulaParser};
+use glam::Vec3;
+
+/// Exact bit patterns, so a change that alters results on any platform is caught
+#[test]
+fn noise_values_are_bit_exact() {
+    assert_eq!(noise::rand(42.).to_bits(), 0x3e91802c);
+    assert_eq!(noise::hash(1., -2., 3.).to_bits(), 0x3f105fee);
+    assert_eq!(noise::perlin(0.3, 1.7, -2.2).to_bits(), 0x3e5e2b4e);
+    assert_eq!(noise::simplex(0.3, 1.7, -2.2).to_bits(), 0x3e09a28f);
+    assert_eq!(noise::fbm(0.3, 1.7, -2.2, 5.).to_bits(), 0x3e481884);
+    assert_eq!(noise::worley(0.3, 1.7, -2.2).to_bits(), 0x3edcf59d);
+}
+
+#[test]
+fn hash_is_constant_within_a_lattice_cell() {
+    assert_eq!(noise::hash(1.1, 2.9, -0.5), noise::hash(1.9, 2.0, -0.01));
+    assert_ne!(noise::hash(1., 2., 3.), noise::hash(1., 2., 4.));
+    assert_eq!(noise::rand(0.), noise::rand(-0.));
+}
+
+#[test]
+fn noise_stays_in_range() {
+    for i in 0..20_000 {
+        let (x, y, z) = (i as f32 * 0.137, i as f32 * -0.071 + 30., (i % 97) as f32 * 0.31);
+        let rand = noise::rand(x);
+        let hash = noise::hash(x, y, z);
+        assert!((0. ..1.).contains(&rand) && (0. ..1.).contains(&hash));
+        for value in [noise::perlin(x, y, z), noise::simplex(x, y, z), noise::fbm(x, y, z, 6.)] {
+            assert!((-1. ..=1.).contains(&value), "{} is out of range at ({}, {}, {})", value, x, y, z);
+        }
+        let worley = noise::worley(x, y, z);
+        assert!((0. ..=noise::WORLEY_MAX).contains(&worley));
+    }
+}
+
+#[test]
+fn perlin_is_zero_on_lattice_points() {
+    assert_eq!(noise::perlin(3., -7., 12.), 0.);
+    // A single octave of fbm is plain perlin noise
+    assert_eq!(noise::fbm(0.3, 1.7, -2.2, 1.), noise::perlin(0.3, 1.7, -2.2));
+    assert_eq!(noise::fbm(0.3, 1.7, -2.2, 0.), noise::perlin(0.3, 1.7, -2.2));
+}
+
+#[test]
+fn noise_functions_are_callable_from_formulas() {
+    let parser = FormulaParser::new();
+    let point = Vec3::new(0.3, 1.7, -2.2);
+    let program = parser.compile("perlin(x, y, z) + fbm(x, y, z, 5) * worley(x, y, z)").unwrap();
+    let expected = noise::perlin(0.3, 1.7, -2.2) + noise::fbm(0.3, 1.7, -2.2, 5.) * noise::worley(0.3, 1.7, -2.2);
+    assert_eq!(program.run(0., point), expected);
+    assert_eq!(parser.parse_tree("rand(time)").unwrap().evaluate(42., point), noise::rand(42.));
+    assert_eq!(parser.compile("simplex(x, y, z)").unwrap().run(0., point), noise::simplex(0.3, 1.7, -2.2));
+}
+
+#[test]
+fn wrong_argument_counts_are_rejected() {
+    let parser = FormulaParser::new();
+    assert!(parser.compile("perlin(x, y)").is_err());
+    assert!(parser.compile("rand(1, 2)").is_err());
+    assert!(parser.compile("sin(x, y)").is_err());
+}
@@ -0,0 +1,285 @@
+use bevy_graph_sim::parsing::{
+    alt, and_then, delimited, expect_char, expect_string, many, many1, map, opt, pair, parse_all, satisfy, sep_by, whitespace,
+    ParseError, ParseInput, Parser,
+};
+
+fn input(text: &str) -> ParseInput {
+    ParseInput::new(text.to_owned())
+}
+
+fn digit() -> impl Parser<char, ParseError> {
+    satisfy(|c| c.is_ascii_digit(), "digit")
+}
+
+fn number() -> impl Parser<u32, ParseError> {
+    map(many1(digit()), |digits| digits.into_iter().collect::<String>().parse().unwrap())
+}
+
+/// A caller-defined error, to check the combinators don't assume `ParseError`
+#[derive(Debug, PartialEq)]
+enum ListError {
+    Syntax(String),
+    TooLarge(u32),
+}
+
+impl From<ParseError> for ListError {
+    fn from(error: ParseError) -> Self {
+        ListError::Syntax(error.to_string())
+    }
+}
+
+#[test]
+fn tracks_lines_and_columns() {
+    let input = input("ab\ncd");
+    let locations: Vec<(char, u32, u32)> = input.chars.iter().map(|c| (c.char, c.line, c.column)).collect();
+    assert_eq!(locations, vec![('a', 1, 1), ('b', 1, 2), ('\n', 1, 3), ('c', 2, 1), ('d', 2, 2)]);
+}
+
+#[test]
+fn handles_every_line_ending() {
+    let input = input("a\r\nb\rc\nd");
+    let lines: Vec<(char, u32)> = input.chars.iter().filter(|c| c.char.is_alphabetic()).map(|c| (c.char, c.line)).collect();
+    assert_eq!(lines, vec![('a', 1), ('b', 2), ('c', 3), ('d', 4)]);
+    assert_eq!(input.chars.iter().find(|c| c.char == 'b').map(|c| c.column), Some(1));
+}
+
+#[test]
+fn tracks_byte_offsets() {
+    let mut input = input("π²x");
+    let offsets: Vec<usize> = input.chars.iter().map(|c| c.byte_offset).collect();
+    assert_eq!(offsets, vec![0, 2, 4]);
+    input.skip_x_chars(2);
+    assert_eq!(input.byte_position(), 4);
+    assert_eq!(input.get_remaining_text(), "x");
+    input.skip_next_char();
+    assert_eq!(input.byte_position(), 5);
+}
+
+#[test]
+fn matches_non_ascii_words() {
+    let mut input = input("π×2");
+    assert!(input.match_word("π×"));
+    assert!(!input.match_word("π×2 "));
+    assert!(input.skip_string("π").is_ok());
+    assert_eq!(input.pop_char('×').map(|c| c.column), Ok(2));
+    assert!(input.skip_word("2").is_ok());
+    assert!(input.finished());
+}
+
+#[test]
+fn skip_any_of_char_only_skips_that_char() {
+    let mut input = input("   x  ");
+    input.skip_any_of_char(' ');
+    assert_eq!(input.get_next_plain_char(), Some('x'));
+    input.skip_any_of_char('y');
+    assert_eq!(input.get_next_plain_char(), Some('x'));
+}
+
+#[test]
+fn skip_any_of_chars_skips_mixed_chars() {
+    let mut input = input(" \t \tend");
+    input.skip_any_of_chars(&[' ', '\t']);
+    assert_eq!(input.get_remaining_text(), "end");
+}
+
+#[test]
+fn skip_next_char_stops_at_end() {
+    let mut input = input("ab");
+    input.skip_next_char();
+    assert_eq!(input.position, 1);
+    input.skip_next_char();
+    assert_eq!(input.position, 2);
+    assert!(input.finished());
+    input.skip_next_char();
+    assert_eq!(input.position, 2);
+}
+
+#[test]
+fn skip_x_chars_reaches_last_char() {
+    let mut input = input("abc");
+    input.skip_x_chars(2);
+    assert_eq!(input.get_next_plain_char(), Some('c'));
+    input.skip_x_chars(1);
+    assert!(input.finished());
+    input.skip_x_chars(usize::MAX);
+    assert_eq!(input.position, 3);
+}
+
+#[test]
+fn save_points_restore_position() {
+    let mut input = input("abc");
+    let save_point = input.create_save_point();
+    input.skip_x_chars(2);
+    input.load_save_point(save_point);
+    assert_eq!(input.get_remaining_text(), "abc");
+}
+
+#[test]
+fn pop_helpers_only_consume_on_success() {
+    let mut input = input("a1");
+    assert!(input.pop_next_char_numerical().is_err());
+    assert_eq!(input.position, 0);
+    assert_eq!(input.pop_next_char_alphabetical().map(|c| c.char), Ok('a'));
+    assert_eq!(input.pop_next_char_numerical().map(|c| c.char), Ok('1'));
+    assert!(input.pop_next_char().is_none());
+    assert!(input.pop_next_char_result().is_err());
+}
+
+#[test]
+fn alphabetical_or_in_group() {
+    let mut input = input("_a-");
+    assert!(input.pop_next_char_alphabetical_or_in_group(&['_']).is_ok());
+    assert!(input.pop_next_char_alphabetical_or_in_group(&['_']).is_ok());
+    assert!(input.pop_next_char_alphabetical_or_in_group(&['_']).is_err());
+}
+
+#[test]
+fn word_matching() {
+    let mut input = input("Time + x");
+    assert!(!input.match_word("time"));
+    assert!(input.match_word("Time"));
+    assert!(input.match_word_ci("TIME"));
+    assert!(!input.match_word("Time + x and more"));
+    assert!(input.skip_string("time").is_err());
+    assert!(input.skip_word("time").is_ok());
+    assert_eq!(input.get_remaining_text(), " + x");
+}
+
+#[test]
+fn next_x_chars_without_moving() {
+    let input = input("abc");
+    let chars: Vec<char> = input.get_next_x_chars(2).unwrap().iter().map(|c| c.char).collect();
+    assert_eq!(chars, vec!['a', 'b']);
+    assert!(input.get_next_x_chars(4).is_none());
+    assert_eq!(input.position, 0);
+}
+
+#[test]
+fn pop_until_chars_stops_before_stop_char() {
+    let mut input = input("key=value;rest");
+    assert_eq!(input.pop_until_char('='), "key");
+    input.skip_char('=').unwrap();
+    assert_eq!(input.pop_until_chars(&[';', ',']), "value");
+    assert_eq!(input.get_next_plain_char(), Some(';'));
+    assert_eq!(input.pop_until_char('!'), ";rest");
+}
+
+#[test]
+fn error_reports_location_and_found_char() {
+    let mut input = input("a\nbc");
+    input.skip_x_chars(3);
+    let error = input.pop_char('x').unwrap_err();
+    assert_eq!(error.to_string(), "Expected 'x' at line: 2, column: 2, but found 'c'");
+    input.skip_next_char();
+    let error = input.pop_char('x').unwrap_err();
+    assert_eq!(error.to_string(), "Expected 'x', but found end of parser input");
+    assert_eq!(error.found, None);
+}
+
+#[test]
+fn map_transforms_output() {
+    assert_eq!(parse_all(number(), "1234"), Ok(1234));
+}
+
+#[test]
+fn and_then_can_fail_with_custom_error() {
+    let small = and_then(
+        map(many1(satisfy::<ListError>(|c| c.is_ascii_digit(), "digit")), |digits| digits.into_iter().collect::<String>().parse::<u32>().unwrap()),
+        |value| if value < 100 { Ok(value) } else { Err(ListError::TooLarge(value)) },
+    );
+    assert_eq!(parse_all(&small, "42"), Ok(42));
+    assert_eq!(parse_all(&small, "420"), Err(ListError::TooLarge(420)));
+    assert!(matches!(parse_all(&small, "x"), Err(ListError::Syntax(_))));
+}
+
+#[test]
+fn alt_backtracks_to_second_choice() {
+    let keyword = alt(expect_string::<ParseError>("sin"), expect_string("sqrt"));
+    let mut input = input("sqrt");
+    assert_eq!(keyword(&mut input), Ok("sqrt".to_string()));
+    assert!(input.finished());
+}
+
+#[test]
+fn alt_prefers_error_that_got_furthest() {
+    let long = map(pair(expect_char::<ParseError>('a'), expect_char('b')), |_| ());
+    let short = map(expect_char('c'), |_| ());
+    let choice = alt(long, short);
+    let mut input = input("ax");
+    let error = choice(&mut input).unwrap_err();
+    assert_eq!(error.expected, "'b'");
+    assert_eq!(error.found.map(|found| found.char), Some('x'));
+}
+
+#[test]
+fn alt_consumes_nothing_when_both_fail() {
+    let long = map(pair(expect_char::<ParseError>('a'), expect_char('b')), |_| ());
+    let choice = alt(long, map(expect_char('c'), |_| ()));
+    let mut input = input("ax");
+    assert!(choice(&mut input).is_err());
+    assert_eq!(input.position, 0);
+    assert_eq!(input.get_remaining_text(), "ax");
+}
+
+#[test]
+fn many_stops_without_consuming_failure() {
+    let digits = many(digit());
+    let mut input = input("12a");
+    assert_eq!(digits(&mut input), Ok(vec!['1', '2']));
+    assert_eq!(input.get_remaining_text(), "a");
+    assert_eq!(digits(&mut input), Ok(vec![]));
+}
+
+#[test]
+fn many_terminates_on_parser_that_consumes_nothing() {
+    let spaces = many(whitespace::<ParseError>());
+    assert_eq!(spaces(&mut input("abc")).map(|found| found.len()), Ok(1));
+}
+
+#[test]
+fn many1_requires_one_match() {
+    assert!(parse_all(many1(digit()), "").is_err());
+    assert_eq!(parse_all(many1(digit()), "7"), Ok(vec!['7']));
+}
+
+#[test]
+fn sep_by_leaves_trailing_separator() {
+    let list = sep_by(number(), expect_char(','));
+    let mut input = input("1,22,333,");
+    assert_eq!(list(&mut input), Ok(vec![1, 22, 333]));
+    assert_eq!(input.get_remaining_text(), ",");
+    assert_eq!(list(&mut ParseInput::new(String::new())), Ok(vec![]));
+}
+
+#[test]
+fn delimited_keeps_inner_output() {
+    let spaced_comma = delimited(whitespace(), expect_char(','), whitespace());
+    let list = delimited(expect_char('['), sep_by(number(), spaced_comma), expect_char(']'));
+    assert_eq!(parse_all(&list, "[1 , 2,3]"), Ok(vec![1, 2, 3]));
+    assert_eq!(parse_all(&list, "[]"), Ok(vec![]));
+    assert_eq!(
+        parse_all(&list, "[1, 2").unwrap_err().to_string(),
+        "Expected ']', but found end of parser input",
+    );
+}
+
+#[test]
+fn opt_gives_none_without_consuming() {
+    let sign = opt(expect_char::<ParseError>('-'));
+    let mut input = input("-5");
+    assert_eq!(sign(&mut input), Ok(Some('-')));
+    assert_eq!(sign(&mut input), Ok(None));
+    assert_eq!(input.get_remaining_text(), "5");
+}
+
+#[test]
+fn parse_all_rejects_leftover_input() {
+    let error = parse_all(number(), "12x").unwrap_err();
+    assert_eq!(error.to_string(), "Expected end of input at line: 1, column: 3, but found 'x'");
+}
+
+#[test]
+fn parse_error_converts_into_string() {
+    let result: Result<char, String> = parse_all(expect_char('a'), "b");
+    assert_eq!(result, Err("Expected 'a' at line: 1, column: 1, but found 'b'".to_string()));
+}
@@ -0,0 +1,212 @@
+use bevy_graph_sim::{
+    parsing_function::FormulaParser,
+    point_evaluation::{grid_coordinate, AxisFormulas, PointColumns},
+    point_files::{load_points, parse_delimited, parse_heightmap, parse_ply, HEIGHTMAP_HEIGHT},
+};
+use glam::Vec3;
+
+#[test]
+fn csv_headers_pick_the_axes_and_name_the_other_columns() {
+    let set = parse_delimited("id,z,y,x,\"Wind Speed\"\n1,3,2,1,4.5\n\n# calm\n2,6,5,4,0\n", ',').unwrap();
+    assert_eq!(set.points, vec![Vec3::new(1., 2., 3.), Vec3::new(4., 5., 6.)]);
+    assert_eq!(set.column_names, vec!["id", "wind_speed"]);
+    assert_eq!(set.data, vec![vec![1., 4.5], vec![2., 0.]]);
+}
+
+#[test]
+fn tables_without_a_header_start_with_the_position() {
+    let set = parse_delimited("1\t2\t3\t7\n-4\t5.5\t6\t8\n", '\t').unwrap();
+    assert_eq!(set.points, vec![Vec3::new(1., 2., 3.), Vec3::new(-4., 5.5, 6.)]);
+    assert_eq!(set.column_names, vec!["column4"]);
+    assert_eq!(set.data, vec![vec![7.], vec![8.]]);
+}
+
+#[test]
+fn bad_tables_say_where_they_went_wrong() {
+    let error = |text: &str| parse_delimited(text, ',').unwrap_err().to_string();
+    assert_eq!(error(""), "Invalid point file: the table is empty");
+    assert_eq!(error("x,y\n1,2\n"), "Invalid point file: expected at least three columns for x, y and z, but found 2");
+    assert_eq!(error("x,y,z\n1,2,3\n4,5\n"), "Invalid point file: line 3 has 2 values but the table has 3 columns");
+    assert_eq!(error("1,2,3\n4,five,6\n"), "Invalid point file: line 2: `five` is not a number");
+}
+
+#[test]
+fn quoted_values_can_hold_delimiters_and_quotes() {
+    let set = parse_delimited("\"x\",\"y\",\"z\",\"Wind, \"\"gust\"\" speed\"\n\"1\", 2 ,3, \"4.5\" \n", ',').unwrap();
+    assert_eq!(set.points, vec![Vec3::new(1., 2., 3.)]);
+    assert_eq!(set.column_names, vec!["wind___gust__speed"]);
+    assert_eq!(set.data, vec![vec![4.5]]);
+
+    let error = |text: &str| parse_delimited(text, ',').unwrap_err().to_string();
+    // A decimal comma stays in its value rather than splitting it in two
+    assert_eq!(error("x,y,z\n1,\"2,5\",3\n"), "Invalid point file: line 2: `2,5` is not a number");
+    assert_eq!(error("x,y,z\n1,\"2,3\n"), "Invalid point file: line 2: a quoted value is never closed");
+    assert_eq!(error("\"1\"2,3,4\n"), "Invalid point file: line 1: `2` follows a closing quote, values are quoted whole");
+}
+
+#[test]
+fn headers_need_the_axes() {
+    let error = |text: &str| parse_delimited(text, ',').unwrap_err().to_string();
+    assert_eq!(error("a,b,c\n1,2,3\n"), "Invalid point file: the header has no `x` column, which the position needs");
+    assert_eq!(error("X,Y,depth\n1,2,3\n"), "Invalid point file: the header has no `z` column, which the position needs");
+}
+
+#[test]
+fn columns_named_after_builtins_are_refused() {
+    let error = |text: &str| parse_delimited(text, ',').unwrap_err().to_string();
+    for (heading, name) in [("time", "time"), ("Seed", "seed"), ("R", "r"), ("theta", "theta"), ("SIN", "sin"), ("Pi", "pi")] {
+        assert_eq!(
+            error(&format!("x,y,z,{}\n1,2,3,4\n", heading)),
+            format!("Invalid point file: column 4 is called `{}`, which formulas already read as a built-in variable or function", name),
+        );
+    }
+    let ply = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nproperty uchar r\nend_header\n1 2 3 4\n";
+    assert_eq!(
+        parse_ply(ply.as_bytes()).unwrap_err().to_string(),
+        "Invalid point file: column 4 is called `r`, which formulas already read as a built-in variable or function",
+    );
+}
+
+#[test]
+fn columns_with_the_same_name_are_refused() {
+    let error = |text: &str| parse_delimited(text, ',').unwrap_err().to_string();
+    assert_eq!(error("x,y,z,Height,height\n1,2,3,4,5\n"), "Invalid point file: columns 4 and 5 are both called `height`");
+    assert_eq!(error("x,y,z,Wind Speed,wind_speed\n1,2,3,4,5\n"), "Invalid point file: columns 4 and 5 are both called `wind_speed`");
+    assert_eq!(error("x,y,z,X\n1,2,3,4\n"), "Invalid point file: columns 1 and 4 are both called `x`");
+}
+
+#[test]
+fn formulas_read_the_extra_columns_by_name() {
+    let set = parse_delimited("x,y,z,height\n0,0,0,2\n1,0,0,5\n", ',').unwrap();
+    let parser = FormulaParser { columns: set.column_names.clone(), ..FormulaParser::default() };
+    let formulas = AxisFormulas {
+        x_func: parser.parse_formula("x"),
+        y_func: parser.parse_formula("HEIGHT * 10 + x"),
+        z_func: parser.parse_formula("z"),
+    };
+    let mut columns = PointColumns::default();
+    columns.clear_with_data_columns(set.column_names.len());
+    for (point, data) in set.points.iter().zip(&set.data) {
+        columns.push_with_data(*point, data);
+    }
+    assert!(columns.evaluate_serial(&formulas, 0.).is_empty());
+    assert_eq!(columns.ys, vec![20., 51.]);
+
+    // Built-in variables win over columns of the same name
+    let parser = FormulaParser { columns: vec!["time".to_string()], ..FormulaParser::default() };
+    assert_eq!(parser.parse_tree("time").unwrap().evaluate_with_columns(3., Vec3::ZERO, &[7.]), 3.);
+}
+
+const ASCII_PLY: &str = "ply
+format ascii 1.0
+comment made by hand
+element vertex 2
+property float x
+property float y
+property float z
+property uchar red
+element face 1
+property list uchar int vertex_indices
+end_header
+1 2 3 255
+-4 5.5 6 0
+3 0 1 1
+";
+
+#[test]
+fn ascii_ply_vertices_become_points() {
+    let set = parse_ply(ASCII_PLY.as_bytes()).unwrap();
+    assert_eq!(set.points, vec![Vec3::new(1., 2., 3.), Vec3::new(-4., 5.5, 6.)]);
+    assert_eq!(set.column_names, vec!["red"]);
+    assert_eq!(set.data, vec![vec![255.], vec![0.]]);
+}
+
+#[test]
+fn binary_ply_reads_either_byte_order() {
+    for (format, little_endian) in [("binary_little_endian", true), ("binary_big_endian", false)] {
+        let mut bytes = format!(
+            "ply\nformat {} 1.0\nelement vertex 2\nproperty double x\nproperty float y\nproperty short z\nproperty int intensity\nend_header\n",
+            format,
+        ).into_bytes();
+        for (x, y, z, intensity) in [(1.5f64, -2f32, 3i16, 40i32), (-0.25, 8., -300, -7)] {
+            if little_endian {
+                bytes.extend(x.to_le_bytes().iter().chain(&y.to_le_bytes()).chain(&z.to_le_bytes()).chain(&intensity.to_le_bytes()));
+            } else {
+                bytes.extend(x.to_be_bytes().iter().chain(&y.to_be_bytes()).chain(&z.to_be_bytes()).chain(&intensity.to_be_bytes()));
+            }
+        }
+        let set = parse_ply(&bytes).unwrap();
+        assert_eq!(set.points, vec![Vec3::new(1.5, -2., 3.), Vec3::new(-0.25, 8., -300.)], "{}", format);
+        assert_eq!(set.column_names, vec!["intensity"]);
+        assert_eq!(set.data, vec![vec![40.], vec![-7.]]);
+    }
+}
+
+#[test]
+fn bad_ply_files_are_explained() {
+    let error = |text: &str| parse_ply(text.as_bytes()).unwrap_err().to_string();
+    assert_eq!(error("solid cube\n"), "Invalid point file: the PLY header has no `end_header`");
+    assert_eq!(error("obj\nend_header\n"), "Invalid point file: not a PLY file, it doesn't start with `ply`");
+    assert_eq!(
+        error("ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nend_header\n1 2\n"),
+        "Invalid point file: the vertices have no `z` property",
+    );
+    assert_eq!(
+        error("ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n1 2 3\n"),
+        "Invalid point file: the file ends after 1 of 2 vertices",
+    );
+    assert_eq!(
+        error("ply\nformat binary_little_endian 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n\0\0"),
+        "Invalid point file: the file ends after 0 of 1 vertices",
+    );
+}
+
+fn encode_png(width: u32, height: u32, color_type: png::ColorType, pixels: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+    bytes
+}
+
+#[test]
+fn heightmaps_raise_a_grid_by_brightness() {
+    let bytes = encode_png(3, 2, png::ColorType::Grayscale, &[0, 255, 51, 102, 0, 255]);
+    let set = parse_heightmap(&bytes, 10).unwrap();
+    assert_eq!(set.points.len(), 6);
+    assert_eq!(set.points[1], Vec3::new(grid_coordinate(1, 3, 10), HEIGHTMAP_HEIGHT * 10., grid_coordinate(0, 2, 10)));
+    assert_eq!(set.points[3], Vec3::new(grid_coordinate(0, 3, 10), 0.4 * HEIGHTMAP_HEIGHT * 10., grid_coordinate(1, 2, 10)));
+    assert_eq!(set.column_names, vec!["brightness"]);
+    assert_eq!(set.data[2], vec![0.2]);
+
+    // Colour images go by luminance
+    let bytes = encode_png(2, 1, png::ColorType::Rgb, &[0, 255, 0, 255, 255, 255]);
+    let set = parse_heightmap(&bytes, 1).unwrap();
+    assert!((set.data[0][0] - 0.7152).abs() < 1e-6);
+    assert_eq!(set.data[1], vec![1.]);
+
+    assert!(parse_heightmap(b"not a png", 1).unwrap_err().to_string().starts_with("Invalid point file: "));
+}
+
+#[test]
+fn files_are_read_by_extension() {
+    let directory = std::env::temp_dir().join(format!("bevy_graph_sim_points_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("points.CSV"), "1,2,3\n").unwrap();
+    std::fs::write(directory.join("points.tsv"), "1\t2\t3\n").unwrap();
+    std::fs::write(directory.join("points.ply"), ASCII_PLY).unwrap();
+    std::fs::write(directory.join("points.png"), encode_png(1, 1, png::ColorType::Grayscale, &[255])).unwrap();
+    std::fs::write(directory.join("points.obj"), "v 1 2 3\n").unwrap();
+
+    assert_eq!(load_points(&directory.join("points.CSV"), 10).unwrap().points, vec![Vec3::new(1., 2., 3.)]);
+    assert_eq!(load_points(&directory.join("points.tsv"), 10).unwrap().points, vec![Vec3::new(1., 2., 3.)]);
+    assert_eq!(load_points(&directory.join("points.ply"), 10).unwrap().points.len(), 2);
+    let corner = grid_coordinate(0, 1, 10);
+    assert_eq!(load_points(&directory.join("points.png"), 10).unwrap().points, vec![Vec3::new(corner, HEIGHTMAP_HEIGHT * 10., corner)]);
+    let error = load_points(&directory.join("points.obj"), 10).unwrap_err().to_string();
+    assert!(error.ends_with("points.obj` isn't a .csv, .tsv, .ply or .png file"), "{}", error);
+    assert!(load_points(&directory.join("missing.csv"), 10).is_err());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,158 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy_graph_sim::{
+    formula_tree::spherical_coordinates,
+    parsing_function::FormulaParser,
+    point_evaluation::{grid_points, AxisFormulas, PointColumns},
+    point_layout::{PointLayout, POISSON_DISTANCE},
+};
+use glam::Vec3;
+
+fn closest_pair(points: &[Vec3]) -> f32 {
+    let mut closest = f32::INFINITY;
+    for (index, point) in points.iter().enumerate() {
+        for other in &points[index + 1..] {
+            closest = closest.min(point.distance(*other));
+        }
+    }
+    closest
+}
+
+#[test]
+fn grid_matches_the_lattice() {
+    assert_eq!(PointLayout::Grid.points([3, 2, 4], 5, 0), grid_points(3, 2, 4, 5));
+    assert_eq!(PointLayout::default(), PointLayout::Grid);
+}
+
+#[test]
+fn cylindrical_rings_have_their_radius_and_angle_as_r_and_theta() {
+    let parser = FormulaParser::new();
+    let (r, theta) = (parser.parse_tree("r").unwrap(), parser.parse_tree("theta").unwrap());
+    let points = PointLayout::Cylindrical.points([3, 1, 8], 10, 0);
+    assert_eq!(points.len(), 24);
+    for (index, point) in points.iter().enumerate() {
+        let (ring, step) = (index / 8, index % 8);
+        assert!((r.evaluate(0., *point) - (ring + 1) as f32 * 10.).abs() < 1e-4);
+        let angle = (step as f32 * std::f32::consts::TAU / 8. + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        assert!((theta.evaluate(0., *point) - angle).abs() < 1e-4, "{} at {}", angle, point);
+    }
+}
+
+/// The points of the layout, with the stored parameters, moved once by formulas that put `r`, `theta` and `phi` on
+/// x, y and z
+fn read_parameters(columns: &mut PointColumns) -> Vec<Vec3> {
+    let parser = FormulaParser::new();
+    let formulas = AxisFormulas {
+        x_func: parser.parse_formula("r"),
+        y_func: parser.parse_formula("theta"),
+        z_func: parser.parse_formula("phi"),
+    };
+    assert!(columns.evaluate_serial(&formulas, 0.).is_empty());
+    (0..columns.len()).map(|index| columns.point(index)).collect()
+}
+
+#[test]
+fn cylindrical_layers_share_their_ring_radius_and_angle() {
+    let points = PointLayout::Cylindrical.points_with_parameters([2, 3, 6], 10, 0);
+    assert_eq!(points.len(), 36);
+    assert_eq!(points.iter().map(|(point, _)| *point).collect::<Vec<Vec3>>(), PointLayout::Cylindrical.points([2, 3, 6], 10, 0));
+    let mut columns = PointColumns::default();
+    for (point, parameters) in &points {
+        columns.push_with_layout(*point, *parameters, &[]);
+    }
+    for (index, (read, (point, _))) in read_parameters(&mut columns).into_iter().zip(&points).enumerate() {
+        let (ring, step) = (index / 18, index % 6);
+        // The layers above and below the ground plane are further from the origin than their ring's radius
+        assert_eq!(read.x, (ring + 1) as f32 * 10., "{}", point);
+        let angle = (step as f32 * TAU / 6. + PI).rem_euclid(TAU) - PI;
+        assert!((read.y - angle).abs() < 1e-5, "{} at {}", angle, point);
+        assert!((read.z - spherical_coordinates(*point)[2]).abs() < 1e-5, "{}", point);
+    }
+}
+
+#[test]
+fn moved_points_keep_the_parameters_they_were_placed_with() {
+    let points = PointLayout::Spherical.points_with_parameters([2, 2, 4], 12, 0);
+    let mut columns = PointColumns::default();
+    for (point, parameters) in &points {
+        columns.push_with_layout(*point, *parameters, &[]);
+    }
+    // Iterating moves the points every step, but `r`, `theta` and `phi` stay where the layout put them
+    let parser = FormulaParser::new();
+    let drift = AxisFormulas {
+        x_func: parser.parse_formula("x + 40"),
+        y_func: parser.parse_formula("y * 2"),
+        z_func: parser.parse_formula("z - r"),
+    };
+    for _ in 0..3 {
+        assert!(columns.evaluate_serial(&drift, 0.).is_empty());
+    }
+    assert!(columns.point(0).distance(points[0].0) > 100.);
+    for (read, (point, parameters)) in read_parameters(&mut columns).into_iter().zip(&points) {
+        assert_eq!(read.to_array(), *parameters, "{}", point);
+        let expected = spherical_coordinates(*point);
+        assert!(Vec3::from(*parameters).abs_diff_eq(Vec3::from(expected), 1e-4), "{:?} should be {:?}", parameters, expected);
+    }
+
+    // Points pushed without parameters keep the spherical coordinates of where they were pushed
+    let mut columns = PointColumns::default();
+    columns.push(Vec3::new(3., 4., 0.));
+    assert!(columns.evaluate_serial(&drift, 0.).is_empty());
+    assert_eq!(columns.point(0), Vec3::new(43., 8., -5.));
+    assert_eq!(read_parameters(&mut columns)[0].x, 5.);
+}
+
+#[test]
+fn spherical_shells_cover_the_sphere_evenly() {
+    let points = PointLayout::Spherical.points([2, 10, 20], 12, 0);
+    assert_eq!(points.len(), 400);
+    let inner_radius = points[0].length();
+    assert!(points[..200].iter().all(|point| (point.length() - inner_radius).abs() < 1e-3));
+    assert!(points[200..].iter().all(|point| (point.length() - 2. * inner_radius).abs() < 1e-3));
+    // Evenly spread, so neither hemisphere gets more than its share and the points aren't bunched up
+    assert_eq!(points[..200].iter().filter(|point| point.y > 0.).count(), 100);
+    assert!(closest_pair(&points[..200]) > 8.);
+}
+
+#[test]
+fn hexagonal_points_have_six_neighbours_a_spacing_away() {
+    let points = PointLayout::Hexagonal.points([7, 1, 7], 10, 0);
+    assert_eq!(points.len(), 49);
+    assert!((closest_pair(&points) - 10.).abs() < 1e-3);
+    let centre = points.iter().copied().fold(Vec3::ZERO, |sum, point| sum + point) / 49.;
+    let middle = points.iter().min_by(|a, b| a.distance(centre).total_cmp(&b.distance(centre))).unwrap();
+    let neighbours = points.iter().filter(|point| (point.distance(*middle) - 10.).abs() < 1e-3).count();
+    assert_eq!(neighbours, 6);
+    assert!(centre.abs_diff_eq(Vec3::ZERO, 2.5));
+}
+
+#[test]
+fn random_points_fill_the_lattice_box_and_follow_the_seed() {
+    let points = PointLayout::Random.points([10, 1, 10], 12, 3);
+    assert_eq!(points.len(), 100);
+    let (min, max) = (Vec3::new(-54., 0., -54.), Vec3::new(54., 0., 54.));
+    assert!(points.iter().all(|point| point.cmpge(min).all() && point.cmple(max).all()));
+    assert_eq!(points, PointLayout::Random.points([10, 1, 10], 12, 3));
+    assert_ne!(points, PointLayout::Random.points([10, 1, 10], 12, 4));
+    assert!(PointLayout::Random.is_random() && !PointLayout::Hexagonal.is_random());
+}
+
+#[test]
+fn poisson_disk_points_keep_their_distance() {
+    let points = PointLayout::PoissonDisk.points([10, 10, 10], 12, 1);
+    assert_eq!(points.len(), 1000);
+    assert!(closest_pair(&points) >= 12. * POISSON_DISTANCE);
+    assert!(points.iter().all(|point| point.abs().max_element() <= 54.));
+    assert_eq!(points, PointLayout::PoissonDisk.points([10, 10, 10], 12, 1));
+
+    // A flat box stays flat
+    let points = PointLayout::PoissonDisk.points([25, 1, 25], 12, 1);
+    assert_eq!(points.len(), 625);
+    assert!(points.iter().all(|point| point.y == 0.));
+    assert!(closest_pair(&points) >= 12. * POISSON_DISTANCE);
+
+    // A single row has no room to spare, so it stops once the row is full
+    let points = PointLayout::PoissonDisk.points([1, 1, 100], 12, 1);
+    assert!(points.len() < 100);
+    assert!(points.iter().all(|point| point.x == 0. && point.y == 0.));
+}
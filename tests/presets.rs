@@ -0,0 +1,113 @@
+use bevy_graph_sim::{
+    point_evaluation::{AxisFormulas, PointColumns},
+    presets::{builtin_presets, categories, presets_from_ron, presets_to_ron, Preset, USER_CATEGORY},
+    scene::Scene,
+};
+use glam::Vec3;
+
+fn builtin(name: &str) -> Preset {
+    builtin_presets().into_iter().find(|preset| preset.name == name).unwrap()
+}
+
+/// Where the preset's formulas put every point of its grid at `time`
+fn plot(preset: &Preset, time: f32) -> Vec<(Vec3, Vec3)> {
+    let formulas = &preset.scene.formulas;
+    let parser = formulas.parser();
+    let formulas = AxisFormulas {
+        x_func: parser.parse_formula(&formulas.x),
+        y_func: parser.parse_formula(&formulas.y),
+        z_func: parser.parse_formula(&formulas.z),
+    };
+    let grid = &preset.scene.grid;
+    let points = grid.layout.points_with_parameters(grid.counts, grid.spacing, grid.layout_seed);
+    let mut columns = PointColumns::default();
+    for (point, parameters) in &points {
+        columns.push_with_layout(*point, *parameters, &[]);
+    }
+    assert!(columns.evaluate_serial(&formulas, time).is_empty());
+    points.iter().enumerate().map(|(index, (point, _))| (*point, columns.point(index))).collect()
+}
+
+#[test]
+fn builtins_have_unique_names_and_valid_formulas() {
+    let presets = builtin_presets();
+    for name in ["Ripples", "Saddle", "Torus", "Helix", "Lorenz Attractor", "Standing Waves", "Stretching Waves", "Möbius Strip"] {
+        assert_eq!(presets.iter().filter(|preset| preset.name == name).count(), 1, "{}", name);
+    }
+    for preset in &presets {
+        let formulas = &preset.scene.formulas;
+        let parser = formulas.parser();
+        for formula in [&formulas.x, &formulas.y, &formulas.z] {
+            assert!(parser.compile_diagnostics(formula).is_ok(), "{}: {}", preset.name, formula);
+        }
+        assert!(preset.preview().contains(&formulas.y));
+        for parameter in &formulas.parameters {
+            assert!(parameter.min <= parameter.value && parameter.value <= parameter.max, "{}: {}", preset.name, parameter.name);
+            assert!(preset.preview().contains(&format!("{} = {}", parameter.name, parameter.value)));
+        }
+    }
+    assert_eq!(categories(&presets), ["Surfaces", "Parametric", "Dynamics"]);
+}
+
+#[test]
+fn torus_points_lie_on_the_torus() {
+    for (_, point) in plot(&builtin("Torus"), 1.3) {
+        let ring_distance = Vec3::new(point.x, point.y, 0.).length() - 100.;
+        assert!((ring_distance.hypot(point.z) - 40.).abs() < 1e-2, "{}", point);
+    }
+}
+
+#[test]
+fn ripples_rise_no_higher_than_their_amplitude() {
+    let mut ripples = builtin("Ripples");
+    for amplitude in [10., 2.] {
+        ripples.scene.formulas.parameters.iter_mut().find(|parameter| parameter.name == "amplitude").unwrap().value = amplitude;
+        let heights: Vec<f32> = plot(&ripples, 0.7).iter().map(|(_, point)| point.y.abs()).collect();
+        assert!(heights.iter().all(|height| *height <= amplitude + 1e-4));
+        assert!(heights.iter().any(|height| *height > amplitude * 0.9));
+    }
+}
+
+#[test]
+fn mobius_strip_turns_half_way_round() {
+    let mut mobius = builtin("Möbius Strip");
+    // Stays a Möbius strip when the band is widened past the ring's radius or the points are spaced differently
+    for (layers, spacing) in [(7, 10), (31, 3)] {
+        mobius.scene.grid.counts[1] = layers;
+        mobius.scene.grid.spacing = spacing;
+        for (start, point) in plot(&mobius, 0.) {
+            let (across, along) = (start.y / spacing as f32 * 8., start.z.atan2(start.x));
+            let expected = Vec3::new(
+                (100. + across * (along / 2.).cos()) * along.cos(),
+                (100. + across * (along / 2.).cos()) * along.sin(),
+                across * (along / 2.).sin(),
+            );
+            assert!(point.distance(expected) < 1e-2, "{} should be {}", point, expected);
+        }
+    }
+}
+
+#[test]
+fn user_presets_round_trip_through_ron() {
+    let mut scene = Scene::default();
+    scene.formulas.x = "x + sin(time)".to_string();
+    let presets = vec![
+        Preset {
+            name: "Wobble".to_string(),
+            category: USER_CATEGORY.to_string(),
+            description: "Sways from side to side".to_string(),
+            scene,
+        },
+        builtin("Lorenz Attractor"),
+    ];
+    assert_eq!(presets_from_ron(&presets_to_ron(&presets).unwrap()).unwrap(), presets);
+}
+
+#[test]
+fn hand_written_presets_default_to_the_user_category() {
+    let presets = presets_from_ron("[(name: \"Flat\", scene: (version: 1))]").unwrap();
+    assert_eq!(presets[0].category, USER_CATEGORY);
+    assert_eq!(presets[0].description, "");
+    assert_eq!(presets[0].scene, Scene::default());
+    assert!(presets_from_ron("[(scene: (version: 1))]").unwrap_err().to_string().starts_with("Invalid presets"));
+}
@@ -0,0 +1,140 @@
+use bevy_graph_sim::{
+    point_evaluation::SimulationMode,
+    point_layout::PointLayout,
+    scene::{check_parameter_name, CameraSettings, GridSettings, Parameter, Scene, SceneError, SCENE_VERSION},
+};
+use glam::{Quat, Vec3};
+
+#[test]
+fn round_trips_through_ron() {
+    let scene = Scene {
+        grid: GridSettings {
+            layout: PointLayout::Hexagonal,
+            counts: [3, 4, 5],
+            spacing: 7,
+            sphere_radius: 2.5,
+            sphere_subdivisions: 3,
+            layout_seed: 9,
+            point_file: Some("scans/bunny.ply".into()),
+        },
+        mode: SimulationMode::Plot,
+        sphere_color: [0.25, 0.5, 0.75, 1.],
+        camera: CameraSettings::looking_at(Vec3::new(10., -20., 30.), Vec3::new(1., 2., 3.)),
+        ..Scene::default()
+    };
+    assert_eq!(Scene::from_ron(&scene.to_ron().unwrap()).unwrap(), scene);
+}
+
+#[test]
+fn missing_sections_keep_defaults() {
+    let scene = Scene::from_ron("(version: 1, formulas: (y: \"sin(x)\"))").unwrap();
+    assert_eq!(scene.formulas.y, "sin(x)");
+    assert_eq!(scene.formulas.x, "x");
+    assert_eq!(scene.grid, GridSettings::default());
+    assert_eq!(scene.camera, CameraSettings::default());
+
+    // Scenes saved before sphere subdivisions could be set keep the old mesh
+    let scene = Scene::from_ron("(version: 1, grid: (counts: (2, 2, 2), spacing: 5, sphere_radius: 1))").unwrap();
+    assert_eq!(scene.grid.sphere_subdivisions, 6);
+}
+
+#[test]
+fn default_camera_looks_at_origin() {
+    let camera = CameraSettings::default();
+    let rotation = Quat::from_array(camera.rotation);
+    let position = Vec3::from(camera.focus) + rotation * Vec3::new(0., 0., camera.radius);
+    assert!(position.abs_diff_eq(Vec3::new(350., 250., 350.), 1e-3));
+    // Upright, so the camera's right is level with the ground
+    assert!((rotation * Vec3::X).y.abs() < 1e-6);
+}
+
+#[test]
+fn refuses_newer_and_unknown_versions() {
+    let newer = format!("(version: {})", SCENE_VERSION + 1);
+    assert!(matches!(Scene::from_ron(&newer), Err(SceneError::NewerVersion(version)) if version == SCENE_VERSION + 1));
+    assert!(matches!(Scene::from_ron("(version: 0)"), Err(SceneError::UnsupportedVersion(0))));
+    assert_eq!(
+        Scene::from_ron(&newer).unwrap_err().to_string(),
+        format!("Scene version {} was saved by a newer version of the app, which reads up to version {}", SCENE_VERSION + 1, SCENE_VERSION),
+    );
+}
+
+#[test]
+fn scenes_without_a_version_are_read_as_the_current_one() {
+    let scene = Scene::from_ron("(grid: (spacing: 3))").unwrap();
+    assert_eq!(scene.version, SCENE_VERSION);
+    assert_eq!(scene.grid.spacing, 3);
+    assert_eq!(Scene::from_ron("()").unwrap(), Scene::default());
+    assert!(matches!(Scene::from_ron("(version: 1, grid: (spacing: \"wide\"))"), Err(SceneError::Format(_))));
+}
+
+#[test]
+fn parameters_keep_their_slider_ranges() {
+    let mut scene = Scene::default();
+    scene.formulas.y = "height * sin(x - time)".to_string();
+    scene.formulas.parameters = vec![Parameter::new("height", 12.5, -5., 40.)];
+    assert_eq!(Scene::from_ron(&scene.to_ron().unwrap()).unwrap(), scene);
+
+    let parser = scene.formulas.parser();
+    assert_eq!(parser.parameters, vec![("height".to_string(), 12.5)]);
+    assert_eq!(parser.parse_tree(&scene.formulas.y).unwrap().evaluate(0., Vec3::new(std::f32::consts::FRAC_PI_2, 0., 0.)), 12.5);
+
+    let scene = Scene::from_ron("(formulas: (parameters: [(name: \"speed\", max: 4)]))").unwrap();
+    assert_eq!(scene.formulas.parameters, vec![Parameter::new("speed", 0., 0., 4.)]);
+}
+
+#[test]
+fn parameters_need_usable_names_and_ranges() {
+    let taken = [Parameter::new("speed", 1., 0., 10.)];
+    assert!(check_parameter_name("height", &taken).is_ok());
+    assert_eq!(check_parameter_name("Speed", &taken).unwrap_err(), "There is already a parameter called `Speed`");
+    assert_eq!(check_parameter_name("theta", &taken).unwrap_err(), "`theta` is already a built-in variable or function");
+    assert_eq!(check_parameter_name("sin", &taken).unwrap_err(), "`sin` is already a built-in variable or function");
+    assert!(check_parameter_name("2fast", &taken).unwrap_err().contains("isn't a name formulas can use"));
+
+    let error = |text: &str| Scene::from_ron(text).unwrap_err().to_string();
+    assert_eq!(
+        error("(formulas: (parameters: [(name: \"a\"), (name: \"A\")]))"),
+        "Invalid scene: There is already a parameter called `A`",
+    );
+    assert_eq!(
+        error("(formulas: (parameters: [(name: \"a\", min: 2, max: 1)]))"),
+        "Invalid scene: parameter `a` runs from 2 to 1, which must be finite with the minimum no higher than the maximum",
+    );
+}
+
+#[test]
+fn reloading_keeps_broken_formulas_on_the_reloaded_parameters() {
+    let before = Scene::from_ron("(formulas: (y: \"height * x\", parameters: [(name: \"height\", value: 1, max: 10)]))").unwrap();
+    let mut parser = before.formulas.parser();
+    let previous = [before.formulas.x.as_str(), before.formulas.y.as_str(), before.formulas.z.as_str()];
+
+    let after = Scene::from_ron("(formulas: (y: \"height * (x\", parameters: [(name: \"height\", value: 3, max: 10)]))").unwrap();
+    after.formulas.apply_to(&mut parser);
+    let texts = [Some(after.formulas.x.clone()), Some(after.formulas.y.clone()), Some(after.formulas.z.clone())];
+    let reload = parser.reload_formulas(previous, texts);
+    assert_eq!(reload.texts[1], "height * x");
+    assert_eq!(reload.errors.len(), 1);
+    assert!(reload.errors[0].starts_with("Y formula: "));
+    // The kept formula reads the new value the slider shows, not the one it was first compiled with
+    assert_eq!((reload.formulas[1].func)(0., Vec3::new(2., 0., 0.)), Ok(6.));
+
+    // Renaming the parameter leaves the kept formula nothing to read, which is reported rather than hidden
+    let renamed = Scene::from_ron("(formulas: (y: \"amplitude * (x\", parameters: [(name: \"amplitude\", value: 3)]))").unwrap();
+    renamed.formulas.apply_to(&mut parser);
+    let reload = parser.reload_formulas(previous, [None, Some(renamed.formulas.y.clone()), None]);
+    assert_eq!(reload.texts[1], "height * x");
+    assert!(reload.formulas[1].program.is_none());
+    assert!(reload.errors.iter().any(|error| error.starts_with("Y formula: the previous formula doesn't compile either: ")));
+}
+
+#[cfg(unix)]
+#[test]
+fn point_files_without_utf8_paths_are_reported_instead_of_written() {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
+
+    let mut scene = Scene::default();
+    scene.grid.point_file = Some(PathBuf::from(OsString::from_vec(b"f\xff.csv".to_vec())));
+    assert!(matches!(scene.to_ron(), Err(SceneError::Serialize(_))));
+    assert!(scene.to_ron().unwrap_err().to_string().starts_with("Couldn't write the scene"));
+}
@@ -0,0 +1,38 @@
+use bevy_graph_sim::{scene::Scene, settings::Settings, storage};
+
+#[test]
+fn round_trips_through_ron() {
+    let mut settings = Settings {
+        window_size: [640., 480.],
+        options_window_position: Some([20., 30.]),
+        ..Settings::default()
+    };
+    settings.scene.formulas.y = "cos(z - time)".to_string();
+    assert_eq!(Settings::from_ron(&settings.to_ron().unwrap()).unwrap(), settings);
+}
+
+#[test]
+fn missing_fields_keep_defaults() {
+    let settings = Settings::from_ron("(window_size: (900, 700))").unwrap();
+    assert_eq!(settings.window_size, [900., 700.]);
+    assert_eq!(settings.options_window_position, None);
+    assert_eq!(settings.scene, Scene::default());
+    assert!(Settings::from_ron("(window_size: \"big\")").unwrap_err().to_string().starts_with("Invalid settings"));
+}
+
+#[test]
+fn stored_in_the_config_directory() {
+    if let Some(path) = storage::path("settings") {
+        assert!(path.ends_with("bevy_graph_sim/settings.ron"));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn point_files_without_utf8_paths_are_reported_instead_of_written() {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
+
+    let mut settings = Settings::default();
+    settings.scene.grid.point_file = Some(PathBuf::from(OsString::from_vec(b"f\xff.csv".to_vec())));
+    assert!(settings.to_ron().unwrap_err().to_string().starts_with("Couldn't write settings"));
+}
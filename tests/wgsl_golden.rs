@@ -0,0 +1,98 @@
+//! Golden-file tests for the WGSL generator.
+//! Run with `UPDATE_GOLDEN=1` to rewrite the expected files after an intentional change to the output.
+
+use std::{env, fs, path::PathBuf};
+
+use bevy_graph_sim::{
+    formula_wgsl::{generate_function, generate_module, UniformField, UniformLayout},
+    parsing_function::FormulaParser,
+};
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/wgsl").join(format!("{}.wgsl", name))
+}
+
+fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Could not read golden file {}: {}", path.display(), err));
+    assert_eq!(expected, actual, "Generated WGSL for '{}' does not match {}", name, path.display());
+}
+
+#[test]
+fn formula_functions_match_golden_files() {
+    let parser = FormulaParser::new();
+    let formulas = [
+        ("identity", "x"),
+        ("ripple", "sin(x - time) * 10"),
+        ("precedence", "1 - (2 - x) / (y * 3) + z"),
+        ("exponent", "2 ^ x ^ 2 * (y + 1) ^ 2"),
+        ("nested_functions", "abs(sin(x / time * 25) * 25) + cos(tan(z))"),
+        ("layout", "r * cos(theta + time) - phi"),
+        ("noise", "fbm(x / 50, time, z / 50, 4) * 20 + worley(x, y, z) - hash(x, 0, z) * rand(3)"),
+    ];
+    for (name, formula) in formulas {
+        let expr = parser.parse_tree(formula).unwrap();
+        assert_golden(name, &generate_function(&format!("formula_{}", name), &expr));
+    }
+}
+
+#[test]
+fn non_finite_constants_are_built_from_their_bits() {
+    let expr = FormulaParser::new().parse_tree("x + 1 / 0 * y - 0 / 0 + (0 - 1 / 0)").unwrap().simplify();
+    assert_golden("non_finite", &generate_function("formula_non_finite", &expr));
+}
+
+#[test]
+fn columns_are_prefixed_parameters() {
+    // `loop` is a WGSL keyword and `sign` a builtin function, neither usable as a parameter name
+    let parser = FormulaParser { columns: vec!["sign".to_string(), "loop".to_string(), "height".to_string()], ..FormulaParser::default() };
+    let expr = parser.parse_tree("height * 2 + loop - sign").unwrap();
+    assert_golden("columns", &generate_function("formula_columns", &expr));
+}
+
+#[test]
+fn module_matches_golden_file() {
+    let parser = FormulaParser::new();
+    let x = parser.parse_tree("x").unwrap();
+    let y = parser.parse_tree("sin(x - time) * 10").unwrap();
+    let z = parser.parse_tree("z").unwrap();
+    let source = generate_module(&UniformLayout::new(), &[("formula_x", &x), ("formula_y", &y), ("formula_z", &z)]);
+    assert_golden("module", &source);
+}
+
+#[test]
+fn noise_module_matches_golden_file() {
+    let parser = FormulaParser::new();
+    let y = parser.parse_tree("perlin(x / 30, time, z / 30) * 25 + simplex(x, y, z)").unwrap();
+    let source = generate_module(&UniformLayout::new(), &[("formula_y", &y)]);
+    assert_golden("noise_module", &source);
+}
+
+#[test]
+fn parameter_module_matches_golden_file() {
+    let parser = FormulaParser {
+        parameters: vec![("amplitude".to_string(), 10.), ("speed".to_string(), 3.)],
+        ..FormulaParser::default()
+    };
+    let y = parser.parse_tree("amplitude * sin(x - time * speed)").unwrap();
+    let source = generate_module(&UniformLayout::with_parameters(&["amplitude", "speed"]), &[("formula_y", &y)]);
+    assert_golden("parameter_module", &source);
+}
+
+#[test]
+fn uniform_layout_is_padded_to_sixteen_bytes() {
+    let layout = UniformLayout::new();
+    assert_eq!(layout.fields, vec![UniformField { name: "time".to_string(), offset: 0 }]);
+    assert_eq!(layout.size, 16);
+
+    let layout = UniformLayout::with_parameters(&["a", "b", "c", "d"]);
+    let names: Vec<&str> = layout.fields.iter().map(|field| field.name.as_str()).collect();
+    assert_eq!(names, vec!["time", "param_a", "param_b", "param_c", "param_d"]);
+    assert_eq!(layout.fields[4].offset, 16);
+    assert_eq!(layout.size, 32);
+}